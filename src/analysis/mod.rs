@@ -0,0 +1,180 @@
+use chess::{Board, BoardStatus, ChessMove, Color};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::evaluation::{Score, CHECKMATE_VALUE};
+use crate::notation::san_line;
+use crate::strategies::{search, SearchLimits};
+
+/// How deep analysis goes if nobody stops it first.
+const MAX_DEPTH: u8 = 64;
+
+/// What the search has found so far about the analysed position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalysisLine {
+    pub depth: u8,
+
+    /// From White's point of view, whoever is to move.
+    pub score: Score,
+
+    pub nodes_per_second: u64,
+
+    /// The principal variation, in SAN.
+    pub pv: Vec<String>,
+
+    /// The first move of the principal variation.
+    pub best_move: Option<ChessMove>,
+}
+
+impl AnalysisLine {
+    /// The score in pawns, like `+1.25`, or `#` for mates.
+    pub fn score_text(&self) -> String {
+        if self.score >= CHECKMATE_VALUE {
+            "+#".to_string()
+        } else if self.score <= -CHECKMATE_VALUE {
+            "-#".to_string()
+        } else {
+            format!("{:+.2}", self.score as f64 / 100.0)
+        }
+    }
+
+    /// How likely White is to win, from 0 to 1, going by the score. This is
+    /// how full an evaluation bar is.
+    pub fn white_share(&self) -> f32 {
+        let pawns = self.score.clamp(-CHECKMATE_VALUE, CHECKMATE_VALUE) as f32 / 100.0;
+        1.0 / (1.0 + 10f32.powf(-pawns / 4.0))
+    }
+}
+
+/// patzer's search running in the background on one position, until it's
+/// stopped or dropped. Each completed iteration replaces the latest line.
+pub struct Analysis {
+    position: Board,
+    stop: Arc<AtomicBool>,
+    latest: Arc<Mutex<Option<AnalysisLine>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Analysis {
+    pub fn start(position: Board) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let latest = Arc::new(Mutex::new(None));
+
+        // there's nothing to look for once the game is over
+        let handle = (position.status() == BoardStatus::Ongoing).then(|| {
+            let stop = stop.clone();
+            let latest = latest.clone();
+
+            thread::spawn(move || {
+                let limits = SearchLimits {
+                    depth: MAX_DEPTH,
+                    move_time: None,
+                    stop: Some(&*stop),
+                    ..Default::default()
+                };
+
+                search(&position, &limits, None, |info| {
+                    let score = match position.side_to_move() {
+                        Color::White => info.score,
+                        Color::Black => -info.score,
+                    };
+                    let seconds = info.elapsed.max(Duration::from_millis(1)).as_secs_f64();
+
+                    *latest.lock().unwrap() = Some(AnalysisLine {
+                        depth: info.depth,
+                        score,
+                        nodes_per_second: (info.nodes as f64 / seconds) as u64,
+                        pv: san_line(&position, &info.pv),
+                        best_move: info.pv.first().copied(),
+                    });
+                });
+            })
+        });
+
+        Analysis {
+            position,
+            stop,
+            latest,
+            handle,
+        }
+    }
+
+    pub fn position(&self) -> Board {
+        self.position
+    }
+
+    pub fn latest(&self) -> Option<AnalysisLine> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// Whether the search is still going.
+    pub fn is_running(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        // the search checks for stopping often, so this doesn't hold up the UI
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Instant;
+
+    fn wait_for(analysis: &Analysis, depth: u8) -> AnalysisLine {
+        let start = Instant::now();
+        loop {
+            match analysis.latest() {
+                Some(line) if line.depth >= depth => return line,
+                _ if start.elapsed() > Duration::from_secs(10) => panic!("analysis stalled"),
+                _ => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    }
+
+    #[test]
+    fn finds_mates_for_black() {
+        let position = Board::from_str("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        let analysis = Analysis::start(position);
+
+        let line = wait_for(&analysis, 2);
+        assert!(line.score <= -CHECKMATE_VALUE, "{:?}", line);
+        assert_eq!(line.score_text(), "-#");
+        assert!(line.white_share() < 0.01);
+    }
+
+    #[test]
+    fn shows_scores_in_pawns() {
+        let line = AnalysisLine {
+            depth: 3,
+            score: 125,
+            nodes_per_second: 1000,
+            pv: vec!["e4".to_string()],
+            best_move: None,
+        };
+        assert_eq!(line.score_text(), "+1.25");
+        assert!(line.white_share() > 0.5);
+    }
+
+    #[test]
+    fn does_not_analyse_finished_games() {
+        let mate = Board::from_str("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let analysis = Analysis::start(mate);
+
+        assert!(!analysis.is_running());
+        assert_eq!(analysis.latest(), None);
+    }
+}
@@ -0,0 +1,214 @@
+use chess::Square;
+use std::fmt;
+use std::str::FromStr;
+
+/// The colors arrows and marked squares are drawn in. PGN comments name them
+/// by their first letters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Brush {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl Brush {
+    pub const ALL: [Brush; 4] = [Brush::Green, Brush::Red, Brush::Yellow, Brush::Blue];
+
+    /// The brush picked by the modifier keys held while drawing, as on
+    /// lichess: shift for red, alt for blue and both for yellow.
+    pub fn for_modifiers(shift: bool, alt: bool) -> Self {
+        match (shift, alt) {
+            (false, false) => Brush::Green,
+            (true, false) => Brush::Red,
+            (false, true) => Brush::Blue,
+            (true, true) => Brush::Yellow,
+        }
+    }
+
+    /// Where the brush's color is in `Theme::arrows`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Brush::Green => 'G',
+            Brush::Red => 'R',
+            Brush::Yellow => 'Y',
+            Brush::Blue => 'B',
+        }
+    }
+
+    fn from_letter(letter: char) -> Option<Self> {
+        Brush::ALL
+            .into_iter()
+            .find(|brush| brush.letter() == letter)
+    }
+}
+
+/// Arrows and marked squares drawn on one position.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotations {
+    pub squares: Vec<(Square, Brush)>,
+    pub arrows: Vec<(Square, Square, Brush)>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.squares.is_empty() && self.arrows.is_empty()
+    }
+
+    /// Marks `square` with `brush`. Marking it again with the same brush
+    /// rubs the mark out, and with another brush repaints it.
+    pub fn toggle_square(&mut self, square: Square, brush: Brush) {
+        match self.squares.iter().position(|&(s, _)| s == square) {
+            Some(i) if self.squares[i].1 == brush => {
+                self.squares.remove(i);
+            }
+            Some(i) => self.squares[i].1 = brush,
+            None => self.squares.push((square, brush)),
+        }
+    }
+
+    /// Draws an arrow, rubbing it out or repainting it like `toggle_square`.
+    pub fn toggle_arrow(&mut self, from: Square, to: Square, brush: Brush) {
+        match self
+            .arrows
+            .iter()
+            .position(|&(f, t, _)| (f, t) == (from, to))
+        {
+            Some(i) if self.arrows[i].2 == brush => {
+                self.arrows.remove(i);
+            }
+            Some(i) => self.arrows[i].2 = brush,
+            None => self.arrows.push((from, to, brush)),
+        }
+    }
+
+    /// Reads the `[%csl]` and `[%cal]` commands out of a PGN comment,
+    /// ignoring the rest of the comment and anything in them which can't be
+    /// read.
+    pub fn from_comment(comment: &str) -> Self {
+        let mut annotations = Annotations::default();
+
+        let commands = comment
+            .split('[')
+            .filter_map(|c| c.split_once(']').map(|(command, _)| command));
+
+        for command in commands {
+            let mut words = command.split_whitespace();
+            let (name, items) = match (words.next(), words.next()) {
+                (Some(name), Some(items)) => (name, items),
+                _ => continue,
+            };
+
+            for item in items.split(',') {
+                let mut chars = item.chars();
+                let brush = match chars.next().and_then(Brush::from_letter) {
+                    Some(brush) => brush,
+                    None => continue,
+                };
+                let squares = chars.as_str();
+
+                match name {
+                    // Square::from_str ignores anything after the square
+                    "%csl" if squares.len() == 2 => {
+                        if let Ok(square) = Square::from_str(squares) {
+                            annotations.squares.push((square, brush));
+                        }
+                    }
+                    "%cal" if squares.len() == 4 && squares.is_ascii() => {
+                        let from = Square::from_str(&squares[..2]);
+                        let to = Square::from_str(&squares[2..]);
+                        if let (Ok(from), Ok(to)) = (from, to) {
+                            annotations.arrows.push((from, to, brush));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        annotations
+    }
+}
+
+/// The commands for a PGN comment, like `[%csl Rd4][%cal Ge2e4,Bg1f3]`.
+impl fmt::Display for Annotations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.squares.is_empty() {
+            let squares: Vec<String> = self
+                .squares
+                .iter()
+                .map(|(square, brush)| format!("{}{}", brush.letter(), square))
+                .collect();
+            write!(f, "[%csl {}]", squares.join(","))?;
+        }
+
+        if !self.arrows.is_empty() {
+            let arrows: Vec<String> = self
+                .arrows
+                .iter()
+                .map(|(from, to, brush)| format!("{}{}{}", brush.letter(), from, to))
+                .collect();
+            write!(f, "[%cal {}]", arrows.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Square {
+        Square::from_str(name).unwrap()
+    }
+
+    #[test]
+    fn toggles_marks() {
+        let mut annotations = Annotations::default();
+        annotations.toggle_square(square("d4"), Brush::Green);
+        annotations.toggle_square(square("d4"), Brush::Red);
+        assert_eq!(annotations.squares, vec![(square("d4"), Brush::Red)]);
+        annotations.toggle_square(square("d4"), Brush::Red);
+        assert!(annotations.is_empty());
+
+        annotations.toggle_arrow(square("e2"), square("e4"), Brush::Blue);
+        annotations.toggle_arrow(square("e4"), square("e2"), Brush::Blue);
+        assert_eq!(annotations.arrows.len(), 2);
+        annotations.toggle_arrow(square("e2"), square("e4"), Brush::Blue);
+        assert_eq!(
+            annotations.arrows,
+            vec![(square("e4"), square("e2"), Brush::Blue)]
+        );
+    }
+
+    #[test]
+    fn round_trips_through_comments() {
+        let annotations = Annotations {
+            squares: vec![(square("d4"), Brush::Red), (square("h8"), Brush::Yellow)],
+            arrows: vec![
+                (square("e2"), square("e4"), Brush::Green),
+                (square("g1"), square("f3"), Brush::Blue),
+            ],
+        };
+
+        let comment = annotations.to_string();
+        assert_eq!(comment, "[%csl Rd4,Yh8][%cal Ge2e4,Bg1f3]");
+        assert_eq!(Annotations::from_comment(&comment), annotations);
+    }
+
+    #[test]
+    fn reads_annotations_among_other_comments() {
+        let annotations =
+            Annotations::from_comment("a strong move [%clk 0:01:00] [%cal Ra1a8,Xb1b2,Gz9] [%csl]");
+        assert_eq!(
+            annotations.arrows,
+            vec![(square("a1"), square("a8"), Brush::Red)]
+        );
+        assert!(annotations.squares.is_empty());
+    }
+}
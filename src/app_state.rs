@@ -1,40 +1,98 @@
+use crate::analysis::{Analysis, AnalysisLine};
+use crate::annotation::{Annotations, Brush};
+use crate::clock::{Clock, TimeControl};
+use crate::controller::{Event, GameController, Notifier};
+use crate::describe;
+use crate::engine_config::{EngineConfig, Strategy};
 use crate::game_state::GameState;
-use crate::player::{MoveFunction, Player};
-use crate::strategies::alpha_beta;
-use crate::ui_state::UiState;
-use chess::{Board, ChessMove, Color, File, Game, GameResult, Piece, Rank, Square};
+use crate::notation;
+use crate::pgn::PgnGame;
+use crate::player::{Decision, HumanPlayer, Player};
+use crate::review::{Review, ReviewReport, REVIEW_DEPTH};
+use crate::settings::{Session, Settings};
+use crate::setup::Setup;
+use crate::tablebase::Tablebase;
+use crate::theme::Theme;
+use crate::ui_state::{coords_of, square_at, Coords, UiState};
+use crate::widget::PieceSet;
+use chess::{Board, ChessMove, Color, Game, MoveGen, Piece, Square};
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub struct AppState {
     game_state: Arc<Mutex<GameState>>,
     ui_state: Arc<Mutex<UiState>>,
 
-    white: Arc<Mutex<Player>>,
-    black: Arc<Mutex<Player>>,
+    white: Arc<Mutex<Arc<dyn Player>>>,
+    black: Arc<Mutex<Arc<dyn Player>>>,
 
-    handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    controller: GameController,
+
+    tablebase: Option<Arc<Tablebase>>,
+
+    /// How each side's computer player was configured, indexed by
+    /// `Color::to_index`, or `None` for humans.
+    configs: Arc<Mutex<[Option<EngineConfig>; 2]>>,
+
+    /// The background search behind the analysis panel, if it's running.
+    analysis: Arc<Mutex<Option<Analysis>>>,
+
+    /// The review of the finished game, if one's been asked for.
+    review: Arc<Mutex<Option<Review>>>,
+
+    /// The built-in themes, followed by the user's.
+    themes: Arc<Mutex<Vec<Theme>>>,
 }
 
 impl AppState {
     pub fn new() -> Self {
-        let white = Player::Human("Human".into());
-        let black = Player::Computer(
-            "Negamax alpha-beta ".into(),
-            Arc::new(Box::new(|g| alpha_beta(&g.current_position(), 6))),
-        );
+        let tablebase = Tablebase::from_env().map(Arc::new);
+
+        let white: Arc<dyn Player> = Arc::new(HumanPlayer::new("Human"));
+        let black_config = EngineConfig::new(Strategy::AlphaBeta);
+        let black = black_config
+            .build(tablebase.clone())
+            .expect("built-in players don't fail to start");
+
+        let game_state = Arc::new(Mutex::new(GameState::new(white.name(), black.name())));
+        let white = Arc::new(Mutex::new(white));
+        let black = Arc::new(Mutex::new(black));
+        let controller = GameController::spawn(game_state.clone(), white.clone(), black.clone());
 
         AppState {
-            game_state: Arc::new(Mutex::new(GameState::new(white.name(), black.name()))),
+            game_state,
             ui_state: Arc::new(Mutex::new(UiState::default())),
-            white: Arc::new(Mutex::new(white)),
-            black: Arc::new(Mutex::new(black)),
-            handles: Arc::new(Mutex::new(vec![])),
+            white,
+            black,
+            controller,
+            tablebase,
+            configs: Arc::new(Mutex::new([None, Some(black_config)])),
+            analysis: Arc::new(Mutex::new(None)),
+            review: Arc::new(Mutex::new(None)),
+            themes: Arc::new(Mutex::new(Theme::builtin())),
         }
     }
 
+    /// Sets what to call when the game changes, so the UI knows to redraw.
+    pub fn set_notifier(&self, notifier: Notifier) {
+        self.controller.set_notifier(notifier);
+    }
+
+    /// Stops the game controller, for when the app is closing.
+    pub fn shutdown(&self) {
+        self.controller.send(Event::Shutdown);
+        *self.analysis.lock().unwrap() = None;
+        *self.review.lock().unwrap() = None;
+    }
+
+    pub fn tablebase(&self) -> Option<Arc<Tablebase>> {
+        self.tablebase.clone()
+    }
+
     pub fn game(&self) -> Game {
         self.game_state.lock().unwrap().game.clone()
     }
@@ -44,40 +102,222 @@ impl AppState {
     }
 
     pub fn is_finished(&self) -> bool {
-        self.game_state.lock().unwrap().game.result().is_some()
+        let mut game_state = self.game_state.lock().unwrap();
+        game_state.check_flag();
+        game_state.is_finished()
     }
 
     pub fn start_game(&self) {
-        self.game_state.lock().unwrap().started = true;
-        self.start_computer_players();
+        self.game_state.lock().unwrap().start();
+        self.controller.send(Event::Refresh);
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.game_state.lock().unwrap().clock.clone()
+    }
+
+    /// Changes the time control, if the game hasn't started yet.
+    pub fn set_time_control(&self, time_control: TimeControl) {
+        let mut game_state = self.game_state.lock().unwrap();
+        if !game_state.started {
+            game_state.set_time_control(time_control);
+        }
     }
 
     pub fn status_message(&self) -> &'static str {
-        let game_state = self.game_state.lock().unwrap();
+        if self.setup().is_some() {
+            return "Setting up a position";
+        }
+
+        let mut game_state = self.game_state.lock().unwrap();
+        game_state.check_flag();
 
         if !game_state.started {
             "Not started"
-        } else if let Some(result) = game_state.game.result() {
-            match result {
-                GameResult::WhiteCheckmates => "White wins (checkmate)",
-                GameResult::BlackResigns => "White wins (black resigned)",
-                GameResult::BlackCheckmates => "Black wins (checkmate)",
-                GameResult::WhiteResigns => "Black wins (white resigned)",
-                GameResult::Stalemate => "Draw by stalemate",
-                GameResult::DrawDeclared => "Draw declared",
-                GameResult::DrawAccepted => "Draw by agreement",
-            }
+        } else if let Some(message) = game_state.result_message() {
+            message
         } else {
             "In progress"
         }
     }
 
-    pub fn set_white_player(&self, player: Player) {
+    pub fn set_white_player(&self, player: Arc<dyn Player>) {
         (*self.white.lock().unwrap()) = player;
+        self.configs.lock().unwrap()[Color::White.to_index()] = None;
+        self.orient_board();
+        self.controller.send(Event::PlayersChanged);
     }
 
-    pub fn set_black_player(&self, player: Player) {
+    pub fn set_black_player(&self, player: Arc<dyn Player>) {
         (*self.black.lock().unwrap()) = player;
+        self.configs.lock().unwrap()[Color::Black.to_index()] = None;
+        self.orient_board();
+        self.controller.send(Event::PlayersChanged);
+    }
+
+    /// Builds a computer player from `config` to play `color`, which starts
+    /// the engine for UCI engines.
+    pub fn configure_player(&self, color: Color, config: EngineConfig) -> io::Result<()> {
+        let player = config.build(self.tablebase())?;
+        match color {
+            Color::White => self.set_white_player(player),
+            Color::Black => self.set_black_player(player),
+        }
+        self.configs.lock().unwrap()[color.to_index()] = Some(config);
+
+        Ok(())
+    }
+
+    /// How `color`'s computer player was configured, if it's one which was
+    /// built from settings.
+    pub fn player_config(&self, color: Color) -> Option<EngineConfig> {
+        self.configs.lock().unwrap()[color.to_index()].clone()
+    }
+
+    /// Turns the board so a lone human player has their pieces at the
+    /// bottom. With no humans, or two, it stays the way it was.
+    fn orient_board(&self) {
+        let white = self.is_human(Color::White);
+        let black = self.is_human(Color::Black);
+
+        if white != black {
+            self.ui_state.lock().unwrap().flipped = black;
+        }
+    }
+
+    pub fn board_flipped(&self) -> bool {
+        self.ui_state.lock().unwrap().flipped
+    }
+
+    pub fn piece_set(&self) -> PieceSet {
+        self.ui_state.lock().unwrap().piece_set
+    }
+
+    pub fn set_piece_set(&self, piece_set: PieceSet) {
+        self.ui_state.lock().unwrap().piece_set = piece_set;
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.ui_state.lock().unwrap().theme.clone()
+    }
+
+    pub fn set_theme(&self, theme: Theme) {
+        self.ui_state.lock().unwrap().theme = theme;
+    }
+
+    pub fn themes(&self) -> Vec<Theme> {
+        self.themes.lock().unwrap().clone()
+    }
+
+    /// Reads the user's themes file again, if they have one, replacing the
+    /// user themes read before. The theme in use is updated if it's one of
+    /// them, so themes can be tweaked while patzer is running.
+    pub fn load_user_themes(&self) -> Result<(), String> {
+        let user_themes = match Theme::user_themes_path() {
+            Some(path) if path.exists() => Theme::load_user_themes(&path)?,
+            _ => vec![],
+        };
+
+        let mut themes = Theme::builtin();
+        themes.extend(user_themes);
+
+        let mut ui_state = self.ui_state.lock().unwrap();
+        if let Some(theme) = find_theme(&themes, &ui_state.theme.name) {
+            ui_state.theme = theme;
+        }
+        *self.themes.lock().unwrap() = themes;
+
+        Ok(())
+    }
+
+    /// The players and how the board is shown, to remember for next time.
+    /// The window size isn't known here, so it's left out.
+    pub fn settings(&self) -> Settings {
+        let [white, black] = self.configs.lock().unwrap().clone();
+        let ui_state = self.ui_state.lock().unwrap();
+
+        Settings {
+            white,
+            black,
+            flipped: ui_state.flipped,
+            theme: ui_state.theme.name.clone(),
+            piece_set: ui_state.piece_set,
+            window_size: None,
+        }
+    }
+
+    /// Sets up the players and the board as `settings` say. Engines which
+    /// won't start are left as they were, and the errors returned.
+    pub fn apply_settings(&self, settings: &Settings) -> Result<(), String> {
+        let mut errors = vec![];
+        for (color, config) in [
+            (Color::White, &settings.white),
+            (Color::Black, &settings.black),
+        ] {
+            match config {
+                Some(config) => {
+                    if let Err(e) = self.configure_player(color, config.clone()) {
+                        errors.push(format!("Could not start {}: {}", config, e));
+                    }
+                }
+                None => {
+                    let human = Arc::new(HumanPlayer::new("Human"));
+                    match color {
+                        Color::White => self.set_white_player(human),
+                        Color::Black => self.set_black_player(human),
+                    }
+                }
+            }
+        }
+
+        let theme = find_theme(&self.themes(), &settings.theme);
+        if theme.is_none() {
+            errors.push(format!("There's no theme called {}", settings.theme));
+        }
+
+        // after the players, which turn the board to face a lone human
+        let mut ui_state = self.ui_state.lock().unwrap();
+        ui_state.flipped = settings.flipped;
+        ui_state.theme = theme.unwrap_or_default();
+        ui_state.piece_set = settings.piece_set;
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// The game being played, if there's one to come back to.
+    pub fn session(&self) -> Option<Session> {
+        let game_state = self.game_state.lock().unwrap();
+        if !game_state.started || game_state.is_finished() {
+            return None;
+        }
+
+        Some(Session {
+            start_position: game_state.start_position,
+            start_halfmove_clock: game_state.start_halfmove_clock,
+            moves: game_state.moves(),
+        })
+    }
+
+    /// Carries on with a saved game, with the current players and time
+    /// control.
+    pub fn restore_session(&self, session: &Session) {
+        self.reset_to(session.start_position, session.start_halfmove_clock);
+        {
+            let mut game_state = self.game_state.lock().unwrap();
+            for &m in &session.moves {
+                game_state.make_move(m);
+            }
+        }
+        self.start_game();
+    }
+
+    pub fn flip_board(&self) {
+        let mut ui_state = self.ui_state.lock().unwrap();
+        ui_state.flipped = !ui_state.flipped;
     }
 
     pub fn player_names(&self) -> (String, String) {
@@ -86,29 +326,66 @@ impl AppState {
         (white, black)
     }
 
-    pub fn declare_draw(&self, color: Color) -> bool {
-        let mut game_state = self.game_state.lock().unwrap();
+    /// What computer players last reported about their searches.
+    pub fn engine_info(&self) -> (Option<String>, Option<String>) {
+        let white = self.white.lock().unwrap().info();
+        let black = self.black.lock().unwrap().info();
+        (white, black)
+    }
 
-        println!("declare_draw:b");
-        if game_state.game.side_to_move() == color {
-            game_state.game.declare_draw()
-        } else {
-            println!("attempted to declare draw when not available");
-            false
-        }
+    /// Offers a draw on behalf of `color`, which can be done on either turn.
+    pub fn offer_draw(&self, color: Color) {
+        self.controller.send(Event::OfferDraw(color));
+    }
+
+    /// The side whose draw offer is waiting on an answer, if any.
+    pub fn draw_offer(&self) -> Option<Color> {
+        let game_state = self.game_state.lock().unwrap();
+        game_state.draw_offer.filter(|_| !game_state.is_finished())
+    }
+
+    /// Accepts the draw offered to the side to move.
+    pub fn accept_draw(&self) {
+        self.submit(Decision::AcceptDraw);
+    }
+
+    pub fn decline_draw(&self) {
+        self.controller
+            .send(Event::DeclineDraw(self.side_to_move()));
+    }
+
+    /// Whether the side to move can claim a draw by repetition or the
+    /// fifty-move rule.
+    pub fn can_claim_draw(&self) -> bool {
+        self.game_state.lock().unwrap().can_claim_draw()
+    }
+
+    pub fn claim_draw(&self) {
+        self.submit(Decision::ClaimDraw);
     }
 
     pub fn side_to_move(&self) -> Color {
-        println!("side_to_move");
         self.game_state.lock().unwrap().game.side_to_move()
     }
 
     pub fn resign(&self, color: Color) {
-        self.game_state.lock().unwrap().game.resign(color);
+        self.controller.send(Event::Resign(color));
     }
 
+    /// Plays a move for the side to move. The move is made by the game
+    /// controller, so it shows up on the board a moment later.
     pub fn make_move(&self, m: ChessMove) {
-        self.game_state.lock().unwrap().game.make_move(m);
+        self.submit(Decision::Move(m));
+    }
+
+    /// Hands a decision made at the board to the side to move.
+    fn submit(&self, decision: Decision) {
+        let position = self.game_state.lock().unwrap().current_position();
+        self.controller.send(Event::Submit {
+            color: position.side_to_move(),
+            position,
+            decision,
+        });
     }
 
     pub fn current_position(&mut self) -> Board {
@@ -116,88 +393,467 @@ impl AppState {
     }
 
     pub fn human_to_move(&self) -> bool {
-        match self.game_state.lock().unwrap().game.side_to_move() {
+        let side_to_move = self.game_state.lock().unwrap().game.side_to_move();
+        self.is_human(side_to_move)
+    }
+
+    /// The side a human plays, preferring the side to move if both do.
+    pub fn human_side(&self) -> Option<Color> {
+        let side_to_move = self.side_to_move();
+        [side_to_move, !side_to_move]
+            .into_iter()
+            .find(|&color| self.is_human(color))
+    }
+
+    fn is_human(&self, color: Color) -> bool {
+        match color {
             Color::White => self.white.lock().unwrap().is_human(),
             Color::Black => self.black.lock().unwrap().is_human(),
         }
     }
 
+    /// Whether there's a move to take back in a game with a human in it.
+    pub fn can_take_back(&self) -> bool {
+        let game_state = self.game_state.lock().unwrap();
+        game_state.started
+            && !game_state.is_finished()
+            && !game_state.moves().is_empty()
+            && (self.is_human(Color::White) || self.is_human(Color::Black))
+    }
+
+    pub fn can_redo(&self) -> bool {
+        let game_state = self.game_state.lock().unwrap();
+        game_state.started && !game_state.is_finished() && game_state.can_redo()
+    }
+
+    /// Takes back the last move. Against a computer, its reply is taken back
+    /// too, so that it's the human's move again.
+    pub fn take_back(&self) {
+        if !self.can_take_back() {
+            return;
+        }
+
+        let mut game_state = self.game_state.lock().unwrap();
+        if game_state.undo().is_some() {
+            while !self.is_human(game_state.game.side_to_move()) && game_state.undo().is_some() {}
+        }
+        drop(game_state);
+
+        self.reset_ui();
+        self.controller.send(Event::Refresh);
+    }
+
+    /// Redoes moves taken back, up to the human's next move.
+    pub fn redo(&self) {
+        if !self.can_redo() {
+            return;
+        }
+
+        let mut game_state = self.game_state.lock().unwrap();
+        if game_state.redo().is_some() {
+            while !self.is_human(game_state.game.side_to_move()) && game_state.redo().is_some() {}
+        }
+        drop(game_state);
+
+        self.reset_ui();
+        self.controller.send(Event::Refresh);
+    }
+
+    pub fn start_position(&self) -> Board {
+        self.game_state.lock().unwrap().start_position
+    }
+
+    /// The game so far in PGN, with anything drawn on the board kept in
+    /// comments.
+    pub fn pgn(&self) -> String {
+        let mut game_state = self.game_state.lock().unwrap();
+        game_state.check_flag();
+        let pgn = PgnGame::from_game_state(&game_state, game_state.outcome())
+            .header("Event", "patzer game");
+        pgn.to_string()
+    }
+
+    /// Writes the game so far to `path` in PGN.
+    pub fn save_pgn(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.pgn())
+    }
+
+    /// The moves played so far, in SAN.
+    pub fn move_list(&self) -> Vec<String> {
+        let game_state = self.game_state.lock().unwrap();
+        notation::san_line(&game_state.start_position, &game_state.moves())
+    }
+
+    /// Whether the analysis panel is searching the position on display.
+    pub fn is_analysing(&self) -> bool {
+        self.ui_state.lock().unwrap().analysing
+    }
+
+    pub fn set_analysing(&self, analysing: bool) {
+        self.ui_state.lock().unwrap().analysing = analysing;
+        self.follow_analysis();
+    }
+
+    /// Keeps the analysis on the position on the board, starting it over
+    /// whenever that changes. It pauses while a computer player is thinking,
+    /// so the two don't fight over the CPU.
+    pub fn follow_analysis(&self) {
+        let engine_thinking = self.is_started()
+            && !self.is_finished()
+            && !self.human_to_move()
+            && self.viewed_ply().is_none();
+        let setting_up = self.setup().is_some();
+        let target = Some(self.displayed_position())
+            .filter(|_| self.is_analysing() && !engine_thinking && !setting_up);
+
+        let mut analysis = self.analysis.lock().unwrap();
+        if analysis.as_ref().map(Analysis::position) != target {
+            // dropping the old analysis stops it
+            *analysis = target.map(Analysis::start);
+        }
+    }
+
+    /// The latest findings of the analysis, if it's running.
+    pub fn analysis(&self) -> Option<AnalysisLine> {
+        let analysis = self.analysis.lock().unwrap();
+        analysis.as_ref().and_then(Analysis::latest)
+    }
+
+    /// Has the engine go over every move of the finished game, in the
+    /// background.
+    pub fn start_review(&self) {
+        if !self.is_finished() {
+            return;
+        }
+
+        let (start_position, moves) = {
+            let game_state = self.game_state.lock().unwrap();
+            (game_state.start_position, game_state.moves())
+        };
+        // dropping an old review stops it
+        *self.review.lock().unwrap() = Some(Review::start(start_position, moves, REVIEW_DEPTH));
+    }
+
+    /// What the review has found so far, if the game on the board is the
+    /// one that was reviewed.
+    pub fn review(&self) -> Option<ReviewReport> {
+        let (start_position, moves) = {
+            let game_state = self.game_state.lock().unwrap();
+            (game_state.start_position, game_state.moves())
+        };
+
+        let mut review = self.review.lock().unwrap();
+        let stale = match review.as_ref() {
+            Some(review) => review.game() != (start_position, &moves[..]),
+            None => false,
+        };
+        if stale {
+            *review = None;
+        }
+        review.as_ref().map(Review::report)
+    }
+
+    pub fn is_reviewing(&self) -> bool {
+        let review = self.review.lock().unwrap();
+        review.as_ref().is_some_and(|review| !review.is_finished())
+    }
+
+    /// The position on display: either an earlier one picked from the move
+    /// list, or the current one.
+    pub fn displayed_position(&self) -> Board {
+        let viewed_ply = self.viewed_ply();
+        let game_state = self.game_state.lock().unwrap();
+
+        match viewed_ply {
+            Some(ply) => game_state.positions()[ply],
+            None => game_state.current_position(),
+        }
+    }
+
+    /// The move which led to the position on the board, if any.
+    pub fn displayed_last_move(&self) -> Option<ChessMove> {
+        let viewed_ply = self.viewed_ply();
+        let moves = self.game_state.lock().unwrap().moves();
+        let ply = viewed_ply.unwrap_or(moves.len());
+
+        ply.checked_sub(1).map(|i| moves[i])
+    }
+
+    /// The move which led to the position on the board, the way a screen
+    /// reader should say it.
+    pub fn spoken_last_move(&self) -> Option<String> {
+        let ply = self.displayed_ply().checked_sub(1)?;
+        let game_state = self.game_state.lock().unwrap();
+        let m = game_state.moves()[ply];
+        Some(describe::spoken_move(&game_state.positions()[ply], m))
+    }
+
+    /// The ply of the position on the board, counting the starting position
+    /// as 0.
+    pub fn displayed_ply(&self) -> usize {
+        let viewed_ply = self.viewed_ply();
+        viewed_ply.unwrap_or_else(|| self.game_state.lock().unwrap().moves().len())
+    }
+
+    /// The arrows and marks drawn on the position on the board.
+    pub fn displayed_annotations(&self) -> Annotations {
+        let ply = self.displayed_ply();
+        let game_state = self.game_state.lock().unwrap();
+        game_state
+            .annotations
+            .get(&ply)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn edit_annotations(&self, edit: impl FnOnce(&mut Annotations)) {
+        let ply = self.displayed_ply();
+        let mut game_state = self.game_state.lock().unwrap();
+        edit(game_state.annotations.entry(ply).or_default());
+    }
+
+    /// Marks a square on the position on the board, or rubs the mark out.
+    pub fn annotate_square(&self, square: Square, brush: Brush) {
+        self.edit_annotations(|annotations| annotations.toggle_square(square, brush));
+    }
+
+    /// Draws an arrow on the position on the board, or rubs it out.
+    pub fn annotate_arrow(&self, from: Square, to: Square, brush: Brush) {
+        self.edit_annotations(|annotations| annotations.toggle_arrow(from, to, brush));
+    }
+
+    pub fn clear_annotations(&self) {
+        let ply = self.displayed_ply();
+        self.game_state.lock().unwrap().annotations.remove(&ply);
+    }
+
+    pub fn show_threats(&self) -> bool {
+        self.ui_state.lock().unwrap().show_threats
+    }
+
+    /// Shades the squares the side to move's opponent attacks.
+    pub fn set_show_threats(&self, show: bool) {
+        self.ui_state.lock().unwrap().show_threats = show;
+    }
+
+    pub fn viewed_ply(&self) -> Option<usize> {
+        self.ui_state.lock().unwrap().viewed_ply
+    }
+
+    /// Shows the position after `ply` plies. Viewing the latest position, or
+    /// `None`, goes back to following the game.
+    pub fn view_ply(&self, ply: Option<usize>) {
+        let plies = self.game_state.lock().unwrap().moves().len();
+        let mut ui_state = self.ui_state.lock().unwrap();
+
+        ui_state.viewed_ply = ply.filter(|&ply| ply < plies);
+        ui_state.selected_square = None;
+    }
+
+    /// Steps through the move list, by `delta` plies.
+    pub fn step_view(&self, delta: isize) {
+        let plies = self.game_state.lock().unwrap().moves().len();
+        let current = self.viewed_ply().unwrap_or(plies);
+        let ply = (current as isize + delta).clamp(0, plies as isize) as usize;
+
+        self.view_ply(Some(ply));
+    }
+
+    /// Forgets selections and which move was being looked at, but not how
+    /// the board is shown.
+    fn reset_ui(&self) {
+        let mut ui_state = self.ui_state.lock().unwrap();
+        *ui_state = UiState {
+            show_threats: ui_state.show_threats,
+            flipped: ui_state.flipped,
+            piece_set: ui_state.piece_set,
+            theme: ui_state.theme.clone(),
+            analysing: ui_state.analysing,
+            ..UiState::default()
+        };
+    }
+
     pub fn reset_game(&mut self) {
+        self.reset_to(Board::default(), 0);
+    }
+
+    /// Starts a new game from `position`, with `halfmove_clock` plies already
+    /// gone without a capture or pawn move, keeping the players, the time
+    /// control and how the board is shown.
+    fn reset_to(&self, position: Board, halfmove_clock: usize) {
         {
             let mut game_state = self.game_state.lock().unwrap();
-            let mut ui_state = self.ui_state.lock().unwrap();
 
             let white = self.white.lock().unwrap();
             let black = self.black.lock().unwrap();
 
-            *game_state = GameState::new(white.name(), black.name());
-            *ui_state = UiState::default();
-        } // release locks before we wait for the threads to halt
-
-        for handle in self.handles.lock().unwrap().drain(0..) {
-            handle
-                .join()
-                .expect("Error waiting for computer opponent to conclude");
+            let time_control = game_state.clock.time_control();
+            *game_state = GameState::with_position(white.name(), black.name(), position);
+            game_state.start_halfmove_clock = halfmove_clock;
+            game_state.set_time_control(time_control);
         }
+        self.reset_ui();
+
+        self.controller.send(Event::Refresh);
     }
 
-    fn start_computer_players(&self) {
-        let white_player = self.white.lock().unwrap().clone();
-        let state = self.clone();
-        let color = Color::White;
+    /// Starts setting up a position, beginning with the one on the board.
+    pub fn start_setup(&self) {
+        let mut setup = Setup::from_board(&self.displayed_position());
+        setup.halfmove_clock = {
+            let game_state = self.game_state.lock().unwrap();
+            let ply = self.viewed_ply().unwrap_or(game_state.moves().len());
+            game_state.halfmove_clock_at(ply)
+        };
+        let mut ui_state = self.ui_state.lock().unwrap();
+        ui_state.setup = Some(setup);
+        ui_state.selected_square = None;
+    }
 
-        let mut handles = self.handles.lock().unwrap();
+    /// The position being set up, if the board is in setup mode.
+    pub fn setup(&self) -> Option<Setup> {
+        self.ui_state.lock().unwrap().setup.clone()
+    }
 
-        handles.push(std::thread::spawn(move || {
-            let move_fn = match white_player.move_function() {
-                Some(f) => f,
-                None => return,
-            };
+    /// Changes the position being set up, if there is one.
+    pub fn edit_setup(&self, edit: impl FnOnce(&mut Setup)) {
+        if let Some(setup) = self.ui_state.lock().unwrap().setup.as_mut() {
+            edit(setup);
+        }
+    }
 
-            run_computer(state, move_fn, color);
-        }));
+    /// What clicking on the board in setup mode puts there, or `None` to
+    /// take pieces off.
+    pub fn setup_piece(&self) -> Option<(Piece, Color)> {
+        self.ui_state.lock().unwrap().setup_piece
+    }
 
-        let black_player = self.black.lock().unwrap().clone();
-        let state = self.clone();
-        let color = Color::Black;
+    pub fn set_setup_piece(&self, piece: Option<(Piece, Color)>) {
+        self.ui_state.lock().unwrap().setup_piece = piece;
+    }
 
-        handles.push(std::thread::spawn(move || {
-            let move_fn = match black_player.move_function() {
-                Some(f) => f,
-                None => return,
-            };
+    /// Puts the chosen piece on `coords`. Clicking a piece which is already
+    /// there again takes it off.
+    pub fn setup_click(&self, coords: Coords) {
+        let piece = self.setup_piece();
+        self.edit_setup(|setup| {
+            let square = square_at(coords);
+            if setup.piece_on(square) == piece {
+                setup.put(square, None);
+            } else {
+                setup.put(square, piece);
+            }
+        });
+    }
 
-            run_computer(state, move_fn, color);
-        }));
+    /// Drags a piece to `to`, or off the board if that's `None`.
+    pub fn setup_move(&self, from: Coords, to: Option<Coords>) {
+        self.edit_setup(|setup| match to {
+            Some(to) => setup.move_piece(square_at(from), square_at(to)),
+            None => setup.put(square_at(from), None),
+        });
     }
 
-    pub fn ui_select_square(&self, selection: Option<(usize, usize)>) {
-        if self.is_started() && !self.is_finished() {
-            self.ui_state.lock().unwrap().selected_square = selection;
+    pub fn setup_remove(&self, coords: Coords) {
+        self.edit_setup(|setup| setup.put(square_at(coords), None));
+    }
+
+    pub fn cancel_setup(&self) {
+        self.ui_state.lock().unwrap().setup = None;
+    }
+
+    /// Leaves setup mode with a new game from the position which was set up,
+    /// either starting it or analysing it. If the position can't be played,
+    /// this says why and stays in setup mode.
+    pub fn finish_setup(&self, analyse: bool) -> Result<(), String> {
+        let setup = match self.setup() {
+            Some(setup) => setup,
+            None => return Ok(()),
+        };
+        let position = setup.validate()?;
+
+        self.reset_to(position, setup.halfmove_clock);
+        if analyse {
+            self.set_analysing(true);
+        } else {
+            self.start_game();
         }
+
+        Ok(())
     }
 
-    pub fn ui_attempt_move(&self, to_selection: Option<(usize, usize)>, promote_to: Option<Piece>) {
-        if !self.is_started() || self.is_finished() {
-            return;
+    /// Whether the piece on `coords` can be picked up: it has to belong to a
+    /// human whose move it is.
+    pub fn ui_can_select(&self, coords: Coords) -> bool {
+        if !self.is_started() || self.is_finished() || self.viewed_ply().is_some() {
+            return false;
         }
 
-        if self.human_to_move() {
-            if let (Some((r1, f1)), Some((r2, f2))) =
-                (self.ui_state.lock().unwrap().selected_square, to_selection)
-            {
-                let from_sq = Square::make_square(Rank::from_index(r1), File::from_index(f1));
-                let to_sq = Square::make_square(Rank::from_index(r2), File::from_index(f2));
-                self.make_move(ChessMove::new(from_sq, to_sq, promote_to));
-            };
+        let board = self.game_state.lock().unwrap().current_position();
+        self.human_to_move() && board.color_on(square_at(coords)) == Some(board.side_to_move())
+    }
+
+    pub fn ui_select_square(&self, selection: Option<Coords>) {
+        if selection.is_none_or(|coords| self.ui_can_select(coords)) {
+            self.ui_state.lock().unwrap().selected_square = selection;
         }
+    }
 
+    /// Moves the selected piece to `to_selection`, if that's legal. Illegal
+    /// moves are remembered for a moment, so the board can show they were
+    /// refused. Either way, the piece is put down.
+    pub fn ui_attempt_move(&self, to_selection: Option<Coords>, promote_to: Option<Piece>) {
+        let from = self.ui_selected_square();
         self.ui_select_square(None);
+
+        let (from, to) = match (from, to_selection) {
+            (Some(from), Some(to)) if self.ui_can_select(from) => (from, to),
+            _ => return,
+        };
+
+        let m = ChessMove::new(square_at(from), square_at(to), promote_to);
+        let board = self.game_state.lock().unwrap().current_position();
+        if board.legal(m) {
+            self.make_move(m);
+        } else {
+            self.ui_state.lock().unwrap().illegal_drop = Some((to, Instant::now()));
+        }
     }
 
-    pub fn ui_selected_square(&self) -> Option<(usize, usize)> {
+    pub fn ui_selected_square(&self) -> Option<Coords> {
         self.ui_state.lock().unwrap().selected_square
     }
+
+    /// Where the selected piece can move, and whether each of those moves is
+    /// a capture.
+    pub fn ui_legal_destinations(&self) -> Vec<(Coords, bool)> {
+        let from = match self.ui_selected_square() {
+            Some(coords) => square_at(coords),
+            None => return vec![],
+        };
+        let board = self.game_state.lock().unwrap().current_position();
+
+        let mut destinations = vec![];
+        for m in MoveGen::new_legal(&board).filter(|m| m.get_source() == from) {
+            let to = m.get_dest();
+            let en_passant = board.piece_on(from) == Some(Piece::Pawn)
+                && from.get_file() != to.get_file()
+                && board.piece_on(to).is_none();
+            let destination = (coords_of(to), board.piece_on(to).is_some() || en_passant);
+
+            // promotions give several moves to the same square
+            if !destinations.contains(&destination) {
+                destinations.push(destination);
+            }
+        }
+        destinations
+    }
+
+    /// The square an illegal move was dropped on, and how long ago.
+    pub fn ui_illegal_drop(&self) -> Option<(Coords, Duration)> {
+        let illegal_drop = self.ui_state.lock().unwrap().illegal_drop;
+        illegal_drop.map(|(coords, at)| (coords, at.elapsed()))
+    }
 }
 
 impl Default for AppState {
@@ -206,27 +862,12 @@ impl Default for AppState {
     }
 }
 
-fn run_computer(app_state: AppState, f: Arc<Box<MoveFunction>>, color: Color) {
-    loop {
-        let game = app_state.game();
-        if app_state.is_finished() || !app_state.is_started() {
-            break;
-        }
-
-        if game.side_to_move() != color {
-            thread::sleep(std::time::Duration::from_millis(100));
-            continue;
-        }
-
-        if game.can_declare_draw() {
-            app_state.declare_draw(color);
-        }
-
-        if let Some(m) = f(&game) {
-            thread::sleep(std::time::Duration::from_millis(100));
-            app_state.make_move(m);
-        } else {
-            app_state.resign(color);
-        }
-    }
+/// The theme called `name`. User themes come last, so they win over built-in
+/// themes with the same name.
+fn find_theme(themes: &[Theme], name: &str) -> Option<Theme> {
+    themes
+        .iter()
+        .rev()
+        .find(|theme| theme.name == name)
+        .cloned()
 }
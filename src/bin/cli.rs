@@ -0,0 +1,223 @@
+use chess::{Board, Color};
+use std::process::exit;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use patzer::describe;
+use patzer::engine_config::{EngineConfig, Strategy};
+use patzer::game_state::{fen_halfmove_clock, GameState};
+use patzer::notation::san;
+use patzer::pgn::PgnGame;
+use patzer::player::Decision;
+use patzer::prompt::{input_command, render_board, Command, HELP};
+use patzer::strategies::{search, SearchLimits};
+use patzer::tablebase::Tablebase;
+
+const USAGE: &str = "usage: cli [options] [opponent]
+
+Plays a game in the terminal. The opponent is a strategy spec, like
+alpha_beta:depth=4 or uci:cmd=stockfish (default alpha_beta).
+
+options:
+  --black      play Black
+  --fen FEN    start from this position instead
+  --ascii      draw pieces as letters, for terminals without chess symbols";
+
+/// How deep hints look.
+const HINT_DEPTH: u8 = 5;
+
+struct Options {
+    opponent: EngineConfig,
+    color: Color,
+    start: Board,
+    start_halfmove_clock: usize,
+    ascii: bool,
+}
+
+pub fn main() {
+    let options = parse_args(std::env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        exit(2);
+    });
+
+    let tablebase = Tablebase::from_env().map(Arc::new);
+    let engine = options
+        .opponent
+        .build(tablebase.clone())
+        .unwrap_or_else(|e| {
+            eprintln!("could not start {}: {}", options.opponent.name(), e);
+            exit(1);
+        });
+
+    let you = "You".to_string();
+    let (white_name, black_name) = match options.color {
+        Color::White => (you, engine.name()),
+        Color::Black => (engine.name(), you),
+    };
+    let mut game_state = GameState::with_position(white_name, black_name, options.start);
+    game_state.start_halfmove_clock = options.start_halfmove_clock;
+    game_state.started = true;
+
+    let mut flipped = options.color == Color::Black;
+    let mut show_board = true;
+
+    println!(
+        "Playing {} as {:?}. Type help for the commands.",
+        engine.name(),
+        options.color
+    );
+
+    while !game_state.is_finished() {
+        let board = game_state.current_position();
+        let to_move = board.side_to_move();
+
+        if to_move != options.color {
+            let clock = game_state.clock.clone();
+            match engine.play(
+                &game_state.game,
+                &game_state.start_position,
+                &clock,
+                game_state.draw_offered(),
+                game_state.can_claim_draw(),
+            ) {
+                decision @ (Decision::Move(m) | Decision::MoveAndOfferDraw(m)) => {
+                    println!("{} plays {}", engine.name(), san(&board, m));
+                    game_state.make_move(m);
+                    if let Decision::MoveAndOfferDraw(_) = decision {
+                        game_state.offer_draw(to_move);
+                        println!("{} offers a draw; type draw to accept", engine.name());
+                    }
+                    engine.ponder(&game_state.game, &game_state.clock);
+                }
+                Decision::AcceptDraw if game_state.accept_draw(to_move) => {}
+                Decision::ClaimDraw if game_state.claim_draw(to_move) => {}
+                Decision::Resign => game_state.resign(to_move),
+                decision => {
+                    eprintln!("{} made an invalid decision: {:?}", engine.name(), decision);
+                    game_state.resign(to_move);
+                }
+            }
+            show_board = true;
+            continue;
+        }
+
+        if show_board {
+            println!();
+            print!("{}", render_board(&board, flipped, options.ascii));
+            println!();
+            show_board = false;
+        }
+
+        let command = match input_command(&board) {
+            Ok(Some(command)) => command,
+            // the end of input leaves the game where it is
+            Ok(None) => {
+                println!();
+                exit(0);
+            }
+            Err(e) => {
+                eprintln!("could not read your move: {}", e);
+                exit(1);
+            }
+        };
+
+        match command {
+            Command::Move(m) => {
+                game_state.make_move(m);
+                engine.opponent_moved(&game_state.game, m);
+            }
+            Command::Undo => {
+                // back to the last position where it was your move
+                engine.stop();
+                let mut undone = false;
+                while game_state.undo().is_some() {
+                    undone = true;
+                    if game_state.current_position().side_to_move() == options.color {
+                        break;
+                    }
+                }
+                if undone {
+                    show_board = true;
+                } else {
+                    println!("There's nothing to take back");
+                }
+            }
+            Command::Hint => {
+                let limits = SearchLimits::depth(HINT_DEPTH);
+                match search(&board, &limits, tablebase.as_deref(), |_| {}) {
+                    Some(m) => println!("Try {}", san(&board, m)),
+                    None => println!("I've got nothing"),
+                }
+            }
+            Command::Fen => println!("{}", board),
+            Command::Pgn => println!("{}", pgn(&game_state)),
+            Command::Flip => {
+                flipped = !flipped;
+                show_board = true;
+            }
+            Command::Describe => println!("{}", describe::position(&board)),
+            Command::Resign => game_state.resign(options.color),
+            Command::Draw => {
+                if game_state.draw_offered() {
+                    game_state.accept_draw(options.color);
+                } else if game_state.can_claim_draw() {
+                    game_state.claim_draw(options.color);
+                } else {
+                    game_state.offer_draw(options.color);
+                    println!("You offer a draw; now make your move");
+                }
+            }
+            Command::Help => println!("{}", HELP),
+        }
+    }
+
+    let outcome = game_state.outcome();
+    if let Some(outcome) = outcome {
+        engine.game_over(outcome);
+    }
+
+    println!();
+    print!(
+        "{}",
+        render_board(&game_state.current_position(), flipped, options.ascii)
+    );
+    println!();
+    println!("{}", pgn(&game_state));
+    println!(
+        "{}",
+        game_state.result_message().unwrap_or("The game is over")
+    );
+}
+
+fn pgn(game_state: &GameState) -> PgnGame {
+    PgnGame::from_game_state(game_state, game_state.outcome()).header("Event", "patzer game")
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        opponent: EngineConfig::new(Strategy::AlphaBeta),
+        color: Color::White,
+        start: Board::default(),
+        start_halfmove_clock: 0,
+        ascii: false,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--black" => options.color = Color::Black,
+            "--ascii" => options.ascii = true,
+            "--fen" => {
+                let fen = args.next().ok_or("--fen needs a position")?;
+                options.start =
+                    Board::from_str(&fen).map_err(|_| format!("invalid position: {}", fen))?;
+                options.start_halfmove_clock = fen_halfmove_clock(&fen);
+            }
+            "--help" | "-h" => return Err("patzer in the terminal".into()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            spec => options.opponent = spec.parse()?,
+        }
+    }
+
+    Ok(options)
+}
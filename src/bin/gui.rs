@@ -1,18 +1,66 @@
 use eframe::egui;
+use std::io;
+use std::sync::Arc;
 
 use patzer::app_state::AppState;
-use patzer::windows::PatzerApp;
+use patzer::settings::{Session, Settings};
+use patzer::windows::{PatzerApp, SESSION_KEY};
 
 pub fn main() {
+    let settings_path = Settings::path();
+    let settings = match settings_path.as_deref().map(Settings::load) {
+        Some(Ok(settings)) => settings,
+        Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => {
+            eprintln!("Ignoring saved settings: {}", e);
+            Settings::default()
+        }
+        _ => Settings::default(),
+    };
+
+    let state = AppState::new();
+    if let Err(e) = state.load_user_themes() {
+        eprintln!("Ignoring user themes: {}", e);
+    }
+    if let Err(e) = state.apply_settings(&settings) {
+        eprintln!("{}", e);
+    }
+
+    // the board shrinks to fit, so the default size only has to leave room
+    // for the move list and the controls
+    let (width, height) = settings.window_size.unwrap_or((1_200.0, 1_000.0));
     let options = eframe::NativeOptions {
-        default_theme: eframe::Theme::Light,
-        // TODO: figure out why autosizing doesn't size up
-        min_window_size: Some(egui::Vec2::new(1_200.0, 1_000.0)),
+        default_theme: if state.theme().dark_mode {
+            eframe::Theme::Dark
+        } else {
+            eframe::Theme::Light
+        },
+        follow_system_theme: false,
+        initial_window_size: Some(egui::Vec2::new(width, height)),
+        min_window_size: Some(egui::Vec2::new(640.0, 480.0)),
         ..Default::default()
     };
 
-    let state = AppState::new();
-    let app = PatzerApp::new(state);
+    eframe::run_native(
+        "Patzer Chess",
+        options,
+        Box::new(move |cc| {
+            // redraw whenever the game controller says something happened
+            let ctx = cc.egui_ctx.clone();
+            state.set_notifier(Arc::new(move || ctx.request_repaint()));
+
+            // carry on with the game that was going when patzer last closed
+            let session = cc
+                .storage
+                .and_then(|storage| storage.get_string(SESSION_KEY))
+                .filter(|session| !session.is_empty());
+            if let Some(session) = session {
+                match session.parse::<Session>() {
+                    Ok(session) => state.restore_session(&session),
+                    Err(e) => eprintln!("Couldn't restore the last game: {}", e),
+                }
+            }
 
-    eframe::run_native("Patzer Chess", options, Box::new(|_cc| Box::new(app)));
+            Box::new(PatzerApp::new(state, settings_path))
+        }),
+    );
 }
@@ -0,0 +1,161 @@
+use chess::{Board, Color};
+use std::fs::File;
+use std::io::Write;
+use std::process::exit;
+use std::sync::Arc;
+
+use patzer::engine_config::EngineConfig;
+use patzer::pgn::PgnGame;
+use patzer::tablebase::Tablebase;
+use patzer::tournament::{play_game, read_openings, Adjudication, MatchScore};
+
+const USAGE: &str = "usage: match [options] <player1> <player2>
+
+Players are strategy specs: random_move, first_legal_move, or alpha_beta with
+optional settings, like alpha_beta:depth=4 or alpha_beta:movetime=500.
+External UCI engines are given as uci:cmd=PATH, with the same settings.
+
+options:
+  --games N          number of games to play (default 10)
+  --openings FILE    FEN/EPD starting positions, each played with both colors
+  --pgn FILE         where to write the games (default match.pgn)
+  --max-plies N      draw games which go on longer than this (default 400)
+  --resign-score CP  adjudicate a loss once a side is down this much... (default 1000)
+  --resign-plies N   ...for this many plies in a row (default 10)";
+
+struct Options {
+    players: Vec<EngineConfig>,
+    games: u32,
+    openings: Option<String>,
+    pgn: String,
+    adjudication: Adjudication,
+}
+
+pub fn main() {
+    let options = parse_args(std::env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        exit(2);
+    });
+
+    let openings = match &options.openings {
+        Some(path) => read_openings(path).unwrap_or_else(|e| {
+            eprintln!("could not read openings from {}: {}", path, e);
+            exit(1);
+        }),
+        None => vec![Board::default().into()],
+    };
+    if openings.is_empty() {
+        eprintln!("no openings found");
+        exit(1);
+    }
+
+    let mut pgn_file = File::create(&options.pgn).unwrap_or_else(|e| {
+        eprintln!("could not create {}: {}", options.pgn, e);
+        exit(1);
+    });
+
+    let tablebase = Tablebase::from_env().map(Arc::new);
+    let build = |config: &EngineConfig| {
+        config.build(tablebase.clone()).unwrap_or_else(|e| {
+            eprintln!("could not start {}: {}", config.name(), e);
+            exit(1);
+        })
+    };
+    let first = build(&options.players[0]);
+    let second = build(&options.players[1]);
+
+    let mut score = MatchScore::default();
+
+    for round in 0..options.games {
+        // each opening is played twice, so both players get each side of it
+        let start = openings[(round as usize / 2) % openings.len()];
+        let first_color = if round % 2 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let (white, black) = match first_color {
+            Color::White => (&*first, &*second),
+            Color::Black => (&*second, &*first),
+        };
+
+        let record = play_game(white, black, start, &options.adjudication);
+        score.record(record.outcome, first_color);
+
+        println!(
+            "Game {}/{}: {} vs {}: {} ({})",
+            round + 1,
+            options.games,
+            white.name(),
+            black.name(),
+            record.outcome.pgn_result(),
+            record.reason
+        );
+
+        let pgn = PgnGame::from_game_state(&record.game_state, Some(record.outcome))
+            .header("Event", "patzer match")
+            .header("Round", &(round + 1).to_string())
+            .header("Termination", &record.reason);
+        writeln!(pgn_file, "{}", pgn).expect("writing the PGN file failed");
+    }
+
+    println!(
+        "Score of {} vs {}: {} - {} - {} [{:.3}] {}",
+        first.name(),
+        second.name(),
+        score.wins,
+        score.losses,
+        score.draws,
+        (score.wins as f64 + score.draws as f64 / 2.0) / score.games().max(1) as f64,
+        score.games()
+    );
+    if let Some(elo) = score.elo() {
+        println!(
+            "Elo difference: {:.1} +/- {:.1}",
+            elo.difference, elo.margin
+        );
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        players: vec![],
+        games: 10,
+        openings: None,
+        pgn: "match.pgn".into(),
+        adjudication: Adjudication::default(),
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            options.players.push(arg.parse()?);
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = || {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("{} needs a number, got {}", arg, value))
+        };
+
+        match arg.as_str() {
+            "--games" => options.games = number()?,
+            "--openings" => options.openings = Some(value.clone()),
+            "--pgn" => options.pgn = value.clone(),
+            "--max-plies" => options.adjudication.max_plies = number()? as usize,
+            "--resign-score" => options.adjudication.resign_score = number()? as i64,
+            "--resign-plies" => options.adjudication.resign_plies = number()? as usize,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    if options.players.len() != 2 {
+        return Err("exactly two players are needed".into());
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,192 @@
+use chess::{Board, Color};
+use std::process::exit;
+use std::sync::Arc;
+
+use patzer::engine_config::EngineConfig;
+use patzer::tablebase::Tablebase;
+use patzer::tournament::{
+    play_game, read_openings, Adjudication, MatchScore, Pentanomial, Sprt, SprtDecision,
+};
+
+const USAGE: &str = "usage: sprt [options] <candidate> <baseline>
+
+Runs a sequential probability ratio test of whether the candidate is elo1
+stronger than the baseline (H1) rather than elo0 stronger (H0). Players are
+strategy specs, as for the match binary: random_move, first_legal_move, or
+alpha_beta with optional settings, like alpha_beta:depth=4,movetime=500,
+or an external engine, like uci:cmd=stockfish,movetime=100.
+
+options:
+  --elo0 ELO         Elo difference under H0 (default 0)
+  --elo1 ELO         Elo difference under H1 (default 5)
+  --alpha P          false positive rate (default 0.05)
+  --beta P           false negative rate (default 0.05)
+  --max-pairs N      give up after this many game pairs (default 10000)
+  --openings FILE    FEN/EPD starting positions, cycled through
+  --max-plies N      draw games which go on longer than this (default 400)
+  --resign-score CP  adjudicate a loss once a side is down this much... (default 1000)
+  --resign-plies N   ...for this many plies in a row (default 10)";
+
+struct Options {
+    players: Vec<EngineConfig>,
+    sprt: Sprt,
+    max_pairs: u32,
+    openings: Option<String>,
+    adjudication: Adjudication,
+}
+
+pub fn main() {
+    let options = parse_args(std::env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        exit(2);
+    });
+
+    let openings = match &options.openings {
+        Some(path) => read_openings(path).unwrap_or_else(|e| {
+            eprintln!("could not read openings from {}: {}", path, e);
+            exit(1);
+        }),
+        None => vec![Board::default().into()],
+    };
+    if openings.is_empty() {
+        eprintln!("no openings found");
+        exit(1);
+    }
+
+    let tablebase = Tablebase::from_env().map(Arc::new);
+    let build = |config: &EngineConfig| {
+        config.build(tablebase.clone()).unwrap_or_else(|e| {
+            eprintln!("could not start {}: {}", config.name(), e);
+            exit(1);
+        })
+    };
+    let candidate = build(&options.players[0]);
+    let baseline = build(&options.players[1]);
+    let sprt = options.sprt;
+
+    println!(
+        "SPRT of {} vs {}: elo0 {}, elo1 {}, alpha {}, beta {}, bounds [{:.2}, {:.2}]",
+        candidate.name(),
+        baseline.name(),
+        sprt.elo0,
+        sprt.elo1,
+        sprt.alpha,
+        sprt.beta,
+        sprt.lower_bound(),
+        sprt.upper_bound()
+    );
+
+    let mut pairs = Pentanomial::default();
+    let mut score = MatchScore::default();
+    let mut decision = SprtDecision::Continue;
+
+    for pair in 0..options.max_pairs {
+        let start = openings[pair as usize % openings.len()];
+
+        // the candidate plays each side of the opening once
+        let first = play_game(&*candidate, &*baseline, start, &options.adjudication);
+        let second = play_game(&*baseline, &*candidate, start, &options.adjudication);
+
+        pairs.record(
+            (first.outcome, Color::White),
+            (second.outcome, Color::Black),
+        );
+        score.record(first.outcome, Color::White);
+        score.record(second.outcome, Color::Black);
+
+        println!(
+            "Pairs: {}, pentanomial {:?}, W-L-D {}-{}-{}, LLR {:.2}",
+            pairs.pairs(),
+            pairs.counts,
+            score.wins,
+            score.losses,
+            score.draws,
+            sprt.llr(&pairs)
+        );
+
+        decision = sprt.decide(&pairs);
+        if decision != SprtDecision::Continue {
+            break;
+        }
+    }
+
+    if let Some(elo) = score.elo() {
+        println!(
+            "Elo difference: {:.1} +/- {:.1}",
+            elo.difference, elo.margin
+        );
+    }
+
+    match decision {
+        SprtDecision::AcceptH1 => println!("H1 accepted: the candidate passes"),
+        SprtDecision::AcceptH0 => {
+            println!("H0 accepted: the candidate fails");
+            exit(1);
+        }
+        SprtDecision::Continue => {
+            println!("No decision after {} pairs", pairs.pairs());
+            exit(3);
+        }
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        players: vec![],
+        sprt: Sprt::default(),
+        max_pairs: 10_000,
+        openings: None,
+        adjudication: Adjudication::default(),
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            options.players.push(arg.parse()?);
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = || {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .ok_or_else(|| format!("{} needs a number, got {}", arg, value))
+        };
+        let count = || {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("{} needs a whole number, got {}", arg, value))
+        };
+
+        match arg.as_str() {
+            "--elo0" => options.sprt.elo0 = number()?,
+            "--elo1" => options.sprt.elo1 = number()?,
+            "--alpha" => options.sprt.alpha = number()?,
+            "--beta" => options.sprt.beta = number()?,
+            "--max-pairs" => options.max_pairs = count()?,
+            "--openings" => options.openings = Some(value.clone()),
+            "--max-plies" => options.adjudication.max_plies = count()? as usize,
+            "--resign-score" => options.adjudication.resign_score = count()? as i64,
+            "--resign-plies" => options.adjudication.resign_plies = count()? as usize,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    if options.players.len() != 2 {
+        return Err("a candidate and a baseline are needed".into());
+    }
+    if options.sprt.elo0 >= options.sprt.elo1 {
+        return Err("elo0 must be less than elo1".into());
+    }
+    for p in [options.sprt.alpha, options.sprt.beta] {
+        if p <= 0.0 || p >= 0.5 {
+            return Err("alpha and beta must be between 0 and 0.5".into());
+        }
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,129 @@
+use chess::Color;
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use std::io::{self, stdout};
+use std::process::exit;
+use std::sync::Arc;
+use tui::backend::CrosstermBackend;
+use tui::Terminal;
+
+use patzer::app_state::AppState;
+use patzer::engine_config::EngineConfig;
+use patzer::player::HumanPlayer;
+use patzer::settings::Settings;
+use patzer::terminal::TuiApp;
+
+const USAGE: &str = "usage: tui [options]
+
+Plays or watches games in a full-screen terminal UI. Players are human or a
+strategy spec, like alpha_beta:depth=4 or uci:cmd=stockfish; without options,
+they're whoever played in the GUI last.
+
+options:
+  --white PLAYER   who plays White
+  --black PLAYER   who plays Black
+  --ascii          draw pieces as letters, for terminals without chess symbols";
+
+struct Options {
+    players: Vec<(Color, Option<EngineConfig>)>,
+    ascii: bool,
+}
+
+pub fn main() {
+    let options = parse_args(std::env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        exit(2);
+    });
+
+    // the same settings and themes as the GUI
+    let state = AppState::new();
+    if let Err(e) = state.load_user_themes() {
+        eprintln!("Ignoring user themes: {}", e);
+    }
+    if let Some(Ok(settings)) = Settings::path().as_deref().map(Settings::load) {
+        if let Err(e) = state.apply_settings(&settings) {
+            eprintln!("{}", e);
+        }
+    }
+
+    for (color, config) in options.players {
+        match config {
+            Some(config) => {
+                if let Err(e) = state.configure_player(color, config.clone()) {
+                    eprintln!("could not start {}: {}", config.name(), e);
+                    exit(1);
+                }
+            }
+            None => {
+                let human = Arc::new(HumanPlayer::new("Human"));
+                match color {
+                    Color::White => state.set_white_player(human),
+                    Color::Black => state.set_black_player(human),
+                }
+            }
+        }
+    }
+
+    let mut app = TuiApp::new(state.clone(), options.ascii);
+    let result = run(&mut app);
+    state.shutdown();
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+/// Runs the app full screen, putting the terminal back how it was after,
+/// even if something goes wrong.
+fn run(app: &mut TuiApp) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout(), LeaveAlternateScreen);
+        default_hook(info);
+    }));
+
+    let result = Terminal::new(CrosstermBackend::new(stdout()))
+        .and_then(|mut terminal| app.run(&mut terminal));
+
+    disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        players: vec![],
+        ascii: false,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let color = match arg.as_str() {
+            "--white" => Color::White,
+            "--black" => Color::Black,
+            "--ascii" => {
+                options.ascii = true;
+                continue;
+            }
+            _ => return Err(format!("unknown option: {}", arg)),
+        };
+
+        let player = args
+            .next()
+            .ok_or_else(|| format!("{} needs a player", arg))?;
+        let config = match player.as_str() {
+            "human" => None,
+            spec => Some(spec.parse()?),
+        };
+        options.players.push((color, config));
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,40 @@
+use std::io::{stdin, stdout, BufRead};
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use patzer::cecp::{Cecp, Status};
+use patzer::tablebase::Tablebase;
+
+pub fn main() {
+    let tablebase = Tablebase::from_env().map(Arc::new);
+    let mut cecp = Cecp::new(stdout()).with_tablebase(tablebase);
+
+    // Commands are read on their own thread so that "?" can interrupt a search
+    // which is in progress on the main thread.
+    let stop = cecp.stop_handle();
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for line in stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            if line.trim() == "?" {
+                stop.store(true, Ordering::Relaxed);
+            }
+
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    for line in receiver {
+        if cecp.handle(&line) == Status::Quit {
+            break;
+        }
+    }
+}
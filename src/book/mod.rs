@@ -0,0 +1,122 @@
+use chess::{Board, ChessMove, MoveGen};
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+use crate::notation::san;
+
+/// Main lines of some well-known openings, in SAN.
+const OPENINGS: &[&str] = &[
+    // Ruy Lopez
+    "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7",
+    // Italian
+    "e4 e5 Nf3 Nc6 Bc4 Bc5 c3 Nf6 d3 d6",
+    // Sicilian, Najdorf
+    "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6",
+    // Sicilian, Sveshnikov
+    "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 e5",
+    // French
+    "e4 e6 d4 d5 Nc3 Nf6 Bg5 Be7 e5 Nfd7",
+    // Caro-Kann
+    "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5 Ng3 Bg6",
+    // Queen's Gambit Declined
+    "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O",
+    // Slav
+    "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4 Bf5",
+    // King's Indian
+    "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O",
+    // Nimzo-Indian
+    "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Bd3 d5",
+    // English
+    "c4 e5 Nc3 Nf6 Nf3 Nc6 g3 d5 cxd5 Nxd5",
+    // Réti
+    "Nf3 d5 g3 Nf6 Bg2 e6 O-O Be7 d3 O-O",
+];
+
+/// An opening book, which knows good moves in positions from the start of
+/// the game so computer players don't need to think about them, and don't
+/// play the same opening every game.
+pub struct Book {
+    moves: HashMap<u64, Vec<ChessMove>>,
+}
+
+impl Book {
+    /// The book of openings patzer comes with.
+    pub fn builtin() -> Self {
+        Book::from_lines(OPENINGS).expect("the built-in openings are legal")
+    }
+
+    /// A book of lines of moves in SAN, all played from the starting
+    /// position. Returns the first move which isn't legal if there is one.
+    pub fn from_lines(lines: &[&str]) -> Result<Self, String> {
+        let mut moves: HashMap<u64, Vec<ChessMove>> = HashMap::new();
+
+        for line in lines {
+            let mut board = Board::default();
+
+            for token in line.split_whitespace() {
+                let m = MoveGen::new_legal(&board)
+                    .find(|&m| san(&board, m).trim_end_matches(&['+', '#'][..]) == token)
+                    .ok_or_else(|| format!("illegal move {} in {}", token, line))?;
+
+                let known = moves.entry(board.get_hash()).or_default();
+                if !known.contains(&m) {
+                    known.push(m);
+                }
+                board = board.make_move_new(m);
+            }
+        }
+
+        Ok(Book { moves })
+    }
+
+    /// The book moves for `board`.
+    pub fn moves(&self, board: &Board) -> Vec<ChessMove> {
+        // hashes can collide, so make sure the moves belong here
+        self.moves
+            .get(&board.get_hash())
+            .map(|moves| moves.iter().copied().filter(|&m| board.legal(m)).collect())
+            .unwrap_or_default()
+    }
+
+    /// One of the book moves for `board`, picked at random.
+    pub fn pick(&self, board: &Board) -> Option<ChessMove> {
+        self.moves(board).choose(&mut rand::thread_rng()).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knows_the_openings() {
+        let book = Book::builtin();
+        let start = Board::default();
+
+        let mut first_moves: Vec<String> = book
+            .moves(&start)
+            .into_iter()
+            .map(|m| san(&start, m))
+            .collect();
+        first_moves.sort();
+        assert_eq!(first_moves, vec!["Nf3", "c4", "d4", "e4"]);
+
+        let e4 = start.make_move_new(ChessMove::from_san(&start, "e4").unwrap());
+        assert_eq!(book.moves(&e4).len(), 4);
+        assert!(book.pick(&e4).is_some());
+    }
+
+    #[test]
+    fn rejects_illegal_lines() {
+        assert!(Book::from_lines(&["e4 e5 Ke3"]).is_err());
+    }
+
+    #[test]
+    fn has_nothing_for_unknown_positions() {
+        let book = Book::builtin();
+        let board =
+            Board::default().make_move_new(ChessMove::from_san(&Board::default(), "a3").unwrap());
+
+        assert_eq!(book.pick(&board), None);
+    }
+}
@@ -0,0 +1,448 @@
+use chess::{ChessMove, Color, GameResult};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use crate::clock::TimeControl;
+use crate::game_state::GameState;
+use crate::notation;
+use crate::strategies::{search, SearchInfo, SearchLimits};
+use crate::tablebase::Tablebase;
+
+/// Depth searched when the interface hasn't given us any time information.
+const DEFAULT_DEPTH: u8 = 6;
+
+/// Depth cap when searching against the clock; time runs out well before this.
+const MAX_DEPTH: u8 = 64;
+
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    Continue,
+    Quit,
+}
+
+/// An engine speaking the Chess Engine Communication Protocol, as used by
+/// XBoard and WinBoard. The [protocol
+/// spec](https://www.gnu.org/software/xboard/engine-intf.html) covers all the
+/// commands; only the ones needed to play games are supported here.
+///
+/// Commands are fed in a line at a time through `handle`, and responses are
+/// written to `out`. Searching happens synchronously inside `handle`, so to
+/// interrupt a search (for the `?` command) raise the flag from `stop_handle`
+/// from another thread.
+pub struct Cecp<W: Write> {
+    out: W,
+
+    game_state: GameState,
+
+    /// The color the engine plays, or `None` in force mode.
+    engine_color: Option<Color>,
+
+    post: bool,
+    max_depth: Option<u8>,
+    time_control: TimeControl,
+
+    /// Time left on each side's clock, as the interface last told us with
+    /// `time` and `otim`.
+    engine_time: Option<Duration>,
+    opponent_time: Option<Duration>,
+
+    tablebase: Option<Arc<Tablebase>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl<W: Write> Cecp<W> {
+    pub fn new(out: W) -> Self {
+        Cecp {
+            out,
+            game_state: new_game_state(),
+            engine_color: Some(Color::Black),
+            post: false,
+            max_depth: None,
+            time_control: TimeControl::Unlimited,
+            engine_time: None,
+            opponent_time: None,
+            tablebase: None,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn with_tablebase(mut self, tablebase: Option<Arc<Tablebase>>) -> Self {
+        self.tablebase = tablebase;
+        self
+    }
+
+    /// Flag which makes the current search return its best move so far.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+
+    /// The engine's and the opponent's time left, if the interface has said.
+    pub fn clock_times(&self) -> (Option<Duration>, Option<Duration>) {
+        (self.engine_time, self.opponent_time)
+    }
+
+    pub fn handle(&mut self, line: &str) -> Status {
+        let mut parts = line.split_whitespace();
+        let command = match parts.next() {
+            Some(command) => command,
+            None => return Status::Continue,
+        };
+        let args: Vec<&str> = parts.collect();
+
+        match command {
+            "xboard" | "accepted" | "rejected" | "random" | "easy" | "hard" | "computer"
+            | "name" | "rating" | "ics" | "draw" | "hint" | "bk" => {}
+            "protover" => {
+                self.send(
+                    "feature myname=\"patzer\" usermove=1 setboard=0 ping=1 \
+                     sigint=0 sigterm=0 colors=0 done=1",
+                );
+            }
+            "ping" => {
+                let n = args.first().unwrap_or(&"");
+                self.send(&format!("pong {}", n));
+            }
+            "new" => {
+                self.game_state = new_game_state();
+                self.engine_color = Some(Color::Black);
+                self.max_depth = None;
+                self.time_control = TimeControl::Unlimited;
+                self.engine_time = None;
+                self.opponent_time = None;
+            }
+            "force" | "result" => {
+                self.engine_color = None;
+            }
+            "go" => {
+                self.engine_color = Some(self.game_state.game.side_to_move());
+                self.think();
+            }
+            "usermove" => match args.first() {
+                Some(m) => self.user_move(m),
+                None => self.send("Error (no move given): usermove"),
+            },
+            "undo" => {
+                self.game_state.undo();
+            }
+            "remove" => {
+                self.game_state.undo();
+                self.game_state.undo();
+            }
+            "level" => match parse_level(&args) {
+                Some(time_control) => self.time_control = time_control,
+                None => self.send(&format!("Error (bad time control): {}", line)),
+            },
+            "st" => match args.first().and_then(|s| parse_seconds(s)) {
+                Some(t) => self.time_control = TimeControl::MoveTime(t),
+                None => self.send(&format!("Error (bad time): {}", line)),
+            },
+            "sd" => match args.first().and_then(|s| s.parse().ok()) {
+                Some(depth) => self.max_depth = Some(depth),
+                None => self.send(&format!("Error (bad depth): {}", line)),
+            },
+            "time" => self.engine_time = args.first().and_then(|s| parse_centiseconds(s)),
+            // our opponent's clock doesn't factor into how we budget time,
+            // but it's kept track of along with ours
+            "otim" => self.opponent_time = args.first().and_then(|s| parse_centiseconds(s)),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            // "?" is handled by raising the stop flag
+            "?" => {}
+            "quit" => return Status::Quit,
+            _ => {
+                // Protocol version 1 interfaces send moves without "usermove"
+                if self.parse_move(command).is_some() {
+                    self.user_move(command);
+                } else {
+                    self.send(&format!("Error (unknown command): {}", command));
+                }
+            }
+        }
+
+        Status::Continue
+    }
+
+    fn user_move(&mut self, input: &str) {
+        let m = match self.parse_move(input) {
+            Some(m) if !self.game_state.is_finished() => m,
+            _ => {
+                self.send(&format!("Illegal move: {}", input));
+                return;
+            }
+        };
+
+        self.game_state.make_move(m);
+
+        if !self.report_result() {
+            self.think();
+        }
+    }
+
+    fn parse_move(&self, input: &str) -> Option<ChessMove> {
+        notation::parse_move(&self.game_state.current_position(), input)
+    }
+
+    /// Searches and plays a move if it's the engine's turn.
+    fn think(&mut self) {
+        let board = self.game_state.current_position();
+        if self.engine_color != Some(board.side_to_move()) || self.game_state.is_finished() {
+            return;
+        }
+
+        self.stop.store(false, Ordering::Relaxed);
+
+        let move_time = self.move_time();
+        let limits = SearchLimits {
+            depth: self.max_depth.unwrap_or(match move_time {
+                Some(_) => MAX_DEPTH,
+                None => DEFAULT_DEPTH,
+            }),
+            move_time,
+            stop: Some(&self.stop),
+            ..Default::default()
+        };
+
+        let post = self.post;
+        let out = &mut self.out;
+        let best_move = search(&board, &limits, self.tablebase.as_deref(), |info| {
+            if post {
+                write_thinking(out, info);
+            }
+        });
+
+        match best_move {
+            Some(m) => {
+                self.game_state.make_move(m);
+                self.send(&format!("move {}", m));
+                self.report_result();
+            }
+            None => {
+                self.report_result();
+            }
+        }
+    }
+
+    /// Budgets time for the next move from the clock the interface gave us.
+    fn move_time(&self) -> Option<Duration> {
+        let remaining = match self.time_control {
+            TimeControl::Unlimited => return None,
+            TimeControl::MoveTime(t) => t,
+            TimeControl::Level { .. } => self.engine_time?,
+        };
+        let played = self.game_state.moves().len() as u32 / 2;
+
+        self.time_control.budget(remaining, played)
+    }
+
+    /// Announces the result if the game is over, returning whether it is.
+    fn report_result(&mut self) -> bool {
+        if let Some(reason) = self.game_state.automatic_draw {
+            self.send(&format!("1/2-1/2 {{Draw by {}}}", reason.description()));
+            return true;
+        }
+
+        let result = match self.game_state.game.result() {
+            Some(result) => result,
+            None => return false,
+        };
+
+        let message = match result {
+            GameResult::WhiteCheckmates => "1-0 {White mates}",
+            GameResult::BlackResigns => "1-0 {Black resigns}",
+            GameResult::BlackCheckmates => "0-1 {Black mates}",
+            GameResult::WhiteResigns => "0-1 {White resigns}",
+            GameResult::Stalemate => "1/2-1/2 {Stalemate}",
+            GameResult::DrawDeclared | GameResult::DrawAccepted => "1/2-1/2 {Draw}",
+        };
+        self.send(message);
+
+        true
+    }
+
+    fn send(&mut self, message: &str) {
+        writeln!(self.out, "{}", message).expect("writing to the interface failed");
+        self.out.flush().expect("writing to the interface failed");
+    }
+}
+
+fn new_game_state() -> GameState {
+    GameState::new("Player".into(), "patzer".into())
+}
+
+/// Thinking output is `ply score time nodes pv`, with time in centiseconds.
+fn write_thinking<W: Write>(out: &mut W, info: &SearchInfo) {
+    let pv: Vec<String> = info.pv.iter().map(|m| m.to_string()).collect();
+
+    writeln!(
+        out,
+        "{} {} {} {} {}",
+        info.depth,
+        info.score,
+        info.elapsed.as_millis() / 10,
+        info.nodes,
+        pv.join(" ")
+    )
+    .and_then(|_| out.flush())
+    .expect("writing to the interface failed");
+}
+
+/// Parses `level MPS BASE INC`, where BASE is minutes or `minutes:seconds`
+/// and INC is seconds.
+fn parse_level(args: &[&str]) -> Option<TimeControl> {
+    if args.len() != 3 {
+        return None;
+    }
+
+    let moves = args[0].parse().ok()?;
+    let base = match args[1].split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: u64 = minutes.parse().ok()?;
+            let seconds: u64 = seconds.parse().ok()?;
+            Duration::from_secs(minutes * 60 + seconds)
+        }
+        None => Duration::from_secs_f64(args[1].parse::<f64>().ok()? * 60.0),
+    };
+    let increment = parse_seconds(args[2])?;
+
+    Some(TimeControl::Level {
+        moves,
+        base,
+        increment,
+    })
+}
+
+fn parse_seconds(s: &str) -> Option<Duration> {
+    let seconds: f64 = s.parse().ok()?;
+    if seconds.is_finite() && seconds >= 0.0 {
+        Some(Duration::from_secs_f64(seconds))
+    } else {
+        None
+    }
+}
+
+fn parse_centiseconds(s: &str) -> Option<Duration> {
+    let centiseconds: u64 = s.parse().ok()?;
+    Some(Duration::from_millis(centiseconds * 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(commands: &[&str]) -> (Cecp<Vec<u8>>, String) {
+        let mut cecp = Cecp::new(vec![]);
+        for command in commands {
+            cecp.handle(command);
+        }
+
+        let output = String::from_utf8(cecp.out.clone()).unwrap();
+        (cecp, output)
+    }
+
+    #[test]
+    fn announces_features() {
+        let (_, output) = run(&["xboard", "protover 2", "ping 7"]);
+
+        assert!(output.contains("feature "));
+        assert!(output.contains("usermove=1"));
+        assert!(output.contains("done=1"));
+        assert!(output.ends_with("pong 7\n"));
+    }
+
+    #[test]
+    fn plays_user_moves_in_force_mode() {
+        let (cecp, output) = run(&["new", "force", "usermove e2e4", "usermove e7e5"]);
+
+        assert_eq!(cecp.game_state().moves().len(), 2);
+        assert!(!output.contains("move "));
+    }
+
+    #[test]
+    fn rejects_illegal_moves() {
+        let (cecp, output) = run(&["new", "force", "usermove e2e5"]);
+
+        assert_eq!(output, "Illegal move: e2e5\n");
+        assert!(cecp.game_state().moves().is_empty());
+    }
+
+    #[test]
+    fn replies_to_user_moves() {
+        let (cecp, output) = run(&["new", "sd 2", "post", "usermove e2e4"]);
+
+        assert_eq!(cecp.game_state().moves().len(), 2);
+        assert!(output.lines().any(|l| l.starts_with("1 ")));
+        assert!(output.lines().last().unwrap().starts_with("move "));
+    }
+
+    #[test]
+    fn go_plays_the_side_to_move() {
+        let (cecp, output) = run(&["new", "force", "sd 1", "go"]);
+
+        assert_eq!(cecp.game_state().moves().len(), 1);
+        assert!(output.starts_with("move "));
+        assert_eq!(cecp.engine_color, Some(Color::White));
+    }
+
+    #[test]
+    fn undo_takes_back_moves() {
+        let (cecp, _) = run(&["new", "force", "usermove e2e4", "usermove e7e5", "undo"]);
+        assert_eq!(cecp.game_state().moves().len(), 1);
+
+        let (cecp, _) = run(&["new", "force", "usermove e2e4", "usermove e7e5", "remove"]);
+        assert!(cecp.game_state().moves().is_empty());
+    }
+
+    #[test]
+    fn reports_checkmate() {
+        let (_, output) = run(&[
+            "new",
+            "force",
+            "usermove f2f3",
+            "usermove e7e5",
+            "usermove g2g4",
+            "usermove d8h4",
+        ]);
+
+        assert_eq!(output, "0-1 {Black mates}\n");
+    }
+
+    #[test]
+    fn parses_time_controls() {
+        assert_eq!(
+            parse_level(&["40", "5", "0"]),
+            Some(TimeControl::Level {
+                moves: 40,
+                base: Duration::from_secs(300),
+                increment: Duration::ZERO,
+            })
+        );
+        assert_eq!(
+            parse_level(&["0", "2:30", "1.5"]),
+            Some(TimeControl::Level {
+                moves: 0,
+                base: Duration::from_secs(150),
+                increment: Duration::from_millis(1500),
+            })
+        );
+        assert_eq!(parse_level(&["40", "5"]), None);
+
+        let (cecp, _) = run(&["level 40 5 0", "time 6000", "otim 5000"]);
+        assert_eq!(
+            cecp.clock_times(),
+            (Some(Duration::from_secs(60)), Some(Duration::from_secs(50)))
+        );
+        assert_eq!(cecp.move_time(), Some(Duration::from_millis(1500)));
+
+        let (cecp, _) = run(&["level 40 5 0", "time 6000", "otim 5000", "new"]);
+        assert_eq!(cecp.clock_times(), (None, None));
+
+        let (cecp, _) = run(&["st 5"]);
+        assert_eq!(cecp.move_time(), Some(Duration::from_secs(5)));
+    }
+}
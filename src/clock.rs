@@ -0,0 +1,370 @@
+use chess::Color;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How many moves we assume are left when the time control doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Time held back from every budget, to cover the time it takes to get a move
+/// from a computer player onto the board.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeControl {
+    /// No time control; computer players search to a fixed depth.
+    Unlimited,
+
+    /// `moves` per `base` (0 meaning the whole game), plus `increment` per move.
+    Level {
+        moves: u32,
+        base: Duration,
+        increment: Duration,
+    },
+
+    /// A fixed amount of time for every move.
+    MoveTime(Duration),
+}
+
+impl TimeControl {
+    /// Base plus increment for the whole game, like 5+3 blitz.
+    pub fn incremental(base: Duration, increment: Duration) -> Self {
+        TimeControl::Level {
+            moves: 0,
+            base,
+            increment,
+        }
+    }
+
+    /// How long to think about the next move, given the time `remaining` (for
+    /// `MoveTime`, the time left for this move) and how many moves we've made.
+    pub fn budget(&self, remaining: Duration, moves_played: u32) -> Option<Duration> {
+        match *self {
+            TimeControl::Unlimited => None,
+            TimeControl::MoveTime(_) => Some(remaining),
+            TimeControl::Level {
+                moves, increment, ..
+            } => {
+                let moves_to_go = if moves > 0 {
+                    moves - moves_played % moves
+                } else {
+                    DEFAULT_MOVES_TO_GO
+                };
+
+                let budget = remaining / moves_to_go + increment * 3 / 4;
+                Some(budget.min(remaining / 2))
+            }
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TimeControl::Unlimited => write!(f, "Unlimited"),
+            TimeControl::Level {
+                moves,
+                base,
+                increment,
+            } => {
+                if moves > 0 {
+                    write!(f, "{} moves in ", moves)?;
+                }
+                write!(f, "{}", format_duration(base))?;
+                if increment > Duration::ZERO {
+                    write!(f, " + {}s", increment.as_secs_f64())?;
+                }
+                Ok(())
+            }
+            TimeControl::MoveTime(t) => write!(f, "{}s per move", t.as_secs_f64()),
+        }
+    }
+}
+
+/// A chess clock for both sides. Time is only taken off a side's clock when
+/// it is pressed or stopped; in between, `remaining` works out how much is
+/// left from when the side started thinking.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    time_control: TimeControl,
+
+    /// Time left for each side, by `Color::to_index`, as of when the running
+    /// side started thinking.
+    remaining: [Duration; 2],
+
+    /// How many moves each side has made, for time controls with periods.
+    moves: [u32; 2],
+
+    /// The side whose clock is running, and since when.
+    running: Option<(Color, Instant)>,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        let start = match time_control {
+            TimeControl::Unlimited => Duration::ZERO,
+            TimeControl::Level { base, .. } => base,
+            TimeControl::MoveTime(t) => t,
+        };
+
+        Clock {
+            time_control,
+            remaining: [start; 2],
+            moves: [0; 2],
+            running: None,
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    /// The side whose clock is running, if any.
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    /// Time left for `color`, or `None` without a time control.
+    pub fn remaining(&self, color: Color) -> Option<Duration> {
+        self.remaining_at(color, Instant::now())
+    }
+
+    fn remaining_at(&self, color: Color, now: Instant) -> Option<Duration> {
+        if self.time_control == TimeControl::Unlimited {
+            return None;
+        }
+
+        let remaining = self.remaining[color.to_index()];
+        Some(match self.running {
+            Some((running, since)) if running == color => {
+                remaining.saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => remaining,
+        })
+    }
+
+    /// Starts `color`'s clock, as at the start of a game.
+    pub fn start(&mut self, color: Color) {
+        self.start_at(color, Instant::now());
+    }
+
+    fn start_at(&mut self, color: Color, now: Instant) {
+        self.stop_at(now);
+        self.running = Some((color, now));
+    }
+
+    /// Stops whichever clock is running, as at the end of a game.
+    pub fn stop(&mut self) {
+        self.stop_at(Instant::now());
+    }
+
+    fn stop_at(&mut self, now: Instant) {
+        if let Some((color, _)) = self.running {
+            if let Some(remaining) = self.remaining_at(color, now) {
+                self.remaining[color.to_index()] = remaining;
+            }
+            self.running = None;
+        }
+    }
+
+    /// Called once `color` has moved: takes the time it used, adds any
+    /// increment or new period, and starts the opponent's clock.
+    pub fn press(&mut self, color: Color) {
+        self.press_at(color, Instant::now());
+    }
+
+    fn press_at(&mut self, color: Color, now: Instant) {
+        self.stop_at(now);
+
+        let index = color.to_index();
+        self.moves[index] += 1;
+
+        match self.time_control {
+            TimeControl::Unlimited => {}
+            TimeControl::Level {
+                moves,
+                base,
+                increment,
+            } => {
+                self.remaining[index] += increment;
+                if moves > 0 && self.moves[index].is_multiple_of(moves) {
+                    self.remaining[index] += base;
+                }
+            }
+            TimeControl::MoveTime(t) => self.remaining[index] = t,
+        }
+
+        self.start_at(!color, now);
+    }
+
+    /// The side which has run out of time, if any.
+    pub fn flagged(&self) -> Option<Color> {
+        self.flagged_at(Instant::now())
+    }
+
+    fn flagged_at(&self, now: Instant) -> Option<Color> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|&color| self.remaining_at(color, now) == Some(Duration::ZERO))
+    }
+
+    /// Moves left for `color` until the next period starts, if the time
+    /// control has periods.
+    pub fn moves_to_go(&self, color: Color) -> Option<u32> {
+        match self.time_control {
+            TimeControl::Level { moves, .. } if moves > 0 => {
+                Some(moves - self.moves[color.to_index()] % moves)
+            }
+            _ => None,
+        }
+    }
+
+    /// How long `color` should think about its next move, for computer
+    /// players. `None` means there's no time control to budget for.
+    pub fn budget(&self, color: Color) -> Option<Duration> {
+        let remaining = self.remaining(color)?.saturating_sub(MOVE_OVERHEAD);
+        self.time_control
+            .budget(remaining, self.moves[color.to_index()])
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new(TimeControl::Unlimited)
+    }
+}
+
+/// Formats a clock reading as minutes and seconds, with tenths of a second
+/// once time is running short.
+pub fn format_duration(d: Duration) -> String {
+    let seconds = d.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, d.subsec_millis() / 100)
+    } else if seconds < 3600 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn ticks_for_the_side_to_move() {
+        let mut clock = Clock::new(TimeControl::incremental(secs(60), secs(2)));
+        let start = Instant::now();
+
+        clock.start_at(Color::White, start);
+        assert_eq!(
+            clock.remaining_at(Color::White, start + secs(10)),
+            Some(secs(50))
+        );
+        assert_eq!(
+            clock.remaining_at(Color::Black, start + secs(10)),
+            Some(secs(60))
+        );
+
+        clock.press_at(Color::White, start + secs(10));
+        assert_eq!(clock.running(), Some(Color::Black));
+        assert_eq!(
+            clock.remaining_at(Color::White, start + secs(15)),
+            Some(secs(52))
+        );
+        assert_eq!(
+            clock.remaining_at(Color::Black, start + secs(15)),
+            Some(secs(55))
+        );
+
+        clock.stop_at(start + secs(20));
+        assert_eq!(clock.running(), None);
+        assert_eq!(
+            clock.remaining_at(Color::Black, start + secs(100)),
+            Some(secs(50))
+        );
+    }
+
+    #[test]
+    fn adds_time_for_each_period() {
+        let mut clock = Clock::new(TimeControl::Level {
+            moves: 2,
+            base: secs(60),
+            increment: Duration::ZERO,
+        });
+        let start = Instant::now();
+
+        clock.start_at(Color::White, start);
+        clock.press_at(Color::White, start + secs(10));
+        clock.press_at(Color::Black, start + secs(10));
+        assert_eq!(
+            clock.remaining_at(Color::White, start + secs(10)),
+            Some(secs(50))
+        );
+
+        clock.press_at(Color::White, start + secs(20));
+        assert_eq!(
+            clock.remaining_at(Color::White, start + secs(20)),
+            Some(secs(100))
+        );
+    }
+
+    #[test]
+    fn resets_fixed_move_times() {
+        let mut clock = Clock::new(TimeControl::MoveTime(secs(5)));
+        let start = Instant::now();
+
+        clock.start_at(Color::White, start);
+        clock.press_at(Color::White, start + secs(3));
+        assert_eq!(
+            clock.remaining_at(Color::White, start + secs(3)),
+            Some(secs(5))
+        );
+        assert_eq!(clock.flagged_at(start + secs(7)), None);
+        assert_eq!(clock.flagged_at(start + secs(8)), Some(Color::Black));
+    }
+
+    #[test]
+    fn never_flags_without_a_time_control() {
+        let mut clock = Clock::default();
+        let start = Instant::now();
+
+        clock.start_at(Color::White, start);
+        assert_eq!(clock.remaining_at(Color::White, start + secs(1000)), None);
+        assert_eq!(clock.flagged_at(start + secs(1000)), None);
+        assert_eq!(clock.budget(Color::White), None);
+    }
+
+    #[test]
+    fn budgets_from_remaining_time() {
+        let level = TimeControl::Level {
+            moves: 40,
+            base: secs(300),
+            increment: Duration::ZERO,
+        };
+        assert_eq!(level.budget(secs(100), 30), Some(secs(10)));
+
+        let incremental = TimeControl::incremental(secs(60), secs(4));
+        assert_eq!(incremental.budget(secs(60), 0), Some(secs(5)));
+        assert_eq!(incremental.budget(secs(4), 0), Some(secs(2)));
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_millis(9_450)), "0:09.4");
+        assert_eq!(format_duration(secs(754)), "12:34");
+        assert_eq!(format_duration(secs(5400)), "1:30:00");
+        assert_eq!(
+            TimeControl::incremental(secs(180), secs(2)).to_string(),
+            "3:00 + 2s"
+        );
+    }
+}
@@ -0,0 +1,524 @@
+use chess::{Board, Color, Game};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::game_state::GameState;
+use crate::player::{Decision, Player};
+
+/// Called whenever the game changes, like `egui::Context::request_repaint`.
+pub type Notifier = Arc<dyn Fn() + Send + Sync>;
+
+/// Something that happened to the game, sent to the controller by the UI or
+/// by the players' threads.
+pub enum Event {
+    /// `color` decides on something at the board in `position`, like a move.
+    /// This is handed to the player, so it only counts for human players.
+    Submit {
+        color: Color,
+        position: Board,
+        decision: Decision,
+    },
+
+    /// A player decided what to do about `request`. Decisions for requests
+    /// which have since gone stale (after a takeback, say) are ignored.
+    Decision {
+        color: Color,
+        request: u64,
+        decision: Decision,
+    },
+
+    Resign(Color),
+
+    /// `color` offers a draw, which they can do at any time.
+    OfferDraw(Color),
+
+    /// `color` turns down their opponent's draw offer.
+    DeclineDraw(Color),
+
+    /// The game was changed from outside the controller, such as by starting
+    /// it or taking back a move, so check whose move it is.
+    Refresh,
+
+    /// A player was replaced, so the players' threads need restarting.
+    PlayersChanged,
+
+    Shutdown,
+}
+
+/// A request for a player to decide on their move.
+struct MoveRequest {
+    id: u64,
+    game: Game,
+    start_position: Board,
+    clock: Clock,
+    draw_offered: bool,
+    can_claim_draw: bool,
+}
+
+/// Runs games between players. Each player thinks on a thread of its own,
+/// and is sent requests to move over a channel when it's their turn; their
+/// decisions come back as events. Humans are players like any other, whose
+/// moves are handed to them from the UI.
+/// After each change to the game, the notifier is called, so the UI only
+/// needs to redraw when something has actually happened.
+///
+/// The controller works on a shared `GameState`, which is fine to read from
+/// anywhere. Changes to it from outside should be followed by an
+/// `Event::Refresh`.
+#[derive(Clone)]
+pub struct GameController {
+    events: Sender<Event>,
+    notifier: Arc<Mutex<Option<Notifier>>>,
+}
+
+impl GameController {
+    pub fn spawn(
+        game_state: Arc<Mutex<GameState>>,
+        white: Arc<Mutex<Arc<dyn Player>>>,
+        black: Arc<Mutex<Arc<dyn Player>>>,
+    ) -> Self {
+        let (events, receiver) = mpsc::channel();
+        let notifier = Arc::new(Mutex::new(None));
+
+        let current = [white.lock().unwrap().clone(), black.lock().unwrap().clone()];
+        let mut runner = Runner {
+            game_state,
+            players: [white, black],
+            current,
+            requests: [None, None],
+            pending: None,
+            next_request: 0,
+            game_over: false,
+            events: events.clone(),
+            notifier: notifier.clone(),
+        };
+        thread::spawn(move || runner.run(receiver));
+
+        GameController { events, notifier }
+    }
+
+    pub fn set_notifier(&self, notifier: Notifier) {
+        *self.notifier.lock().unwrap() = Some(notifier);
+    }
+
+    pub fn send(&self, event: Event) {
+        // the only way for this to fail is if the controller has shut down
+        self.events.send(event).ok();
+    }
+}
+
+/// A request which has been sent to a player and not yet answered.
+#[derive(Clone, Copy)]
+struct Pending {
+    id: u64,
+    color: Color,
+    position: Board,
+    draw_offered: bool,
+}
+
+struct Runner {
+    game_state: Arc<Mutex<GameState>>,
+
+    /// Players by `Color::to_index`, as chosen in the UI.
+    players: [Arc<Mutex<Arc<dyn Player>>>; 2],
+
+    /// The players the threads were started for.
+    current: [Arc<dyn Player>; 2],
+
+    /// Where to send move requests for each player.
+    requests: [Option<Sender<MoveRequest>>; 2],
+
+    /// The request the side to move is thinking about, so that it isn't
+    /// asked again while it thinks.
+    pending: Option<Pending>,
+    next_request: u64,
+
+    /// Whether the players have been told the game is over.
+    game_over: bool,
+
+    events: Sender<Event>,
+    notifier: Arc<Mutex<Option<Notifier>>>,
+}
+
+impl Runner {
+    fn run(&mut self, receiver: Receiver<Event>) {
+        self.start_players();
+
+        loop {
+            self.request_move();
+
+            // wake up when the side to move runs out of time, if nothing else
+            let event = match self.time_to_flag() {
+                Some(timeout) => match receiver.recv_timeout(timeout) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => Event::Refresh,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match receiver.recv() {
+                    Ok(event) => event,
+                    Err(_) => break,
+                },
+            };
+
+            match event {
+                Event::Submit {
+                    color,
+                    position,
+                    decision,
+                } => {
+                    self.current[color.to_index()].submit(position, decision);
+                }
+                Event::Decision {
+                    color,
+                    request,
+                    decision,
+                } => self.decide(color, request, decision),
+                Event::Resign(color) => {
+                    self.game_state.lock().unwrap().resign(color);
+                }
+                Event::OfferDraw(color) => {
+                    self.game_state.lock().unwrap().offer_draw(color);
+                }
+                Event::DeclineDraw(color) => {
+                    self.game_state.lock().unwrap().decline_draw(color);
+                }
+                Event::Refresh => {
+                    self.game_state.lock().unwrap().check_flag();
+                }
+                Event::PlayersChanged => self.start_players(),
+                Event::Shutdown => break,
+            }
+
+            self.stop_stale();
+            self.notify();
+        }
+
+        if let Some(pending) = self.pending.take() {
+            self.current[pending.color.to_index()].stop();
+        }
+    }
+
+    /// Starts a thread for each player, which waits for requests to move.
+    /// Dropping the request sender stops the thread once it has finished any
+    /// `play` in progress.
+    fn start_players(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            self.current[pending.color.to_index()].stop();
+        }
+
+        for color in [Color::White, Color::Black] {
+            let player = self.players[color.to_index()].lock().unwrap().clone();
+            let (requests, receiver) = mpsc::channel::<MoveRequest>();
+            let events = self.events.clone();
+
+            self.current[color.to_index()] = player.clone();
+            self.requests[color.to_index()] = Some(requests);
+
+            thread::spawn(move || {
+                for request in receiver {
+                    let decision = player.play(
+                        &request.game,
+                        &request.start_position,
+                        &request.clock,
+                        request.draw_offered,
+                        request.can_claim_draw,
+                    );
+
+                    let event = Event::Decision {
+                        color,
+                        request: request.id,
+                        decision,
+                    };
+                    if events.send(event).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    /// Asks the side to move for a decision, if it hasn't been asked already.
+    fn request_move(&mut self) {
+        let game_state = self.game_state.lock().unwrap();
+        if !game_state.started || game_state.is_finished() || self.pending.is_some() {
+            return;
+        }
+
+        let pending = Pending {
+            id: self.next_request,
+            color: game_state.game.side_to_move(),
+            position: game_state.current_position(),
+            draw_offered: game_state.draw_offered(),
+        };
+        let request = MoveRequest {
+            id: pending.id,
+            game: game_state.game.clone(),
+            start_position: game_state.start_position,
+            clock: game_state.clock.clone(),
+            draw_offered: pending.draw_offered,
+            can_claim_draw: game_state.can_claim_draw(),
+        };
+        self.next_request += 1;
+
+        let sent = match &self.requests[pending.color.to_index()] {
+            Some(requests) => requests.send(request).is_ok(),
+            None => false,
+        };
+        if sent {
+            self.pending = Some(pending);
+        }
+    }
+
+    fn decide(&mut self, color: Color, request: u64, decision: Decision) {
+        match self.pending {
+            Some(pending) if pending.id == request => self.pending = None,
+            _ => return,
+        }
+
+        let player = self.current[color.to_index()].clone();
+        let mut game_state = self.game_state.lock().unwrap();
+
+        let accepted = match decision {
+            Decision::Move(m) => game_state.make_move(m),
+            Decision::MoveAndOfferDraw(m) => {
+                let made = game_state.make_move(m);
+                if made {
+                    game_state.offer_draw(color);
+                }
+                made
+            }
+            Decision::AcceptDraw => game_state.accept_draw(color),
+            Decision::ClaimDraw => game_state.claim_draw(color),
+            // humans resign from the board instead, so from them this only
+            // means they were stopped
+            Decision::Resign if player.is_human() => return,
+            Decision::Resign => {
+                game_state.resign(color);
+                true
+            }
+        };
+
+        // humans get to try again, but a computer making an illegal move (or
+        // claiming a draw it can't) forfeits
+        if !accepted {
+            if !player.is_human() {
+                game_state.forfeit(color);
+            }
+            return;
+        }
+
+        if let Decision::Move(m) | Decision::MoveAndOfferDraw(m) = decision {
+            let game = game_state.game.clone();
+            let clock = game_state.clock.clone();
+            drop(game_state);
+
+            self.current[(!color).to_index()].opponent_moved(&game, m);
+            player.ponder(&game, &clock);
+        }
+    }
+
+    /// Stops a player thinking about a position the game has left, or about
+    /// whether to take a draw offer that has since changed, and tells the
+    /// players once the game is over.
+    fn stop_stale(&mut self) {
+        let game_state = self.game_state.lock().unwrap();
+
+        if let Some(pending) = self.pending {
+            if !game_state.started
+                || game_state.is_finished()
+                || game_state.current_position() != pending.position
+                || game_state.draw_offered() != pending.draw_offered
+            {
+                self.pending = None;
+                self.current[pending.color.to_index()].stop();
+            }
+        }
+
+        match game_state.outcome() {
+            Some(outcome) if !self.game_over => {
+                self.game_over = true;
+                drop(game_state);
+                for player in self.current.iter() {
+                    player.game_over(outcome);
+                }
+            }
+            Some(_) => {}
+            None => self.game_over = false,
+        }
+    }
+
+    /// How long until the side to move runs out of time, if it can.
+    fn time_to_flag(&self) -> Option<Duration> {
+        let game_state = self.game_state.lock().unwrap();
+        if !game_state.started || game_state.is_finished() {
+            return None;
+        }
+
+        let color = game_state.clock.running()?;
+        let remaining = game_state.clock.remaining(color)?;
+        Some(remaining + Duration::from_millis(1))
+    }
+
+    fn notify(&self) {
+        if let Some(notifier) = self.notifier.lock().unwrap().as_ref() {
+            notifier();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TimeControl;
+    use crate::engine_config::{EngineConfig, Strategy};
+    use crate::game_state::Outcome;
+    use crate::player::HumanPlayer;
+    use chess::{ChessMove, GameResult};
+    use std::str::FromStr;
+
+    struct Harness {
+        game_state: Arc<Mutex<GameState>>,
+        controller: GameController,
+        changes: Receiver<()>,
+    }
+
+    fn harness(board: Board, white: Arc<dyn Player>, black: Arc<dyn Player>) -> Harness {
+        let game_state = Arc::new(Mutex::new(GameState::with_position(
+            white.name(),
+            black.name(),
+            board,
+        )));
+        let controller = GameController::spawn(
+            game_state.clone(),
+            Arc::new(Mutex::new(white)),
+            Arc::new(Mutex::new(black)),
+        );
+
+        let (sender, changes) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        controller.set_notifier(Arc::new(move || {
+            sender.lock().unwrap().send(()).ok();
+        }));
+
+        Harness {
+            game_state,
+            controller,
+            changes,
+        }
+    }
+
+    impl Harness {
+        fn start(&self) {
+            self.game_state.lock().unwrap().start();
+            self.controller.send(Event::Refresh);
+        }
+
+        /// Waits for changes until `done` holds for the game.
+        fn wait_for(&self, done: impl Fn(&GameState) -> bool) {
+            while !done(&self.game_state.lock().unwrap()) {
+                self.changes
+                    .recv_timeout(Duration::from_secs(10))
+                    .expect("the game stopped changing");
+            }
+        }
+    }
+
+    fn computer(strategy: Strategy) -> Arc<dyn Player> {
+        EngineConfig::new(strategy).build(None).unwrap()
+    }
+
+    fn human(name: &str) -> Arc<dyn Player> {
+        Arc::new(HumanPlayer::new(name))
+    }
+
+    #[test]
+    fn asks_computers_to_move() {
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let game = harness(
+            board,
+            computer(Strategy::AlphaBeta),
+            computer(Strategy::FirstLegalMove),
+        );
+
+        game.start();
+        game.wait_for(GameState::is_finished);
+
+        let game_state = game.game_state.lock().unwrap();
+        assert_eq!(game_state.outcome(), Some(Outcome::Win(Color::White)));
+        assert_eq!(game_state.moves().len(), 1);
+    }
+
+    #[test]
+    fn plays_human_moves_and_ignores_stale_ones() {
+        let game = harness(
+            Board::default(),
+            human("white"),
+            computer(Strategy::FirstLegalMove),
+        );
+        game.start();
+
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+        let d4 = ChessMove::from_str("d2d4").unwrap();
+        game.controller.send(Event::Submit {
+            color: Color::White,
+            position: Board::default(),
+            decision: Decision::Move(e4),
+        });
+        game.wait_for(|g| g.moves().len() == 2);
+
+        // the position this was meant for has gone
+        game.controller.send(Event::Submit {
+            color: Color::White,
+            position: Board::default(),
+            decision: Decision::Move(d4),
+        });
+        game.controller.send(Event::Resign(Color::White));
+        game.wait_for(GameState::is_finished);
+
+        let game_state = game.game_state.lock().unwrap();
+        assert_eq!(game_state.moves()[0], e4);
+        assert_eq!(game_state.moves().len(), 2);
+        assert_eq!(game_state.outcome(), Some(Outcome::Win(Color::Black)));
+    }
+
+    #[test]
+    fn passes_draw_offers_on() {
+        let board = Board::from_str("4k3/8/8/8/8/8/8/QQ2K3 w - - 0 1").unwrap();
+        let losing = EngineConfig {
+            depth: 2,
+            ..EngineConfig::new(Strategy::AlphaBeta)
+        };
+        let game = harness(board, human("white"), losing.build(None).unwrap());
+        game.start();
+
+        game.controller.send(Event::Submit {
+            color: Color::White,
+            position: board,
+            decision: Decision::MoveAndOfferDraw(ChessMove::from_str("e1d1").unwrap()),
+        });
+        game.wait_for(GameState::is_finished);
+
+        let game_state = game.game_state.lock().unwrap();
+        assert_eq!(game_state.game.result(), Some(GameResult::DrawAccepted));
+        assert_eq!(game_state.moves().len(), 1);
+    }
+
+    #[test]
+    fn flags_without_being_prodded() {
+        let game = harness(Board::default(), human("white"), human("black"));
+        game.game_state
+            .lock()
+            .unwrap()
+            .set_time_control(TimeControl::MoveTime(Duration::from_millis(50)));
+        game.start();
+
+        game.wait_for(GameState::is_finished);
+        assert_eq!(
+            game.game_state.lock().unwrap().flag_fall,
+            Some(Color::White)
+        );
+    }
+}
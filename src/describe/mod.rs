@@ -0,0 +1,226 @@
+use chess::{
+    BitBoard, Board, BoardStatus, ChessMove, Color, File, MoveGen, Piece, Square, ALL_PIECES,
+};
+
+/// What's on a square, the way a screen reader should say it, like
+/// "e4, white knight" or "e4, empty".
+pub fn square(board: &Board, square: Square) -> String {
+    match (board.piece_on(square), board.color_on(square)) {
+        (Some(piece), Some(color)) => {
+            format!("{}, {} {}", square, color_name(color), piece_name(piece))
+        }
+        _ => format!("{}, empty", square),
+    }
+}
+
+/// A move spoken rather than written, like "knight takes e5, check". The
+/// move must be legal on `board`.
+pub fn spoken_move(board: &Board, m: ChessMove) -> String {
+    let source = m.get_source();
+    let dest = m.get_dest();
+    let piece = board
+        .piece_on(source)
+        .expect("move must start on an occupied square");
+
+    let file_distance =
+        (source.get_file().to_index() as i32 - dest.get_file().to_index() as i32).abs();
+    let mut words = if piece == Piece::King && file_distance == 2 {
+        if dest.get_file() == File::G {
+            "castles kingside".to_string()
+        } else {
+            "castles queenside".to_string()
+        }
+    } else {
+        // pawns only change files when capturing
+        let en_passant = piece == Piece::Pawn
+            && source.get_file() != dest.get_file()
+            && board.piece_on(dest).is_none();
+        let capture = board.piece_on(dest).is_some() || en_passant;
+
+        let mut words = piece_name(piece).to_string();
+        if is_ambiguous(board, m, piece) {
+            words.push_str(&format!(" on {}", source));
+        }
+        words.push_str(if capture { " takes " } else { " to " });
+        words.push_str(&dest.to_string());
+        if en_passant {
+            words.push_str(" en passant");
+        }
+        if let Some(promotion) = m.get_promotion() {
+            words.push_str(&format!(", promotes to {}", piece_name(promotion)));
+        }
+        words
+    };
+
+    let after = board.make_move_new(m);
+    if after.status() == BoardStatus::Checkmate {
+        words.push_str(", checkmate");
+    } else if after.checkers().popcnt() > 0 {
+        words.push_str(", check");
+    }
+
+    words
+}
+
+/// Lists the pieces of each side, then says whose move it is, like
+/// "White: king on e1, pawn on e4. Black: king on e8. Black to move."
+pub fn position(board: &Board) -> String {
+    let mut sentences: Vec<String> = [Color::White, Color::Black]
+        .iter()
+        .map(|&color| format!("{}: {}.", capitalized(color), pieces(board, color)))
+        .collect();
+
+    let to_move = board.side_to_move();
+    sentences.push(match board.status() {
+        BoardStatus::Checkmate => format!("{} is checkmated.", capitalized(to_move)),
+        BoardStatus::Stalemate => format!("{} is stalemated.", capitalized(to_move)),
+        BoardStatus::Ongoing if board.checkers().popcnt() > 0 => {
+            format!("{} to move, in check.", capitalized(to_move))
+        }
+        BoardStatus::Ongoing => format!("{} to move.", capitalized(to_move)),
+    });
+
+    sentences.join(" ")
+}
+
+/// A side's pieces, most valuable first, like "king on g1, rooks on a1
+/// and f1".
+fn pieces(board: &Board, color: Color) -> String {
+    let mut groups = vec![];
+
+    // kings first, then down in value to the pawns
+    let mut order = ALL_PIECES;
+    order.reverse();
+    for piece in order {
+        let mut squares: Vec<Square> =
+            (board.pieces(piece) & board.color_combined(color)).collect();
+        if squares.is_empty() {
+            continue;
+        }
+        // along the board, the way you'd read the pawns off
+        squares.sort_by_key(|s| (s.get_file().to_index(), s.get_rank().to_index()));
+
+        let names: Vec<String> = squares.iter().map(|s| s.to_string()).collect();
+        let plural = if names.len() > 1 { "s" } else { "" };
+        groups.push(format!(
+            "{}{} on {}",
+            piece_name(piece),
+            plural,
+            and_list(&names)
+        ));
+    }
+
+    groups.join(", ")
+}
+
+/// Whether another piece of the same kind could also move to `m`'s
+/// destination, so saying which square it came from helps.
+fn is_ambiguous(board: &Board, m: ChessMove, piece: Piece) -> bool {
+    let mut moves = MoveGen::new_legal(board);
+    moves.set_iterator_mask(BitBoard::from_square(m.get_dest()));
+    moves.any(|other| {
+        other.get_source() != m.get_source() && board.piece_on(other.get_source()) == Some(piece)
+    })
+}
+
+fn and_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+pub fn piece_name(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "pawn",
+        Piece::Knight => "knight",
+        Piece::Bishop => "bishop",
+        Piece::Rook => "rook",
+        Piece::Queen => "queen",
+        Piece::King => "king",
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+fn capitalized(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn spoken(fen: &str, uci: &str) -> String {
+        let board = Board::from_str(fen).unwrap();
+        spoken_move(&board, ChessMove::from_str(uci).unwrap())
+    }
+
+    #[test]
+    fn describes_squares() {
+        let board = Board::default();
+        assert_eq!(square(&board, Square::G1), "g1, white knight");
+        assert_eq!(square(&board, Square::D8), "d8, black queen");
+        assert_eq!(square(&board, Square::E4), "e4, empty");
+    }
+
+    #[test]
+    fn speaks_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(spoken(start, "e2e4"), "pawn to e4");
+        assert_eq!(spoken(start, "g1f3"), "knight to f3");
+
+        assert_eq!(
+            spoken("4k3/8/8/4p3/8/3N4/8/4K3 w - - 0 1", "d3e5"),
+            "knight takes e5"
+        );
+        assert_eq!(
+            spoken("8/5k2/8/4p3/8/3N4/8/4K3 w - - 0 1", "d3e5"),
+            "knight takes e5, check"
+        );
+        assert_eq!(
+            spoken("4k3/8/8/4r3/8/3N4/8/4K3 w - - 0 1", "d3f4"),
+            "knight to f4"
+        );
+        assert_eq!(
+            spoken("3k4/8/8/8/8/2N1N3/8/4K3 w - - 0 1", "c3d5"),
+            "knight on c3 to d5"
+        );
+        assert_eq!(
+            spoken("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"),
+            "pawn takes d6 en passant"
+        );
+        assert_eq!(
+            spoken("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q"),
+            "pawn to e8, promotes to queen, check"
+        );
+        assert_eq!(
+            spoken("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1g1"),
+            "castles kingside"
+        );
+        assert_eq!(
+            spoken("6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1", "a1a8"),
+            "rook to a8, checkmate"
+        );
+    }
+
+    #[test]
+    fn describes_positions() {
+        let board = Board::from_str("6k1/5ppp/8/8/4P3/8/8/R3K2R b KQ - 0 1").unwrap();
+        assert_eq!(
+            position(&board),
+            "White: king on e1, rooks on a1 and h1, pawn on e4. \
+             Black: king on g8, pawns on f7, g7 and h7. Black to move."
+        );
+    }
+}
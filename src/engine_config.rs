@@ -0,0 +1,337 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::book::Book;
+use crate::evaluation::Evaluator;
+use crate::player::{AlphaBetaPlayer, Player, StrategyPlayer};
+use crate::strategies::{first_legal_move, random_move};
+use crate::tablebase::Tablebase;
+use crate::uci::{UciEngine, UciLimits};
+
+/// Depth used for alpha-beta when nothing else is configured.
+pub const DEFAULT_DEPTH: u8 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    RandomMove,
+    FirstLegalMove,
+    AlphaBeta,
+    Uci,
+}
+
+/// Everything needed to build a computer `Player`.
+///
+/// Configurations can be written as short specs for use on the command line:
+/// `random_move`, `first_legal_move`, or `alpha_beta` with optional
+/// `depth=N` and `movetime=MS` settings, as in `alpha_beta:depth=4,movetime=500`.
+/// Alpha-beta can also be given a transposition table size with `hash=MB`,
+/// a number of search threads with `threads=N`, an opening book with
+/// `book=true`, and a cruder evaluation with `eval=material`.
+/// External UCI engines take the command to run along with the same
+/// settings (except `eval`), as in `uci:cmd=/usr/bin/stockfish,movetime=100`.
+/// Either can be left pondering on the opponent's time with `ponder=true`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineConfig {
+    pub strategy: Strategy,
+    pub depth: u8,
+    pub move_time: Option<Duration>,
+
+    /// The engine to run, for `Strategy::Uci`.
+    pub command: Option<String>,
+
+    /// Whether the engine thinks on the opponent's time.
+    pub ponder: bool,
+
+    pub evaluator: Evaluator,
+
+    /// Roughly how much memory the transposition table takes up, or `None`
+    /// for the engine's default.
+    pub hash_mb: Option<usize>,
+
+    pub threads: usize,
+
+    /// Whether to play from an opening book: patzer's own, or for UCI
+    /// engines, whichever the engine has.
+    pub book: bool,
+}
+
+impl EngineConfig {
+    pub fn new(strategy: Strategy) -> Self {
+        EngineConfig {
+            strategy,
+            depth: DEFAULT_DEPTH,
+            move_time: None,
+            command: None,
+            ponder: false,
+            evaluator: Evaluator::default(),
+            hash_mb: None,
+            threads: 1,
+            book: false,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self.strategy {
+            Strategy::RandomMove => "Random move".into(),
+            Strategy::FirstLegalMove => "First legal move".into(),
+            Strategy::AlphaBeta => match self.move_time {
+                Some(t) => format!("Negamax alpha-beta ({} ms)", t.as_millis()),
+                None => format!("Negamax alpha-beta (depth {})", self.depth),
+            },
+            Strategy::Uci => format!("UCI engine ({})", self.command.as_deref().unwrap_or("")),
+        }
+    }
+
+    /// Builds the player, which starts the engine process for UCI engines.
+    pub fn build(&self, tablebase: Option<Arc<Tablebase>>) -> io::Result<Arc<dyn Player>> {
+        let name = self.name();
+
+        Ok(match self.strategy {
+            Strategy::RandomMove => Arc::new(StrategyPlayer::new(&name, random_move)),
+            Strategy::FirstLegalMove => Arc::new(StrategyPlayer::new(&name, first_legal_move)),
+            Strategy::AlphaBeta => Arc::new(
+                AlphaBetaPlayer::new(&name, self.depth, self.move_time, tablebase)
+                    .evaluator(self.evaluator)
+                    .hash_mb(self.hash_mb)
+                    .threads(self.threads)
+                    .book(self.book.then(Book::builtin))
+                    .ponders(self.ponder),
+            ),
+            Strategy::Uci => {
+                // the command may come with arguments for the engine
+                let command = self.command.as_deref().unwrap_or_default();
+                let mut parts = command.split_whitespace();
+                let program = parts.next().unwrap_or_default();
+                let args: Vec<&str> = parts.collect();
+
+                let mut engine = UciEngine::spawn(program, &args)?;
+                engine.set_limits(UciLimits {
+                    depth: self.move_time.is_none().then_some(self.depth),
+                    move_time: self.move_time,
+                });
+                if let Some(hash_mb) = self.hash_mb {
+                    engine.set_option("Hash", &hash_mb.to_string())?;
+                }
+                if self.threads > 1 {
+                    engine.set_option("Threads", &self.threads.to_string())?;
+                }
+                if self.book {
+                    engine.set_option("OwnBook", "true")?;
+                }
+                if self.ponder {
+                    engine.enable_pondering()?;
+                }
+                Arc::new(engine)
+            }
+        })
+    }
+}
+
+impl FromStr for EngineConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (strategy, settings) = s.split_once(':').unwrap_or((s, ""));
+
+        let mut config = EngineConfig::new(match strategy {
+            "random_move" => Strategy::RandomMove,
+            "first_legal_move" => Strategy::FirstLegalMove,
+            "alpha_beta" => Strategy::AlphaBeta,
+            "uci" => Strategy::Uci,
+            _ => return Err(format!("unknown strategy: {}", strategy)),
+        });
+
+        for setting in settings.split(',').filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got: {}", setting))?;
+
+            match key {
+                "depth" => {
+                    config.depth = value
+                        .parse()
+                        .ok()
+                        .filter(|&d| d > 0)
+                        .ok_or_else(|| format!("invalid depth: {}", value))?;
+                }
+                "movetime" => {
+                    let ms = value
+                        .parse()
+                        .map_err(|_| format!("invalid movetime: {}", value))?;
+                    config.move_time = Some(Duration::from_millis(ms));
+                }
+                "cmd" => config.command = Some(value.to_string()),
+                "ponder" => {
+                    config.ponder = value
+                        .parse()
+                        .map_err(|_| format!("invalid ponder: {}", value))?;
+                }
+                "eval" => {
+                    config.evaluator = Evaluator::ALL
+                        .into_iter()
+                        .find(|evaluator| evaluator.spec() == value)
+                        .ok_or_else(|| format!("unknown eval: {}", value))?;
+                }
+                "hash" => {
+                    let mb = value
+                        .parse()
+                        .ok()
+                        .filter(|&mb| mb > 0)
+                        .ok_or_else(|| format!("invalid hash: {}", value))?;
+                    config.hash_mb = Some(mb);
+                }
+                "threads" => {
+                    config.threads = value
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("invalid threads: {}", value))?;
+                }
+                "book" => {
+                    config.book = value
+                        .parse()
+                        .map_err(|_| format!("invalid book: {}", value))?;
+                }
+                _ => return Err(format!("unknown setting: {}", key)),
+            }
+        }
+
+        match config.strategy {
+            Strategy::AlphaBeta => {}
+            Strategy::Uci if config.command.is_none() => {
+                return Err("uci needs the engine to run, like uci:cmd=stockfish".into());
+            }
+            Strategy::Uci => {}
+            _ if !settings.is_empty() => {
+                return Err(format!("{} doesn't take any settings", strategy));
+            }
+            _ => {}
+        }
+        if config.strategy != Strategy::Uci && config.command.is_some() {
+            return Err(format!("{} doesn't take a command", strategy));
+        }
+        if !matches!(config.strategy, Strategy::AlphaBeta | Strategy::Uci) && config.ponder {
+            return Err(format!("{} doesn't ponder", strategy));
+        }
+        if config.strategy != Strategy::AlphaBeta && config.evaluator != Evaluator::default() {
+            return Err(format!("{} doesn't take an eval", strategy));
+        }
+
+        Ok(config)
+    }
+}
+
+impl fmt::Display for EngineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.strategy {
+            Strategy::RandomMove => write!(f, "random_move"),
+            Strategy::FirstLegalMove => write!(f, "first_legal_move"),
+            Strategy::AlphaBeta | Strategy::Uci => {
+                match &self.command {
+                    Some(command) => write!(f, "uci:cmd={},", command)?,
+                    None => write!(f, "alpha_beta:")?,
+                }
+                write!(f, "depth={}", self.depth)?;
+                if let Some(t) = self.move_time {
+                    write!(f, ",movetime={}", t.as_millis())?;
+                }
+                if self.evaluator != Evaluator::default() {
+                    write!(f, ",eval={}", self.evaluator.spec())?;
+                }
+                if let Some(mb) = self.hash_mb {
+                    write!(f, ",hash={}", mb)?;
+                }
+                if self.threads > 1 {
+                    write!(f, ",threads={}", self.threads)?;
+                }
+                if self.book {
+                    write!(f, ",book=true")?;
+                }
+                if self.ponder {
+                    write!(f, ",ponder=true")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_specs() {
+        assert_eq!(
+            "random_move".parse(),
+            Ok(EngineConfig::new(Strategy::RandomMove))
+        );
+        assert_eq!(
+            "alpha_beta".parse(),
+            Ok(EngineConfig::new(Strategy::AlphaBeta))
+        );
+        assert_eq!(
+            "alpha_beta:depth=4,movetime=250".parse(),
+            Ok(EngineConfig {
+                depth: 4,
+                move_time: Some(Duration::from_millis(250)),
+                ..EngineConfig::new(Strategy::AlphaBeta)
+            })
+        );
+        assert_eq!(
+            "uci:cmd=stockfish,movetime=100,ponder=true".parse(),
+            Ok(EngineConfig {
+                move_time: Some(Duration::from_millis(100)),
+                command: Some("stockfish".into()),
+                ponder: true,
+                ..EngineConfig::new(Strategy::Uci)
+            })
+        );
+        assert_eq!(
+            "alpha_beta:eval=material,hash=64,threads=4,book=true".parse(),
+            Ok(EngineConfig {
+                evaluator: Evaluator::MaterialOnly,
+                hash_mb: Some(64),
+                threads: 4,
+                book: true,
+                ..EngineConfig::new(Strategy::AlphaBeta)
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        assert!("minimax".parse::<EngineConfig>().is_err());
+        assert!("alpha_beta:depth=0".parse::<EngineConfig>().is_err());
+        assert!("alpha_beta:depth".parse::<EngineConfig>().is_err());
+        assert!("alpha_beta:width=3".parse::<EngineConfig>().is_err());
+        assert!("random_move:depth=3".parse::<EngineConfig>().is_err());
+        assert!("uci:depth=3".parse::<EngineConfig>().is_err());
+        assert!("alpha_beta:cmd=stockfish".parse::<EngineConfig>().is_err());
+        assert!("alpha_beta:threads=0".parse::<EngineConfig>().is_err());
+        assert!("alpha_beta:eval=psychic".parse::<EngineConfig>().is_err());
+        assert!("uci:cmd=stockfish,eval=material"
+            .parse::<EngineConfig>()
+            .is_err());
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        for spec in [
+            "random_move",
+            "first_legal_move",
+            "alpha_beta:depth=3,movetime=100",
+            "alpha_beta:depth=4,ponder=true",
+            "uci:cmd=stockfish,depth=6",
+            "uci:cmd=stockfish,depth=6,movetime=50,ponder=true",
+            "alpha_beta:depth=5,eval=material,hash=32,threads=2,book=true",
+            "uci:cmd=stockfish,depth=6,hash=128,threads=8,book=true",
+        ] {
+            let config: EngineConfig = spec.parse().unwrap();
+            assert_eq!(config.to_string(), spec);
+        }
+    }
+}
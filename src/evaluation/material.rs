@@ -7,19 +7,11 @@ const BISHOP_VALUE: Score = 300;
 const KNIGHT_VALUE: Score = 300;
 const ROOK_VALUE: Score = 500;
 const QUEEN_VALUE: Score = 900;
-const CHECKMATE_VALUE: Score = 20_000;
+pub const CHECKMATE_VALUE: Score = 20_000;
 
 pub fn evaluate(board: &Board, color: Color, to_move: Color) -> Score {
-    let moves = MoveGen::new_legal(board);
-    let num_moves = moves.len();
-    if num_moves == 0 {
-        if board.checkers().popcnt() == 0 {
-            return 0;
-        } else if to_move != color {
-            return CHECKMATE_VALUE;
-        } else {
-            return -CHECKMATE_VALUE;
-        }
+    if let Some(score) = game_over(board, color, to_move) {
+        return score;
     }
 
     let other_color = match color {
@@ -32,6 +24,31 @@ pub fn evaluate(board: &Board, color: Color, to_move: Color) -> Score {
         - mobility(board, other_color)
 }
 
+/// Like `evaluate`, but only counting material, which is quicker and plays
+/// much more crudely.
+pub fn evaluate_material(board: &Board, color: Color, to_move: Color) -> Score {
+    if let Some(score) = game_over(board, color, to_move) {
+        return score;
+    }
+
+    material(board, color) - material(board, !color)
+}
+
+/// The score of a position with no legal moves, for mates and stalemates.
+fn game_over(board: &Board, color: Color, to_move: Color) -> Option<Score> {
+    if MoveGen::new_legal(board).len() > 0 {
+        return None;
+    }
+
+    Some(if board.checkers().popcnt() == 0 {
+        0
+    } else if to_move != color {
+        CHECKMATE_VALUE
+    } else {
+        -CHECKMATE_VALUE
+    })
+}
+
 fn material(board: &Board, color: Color) -> Score {
     let color_bitboard = board.color_combined(color);
 
@@ -91,4 +108,11 @@ mod tests {
         let board = Board::from_str("4rk1b/1ppb1p2/p1Bp4/8/5q2/7P/P5P1/4R2K b - - 0 27").unwrap();
         assert_eq!(evaluate(&board, Color::White, Color::White), -16_70);
     }
+
+    #[test]
+    fn material_only_ignores_mobility() {
+        let board = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(evaluate_material(&board, Color::White, Color::White), 500);
+        assert!(evaluate(&board, Color::White, Color::White) > 500);
+    }
 }
@@ -1,5 +1,46 @@
+use chess::{Board, Color};
+use std::fmt;
+
 mod material;
 mod types;
 
-pub use material::evaluate;
+pub use material::{evaluate, evaluate_material, CHECKMATE_VALUE};
 pub use types::Score;
+
+/// The ways the search can score positions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Evaluator {
+    /// Material and mobility.
+    #[default]
+    Standard,
+    MaterialOnly,
+}
+
+impl Evaluator {
+    pub const ALL: [Evaluator; 2] = [Evaluator::Standard, Evaluator::MaterialOnly];
+
+    /// Scores `board` from `color`'s point of view, like `evaluate`.
+    pub fn evaluate(self, board: &Board, color: Color, to_move: Color) -> Score {
+        match self {
+            Evaluator::Standard => evaluate(board, color, to_move),
+            Evaluator::MaterialOnly => evaluate_material(board, color, to_move),
+        }
+    }
+
+    /// The name used for the evaluator in engine specs.
+    pub fn spec(self) -> &'static str {
+        match self {
+            Evaluator::Standard => "standard",
+            Evaluator::MaterialOnly => "material",
+        }
+    }
+}
+
+impl fmt::Display for Evaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Evaluator::Standard => write!(f, "Material and mobility"),
+            Evaluator::MaterialOnly => write!(f, "Material only"),
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use chess::{Board, Game};
+use chess::{Action, BitBoard, Board, ChessMove, Color, Game, GameResult, Piece};
+use std::collections::HashMap;
+
+use crate::annotation::Annotations;
+use crate::clock::{Clock, TimeControl};
 
 #[derive(Clone)]
 pub struct GameState {
@@ -8,11 +12,105 @@ pub struct GameState {
     pub started: bool,
 
     pub game: Game,
+    pub start_position: Board,
+
+    pub clock: Clock,
+
+    /// The side which ran out of time, since `Game` has no result for that.
+    pub flag_fall: Option<Color>,
+
+    /// The side which lost by making an illegal move or a claim it couldn't,
+    /// which `Game` only records as a resignation.
+    pub forfeit: Option<Color>,
+
+    /// Why the game was drawn without anyone claiming it, since `Game` has no
+    /// result for that either. Fifty-move claims which only count because of
+    /// `start_halfmove_clock` end up here too, since `Game` can't see them.
+    pub automatic_draw: Option<DrawReason>,
+
+    /// Plies without a capture or pawn move before the start position, from
+    /// the halfmove field of its FEN.
+    pub start_halfmove_clock: usize,
+
+    /// The side with a draw offer open. It lapses once the other side moves.
+    pub draw_offer: Option<Color>,
+
+    /// What's been drawn on the board after each ply, counting the starting
+    /// position as 0.
+    pub annotations: HashMap<usize, Annotations>,
+
+    /// Moves which have been taken back, most recent last, so they can be
+    /// redone.
+    undone: Vec<ChessMove>,
+}
+
+/// Draws which happen whether or not either side claims them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawReason {
+    InsufficientMaterial,
+    FivefoldRepetition,
+
+    /// 75 moves by each side without a capture or pawn move.
+    SeventyFiveMoves,
+
+    /// A claim under the 50-move rule, counting plies from before the start
+    /// position.
+    FiftyMoves,
+}
+
+impl DrawReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            DrawReason::InsufficientMaterial => "insufficient material",
+            DrawReason::FivefoldRepetition => "fivefold repetition",
+            DrawReason::SeventyFiveMoves => "75-move rule",
+            DrawReason::FiftyMoves => "50-move rule",
+        }
+    }
+}
+
+/// Plies without a capture or pawn move after which the game is drawn.
+const SEVENTY_FIVE_MOVES: usize = 150;
+
+/// Plies without a capture or pawn move after which a draw can be claimed.
+const FIFTY_MOVES: usize = 100;
+
+/// How a game ended, independent of the reason it ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Color),
+    Draw,
+}
+
+impl Outcome {
+    pub fn from_game_result(result: GameResult) -> Self {
+        match result {
+            GameResult::WhiteCheckmates | GameResult::BlackResigns => Outcome::Win(Color::White),
+            GameResult::BlackCheckmates | GameResult::WhiteResigns => Outcome::Win(Color::Black),
+            GameResult::Stalemate | GameResult::DrawDeclared | GameResult::DrawAccepted => {
+                Outcome::Draw
+            }
+        }
+    }
+
+    /// The result as written in PGN: `1-0`, `0-1` or `1/2-1/2`.
+    pub fn pgn_result(&self) -> &'static str {
+        match self {
+            Outcome::Win(Color::White) => "1-0",
+            Outcome::Win(Color::Black) => "0-1",
+            Outcome::Draw => "1/2-1/2",
+        }
+    }
 }
 
 impl GameState {
     pub fn new(white_name: String, black_name: String) -> Self {
-        let game = chess::Game::new();
+        Self::with_position(white_name, black_name, Board::default())
+    }
+
+    /// Creates a game which starts from `board` instead of the usual setup.
+    pub fn with_position(white_name: String, black_name: String, board: Board) -> Self {
+        let game = chess::Game::new_with_board(board);
 
         Self {
             white_name,
@@ -21,10 +119,624 @@ impl GameState {
             started: false,
 
             game,
+            start_position: board,
+
+            clock: Clock::default(),
+            flag_fall: None,
+            forfeit: None,
+            automatic_draw: None,
+            start_halfmove_clock: 0,
+            draw_offer: None,
+
+            annotations: HashMap::new(),
+            undone: vec![],
+        }
+    }
+
+    /// Replaces the clock; only makes sense before the game starts.
+    pub fn set_time_control(&mut self, time_control: TimeControl) {
+        self.clock = Clock::new(time_control);
+    }
+
+    /// Starts the game, and the clock of the side to move.
+    pub fn start(&mut self) {
+        self.started = true;
+        self.clock.start(self.game.side_to_move());
+    }
+
+    /// Plays `m` and presses the clock, unless the game is already over
+    /// (including by the mover's flag having fallen). Returns whether the move
+    /// was made. Moving turns down any draw the opponent offered.
+    pub fn make_move(&mut self, m: ChessMove) -> bool {
+        if self.check_flag() || self.is_finished() {
+            return false;
+        }
+
+        let color = self.game.side_to_move();
+        if !self.current_position().legal(m) || !self.game.make_move(m) {
+            return false;
+        }
+
+        // playing the move that was taken back keeps the rest redoable, while
+        // any other move starts a new line, so drawings on the old line's
+        // positions go
+        if self.undone.last() == Some(&m) {
+            self.undone.pop();
+        } else {
+            self.undone.clear();
+            let ply = self.moves().len();
+            self.annotations.retain(|&p, _| p < ply);
+        }
+
+        if self.draw_offer == Some(!color) {
+            self.draw_offer = None;
+        }
+
+        self.clock.press(color);
+        if self.game.result().is_none() {
+            self.automatic_draw = self.find_automatic_draw();
+        }
+        if self.is_finished() {
+            self.clock.stop();
+        }
+        true
+    }
+
+    fn find_automatic_draw(&self) -> Option<DrawReason> {
+        let board = self.current_position();
+        if insufficient_material(&board) {
+            return Some(DrawReason::InsufficientMaterial);
+        }
+
+        let positions = self.positions();
+        if positions.iter().filter(|&&p| p == board).count() >= 5 {
+            return Some(DrawReason::FivefoldRepetition);
+        }
+
+        if self.halfmove_clock() >= SEVENTY_FIVE_MOVES {
+            return Some(DrawReason::SeventyFiveMoves);
+        }
+
+        None
+    }
+
+    /// Plies since the last capture or pawn move, carrying on from
+    /// `start_halfmove_clock` if there hasn't been one since the start.
+    pub fn halfmove_clock(&self) -> usize {
+        self.halfmove_clock_at(self.moves().len())
+    }
+
+    /// The halfmove clock in the position after `ply` plies.
+    pub fn halfmove_clock_at(&self, ply: usize) -> usize {
+        let positions = self.positions();
+        let mut moves = self.moves();
+        moves.truncate(ply);
+
+        let plies = moves
+            .iter()
+            .zip(positions.iter())
+            .rev()
+            .take_while(|(m, board)| {
+                board.piece_on(m.get_source()) != Some(Piece::Pawn)
+                    && board.piece_on(m.get_dest()).is_none()
+            })
+            .count();
+
+        if plies == moves.len() {
+            plies + self.start_halfmove_clock
+        } else {
+            plies
+        }
+    }
+
+    pub fn resign(&mut self, color: Color) {
+        if !self.is_finished() && self.game.resign(color) {
+            self.clock.stop();
+        }
+    }
+
+    /// Ends the game in a loss for `color`, which broke the rules.
+    pub fn forfeit(&mut self, color: Color) {
+        if !self.is_finished() {
+            self.resign(color);
+            self.forfeit = Some(color);
+        }
+    }
+
+    /// Offers a draw on behalf of `color`, which stands until the opponent
+    /// accepts it, declines it or moves.
+    pub fn offer_draw(&mut self, color: Color) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+
+        self.draw_offer = Some(color);
+        true
+    }
+
+    /// Whether the side to move has a draw offer open.
+    pub fn draw_offered(&self) -> bool {
+        !self.is_finished() && self.draw_offer == Some(!self.game.side_to_move())
+    }
+
+    /// Accepts the opponent's open draw offer, for `color`.
+    pub fn accept_draw(&mut self, color: Color) -> bool {
+        if self.is_finished() || self.draw_offer != Some(!color) {
+            return false;
+        }
+
+        self.draw_offer = None;
+        self.game.offer_draw(!color);
+        let accepted = self.game.accept_draw();
+        if accepted {
+            self.clock.stop();
+        }
+        accepted
+    }
+
+    pub fn decline_draw(&mut self, color: Color) {
+        if self.draw_offer == Some(!color) {
+            self.draw_offer = None;
+        }
+    }
+
+    /// Whether the side to move can claim a draw by threefold repetition or
+    /// the fifty-move rule.
+    pub fn can_claim_draw(&self) -> bool {
+        !self.is_finished()
+            && (self.game.can_declare_draw() || self.halfmove_clock() >= FIFTY_MOVES)
+    }
+
+    /// Claims a draw for `color`, which must be the side to move.
+    pub fn claim_draw(&mut self, color: Color) -> bool {
+        if self.game.side_to_move() != color || !self.can_claim_draw() {
+            return false;
+        }
+
+        // `Game` only counts the plies it saw played, so it turns down claims
+        // which need the ones from before the start
+        if !self.game.declare_draw() {
+            self.automatic_draw = Some(DrawReason::FiftyMoves);
+        }
+        self.draw_offer = None;
+        self.clock.stop();
+        true
+    }
+
+    /// Records a flag fall if the side to move has run out of time, returning
+    /// whether either side has.
+    pub fn check_flag(&mut self) -> bool {
+        if self.started && !self.is_finished() {
+            if let Some(color) = self.clock.flagged() {
+                self.flag_fall = Some(color);
+                self.clock.stop();
+            }
+        }
+
+        self.flag_fall.is_some()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.game.result().is_some() || self.flag_fall.is_some() || self.automatic_draw.is_some()
+    }
+
+    /// How the game ended, if it has. Running out of time loses, unless the
+    /// opponent couldn't have checkmated anyway.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(result) = self.game.result() {
+            return Some(Outcome::from_game_result(result));
+        }
+        if self.automatic_draw.is_some() {
+            return Some(Outcome::Draw);
+        }
+
+        let flagged = self.flag_fall?;
+        if insufficient_mating_material(&self.current_position(), !flagged) {
+            Some(Outcome::Draw)
+        } else {
+            Some(Outcome::Win(!flagged))
+        }
+    }
+
+    /// How the game ended, in words, like "Black wins on time".
+    pub fn result_message(&self) -> Option<&'static str> {
+        if let Some(flagged) = self.flag_fall {
+            Some(match (flagged, self.outcome()) {
+                (_, Some(Outcome::Draw)) => "Draw (out of time, but no mating material)",
+                (Color::White, _) => "Black wins on time",
+                (Color::Black, _) => "White wins on time",
+            })
+        } else if let Some(reason) = self.automatic_draw {
+            Some(match reason {
+                DrawReason::InsufficientMaterial => "Draw by insufficient material",
+                DrawReason::FivefoldRepetition => "Draw by fivefold repetition",
+                DrawReason::SeventyFiveMoves => "Draw by the 75-move rule",
+                DrawReason::FiftyMoves => "Draw claimed (50-move rule)",
+            })
+        } else {
+            self.game.result().map(|result| match result {
+                GameResult::WhiteCheckmates => "White wins (checkmate)",
+                GameResult::BlackResigns if self.forfeit.is_some() => {
+                    "White wins (black forfeited)"
+                }
+                GameResult::BlackResigns => "White wins (black resigned)",
+                GameResult::BlackCheckmates => "Black wins (checkmate)",
+                GameResult::WhiteResigns if self.forfeit.is_some() => {
+                    "Black wins (white forfeited)"
+                }
+                GameResult::WhiteResigns => "Black wins (white resigned)",
+                GameResult::Stalemate => "Draw by stalemate",
+                GameResult::DrawDeclared => "Draw claimed",
+                GameResult::DrawAccepted => "Draw by agreement",
+            })
         }
     }
 
     pub fn current_position(&self) -> Board {
         self.game.current_position()
     }
+
+    /// All the moves played so far, in order.
+    pub fn moves(&self) -> Vec<ChessMove> {
+        self.game
+            .actions()
+            .iter()
+            .filter_map(|action| match action {
+                Action::MakeMove(m) => Some(*m),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The starting position, followed by the position after each move.
+    pub fn positions(&self) -> Vec<Board> {
+        let mut board = self.start_position;
+        let mut positions = vec![board];
+
+        for m in self.moves() {
+            board = board.make_move_new(m);
+            positions.push(board);
+        }
+
+        positions
+    }
+
+    /// Takes back the last move, returning it. `Game` can't unmake moves, so
+    /// this replays every move before it from the starting position. The
+    /// clock carries on from where it was, for the side now to move.
+    pub fn undo(&mut self) -> Option<ChessMove> {
+        let mut moves = self.moves();
+        let last = moves.pop()?;
+
+        self.game = Game::new_with_board(self.start_position);
+        for m in moves {
+            self.game.make_move(m);
+        }
+
+        self.undone.push(last);
+        self.automatic_draw = None;
+        self.forfeit = None;
+        self.draw_offer = None;
+        if self.clock.running().is_some() {
+            self.clock.start(self.game.side_to_move());
+        }
+
+        Some(last)
+    }
+
+    /// Plays the last move taken back again, returning it.
+    pub fn redo(&mut self) -> Option<ChessMove> {
+        let m = *self.undone.last()?;
+        self.make_move(m).then_some(m)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+}
+
+/// The halfmove clock field of a FEN, or 0 if it hasn't got one.
+pub fn fen_halfmove_clock(fen: &str) -> usize {
+    fen.split_whitespace()
+        .nth(4)
+        .and_then(|field| field.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Whether neither side has enough material left to checkmate: bare kings,
+/// a single minor piece, or only bishops which all stand on one color.
+pub fn insufficient_material(board: &Board) -> bool {
+    let heavy =
+        *board.pieces(Piece::Pawn) | *board.pieces(Piece::Rook) | *board.pieces(Piece::Queen);
+    if heavy.popcnt() > 0 {
+        return false;
+    }
+
+    let knights = *board.pieces(Piece::Knight);
+    let bishops = *board.pieces(Piece::Bishop);
+    if (knights | bishops).popcnt() <= 1 {
+        return true;
+    }
+
+    let dark_squares = BitBoard::new(0x55AA_55AA_55AA_55AA);
+    knights.popcnt() == 0
+        && ((bishops & dark_squares).popcnt() == 0 || (bishops & !dark_squares).popcnt() == 0)
+}
+
+/// Whether `color` has too little material to ever checkmate: a bare king, or
+/// a king and a single minor piece. (Positions where a lone knight can mate
+/// because the opponent's own pieces get in the way are ignored.)
+pub fn insufficient_mating_material(board: &Board, color: Color) -> bool {
+    let ours = *board.color_combined(color);
+    let heavy =
+        *board.pieces(Piece::Pawn) | *board.pieces(Piece::Rook) | *board.pieces(Piece::Queen);
+    let minor = *board.pieces(Piece::Knight) | *board.pieces(Piece::Bishop);
+
+    (heavy & ours).popcnt() == 0 && (minor & ours).popcnt() <= 1
+}
+
+/// Every square `color` attacks, whether or not there's anything on it to
+/// take, and even where capturing would be illegal.
+pub fn attacked_squares(board: &Board, color: Color) -> BitBoard {
+    let blockers = *board.combined();
+    let mut attacked = chess::EMPTY;
+
+    for square in *board.color_combined(color) {
+        attacked |= match board.piece_on(square) {
+            Some(Piece::Pawn) => chess::get_pawn_attacks(square, color, !chess::EMPTY),
+            Some(Piece::Knight) => chess::get_knight_moves(square),
+            Some(Piece::Bishop) => chess::get_bishop_moves(square, blockers),
+            Some(Piece::Rook) => chess::get_rook_moves(square, blockers),
+            Some(Piece::Queen) => {
+                chess::get_bishop_moves(square, blockers) | chess::get_rook_moves(square, blockers)
+            }
+            Some(Piece::King) => chess::get_king_moves(square),
+            None => chess::EMPTY,
+        };
+    }
+
+    attacked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn undo_takes_back_the_last_move() {
+        let mut game_state = GameState::new("white".into(), "black".into());
+        let before = game_state.current_position();
+
+        let e4 = ChessMove::from_san(&before, "e4").unwrap();
+        game_state.game.make_move(e4);
+
+        assert_eq!(game_state.moves(), vec![e4]);
+        assert_eq!(game_state.undo(), Some(e4));
+        assert_eq!(game_state.current_position(), before);
+        assert_eq!(game_state.undo(), None);
+    }
+
+    #[test]
+    fn redo_replays_taken_back_moves() {
+        let mut game_state = GameState::new("white".into(), "black".into());
+        for san in ["e4", "e5", "Nf3"] {
+            let m = ChessMove::from_san(&game_state.current_position(), san).unwrap();
+            assert!(game_state.make_move(m));
+        }
+        let moves = game_state.moves();
+        let positions = game_state.positions();
+        assert_eq!(positions.len(), 4);
+        assert_eq!(positions[3], game_state.current_position());
+
+        game_state.undo();
+        game_state.undo();
+        assert_eq!(game_state.current_position(), positions[1]);
+
+        assert_eq!(game_state.redo(), Some(moves[1]));
+        assert!(game_state.can_redo());
+
+        // a different move abandons what was left to redo, and what was
+        // drawn on it
+        game_state.annotations.insert(2, Annotations::default());
+        game_state.annotations.insert(3, Annotations::default());
+        let d4 = ChessMove::from_san(&game_state.current_position(), "d4").unwrap();
+        assert!(game_state.make_move(d4));
+        assert!(!game_state.can_redo());
+        assert_eq!(game_state.redo(), None);
+        assert!(game_state.annotations.contains_key(&2));
+        assert!(!game_state.annotations.contains_key(&3));
+    }
+
+    fn play(game_state: &mut GameState, moves: &[&str]) {
+        for san in moves {
+            let m = ChessMove::from_san(&game_state.current_position(), san).unwrap();
+            assert!(game_state.make_move(m), "{}", san);
+        }
+    }
+
+    #[test]
+    fn draw_offers_lapse_once_the_opponent_moves() {
+        let mut game_state = GameState::new("white".into(), "black".into());
+
+        // offered along with white's move
+        game_state.offer_draw(Color::White);
+        assert!(!game_state.draw_offered());
+        play(&mut game_state, &["e4"]);
+        assert!(game_state.draw_offered());
+
+        play(&mut game_state, &["e5"]);
+        assert!(!game_state.draw_offered());
+        assert!(!game_state.accept_draw(Color::Black));
+
+        game_state.offer_draw(Color::Black);
+        game_state.decline_draw(Color::White);
+        assert!(!game_state.accept_draw(Color::White));
+
+        game_state.offer_draw(Color::Black);
+        assert!(game_state.accept_draw(Color::White));
+        assert_eq!(game_state.game.result(), Some(GameResult::DrawAccepted));
+        assert_eq!(game_state.moves().len(), 2);
+    }
+
+    #[test]
+    fn draws_fivefold_repetitions_automatically() {
+        let mut game_state = GameState::new("white".into(), "black".into());
+        let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8"];
+
+        play(&mut game_state, &shuffle);
+        play(&mut game_state, &shuffle);
+        assert!(game_state.can_claim_draw());
+        assert!(!game_state.claim_draw(Color::Black));
+
+        play(&mut game_state, &shuffle);
+        play(&mut game_state, &shuffle[..3]);
+        assert!(!game_state.is_finished());
+
+        play(&mut game_state, &shuffle[3..]);
+        assert_eq!(
+            game_state.automatic_draw,
+            Some(DrawReason::FivefoldRepetition)
+        );
+        assert_eq!(game_state.outcome(), Some(Outcome::Draw));
+        assert!(!game_state.claim_draw(Color::White));
+    }
+
+    #[test]
+    fn draws_when_mating_material_runs_out() {
+        let fen = "4k3/8/8/8/8/8/3n4/4K1N1 w - - 0 1";
+        let mut game_state = GameState::with_position(
+            "white".into(),
+            "black".into(),
+            Board::from_str(fen).unwrap(),
+        );
+
+        play(&mut game_state, &["Kxd2"]);
+        assert_eq!(
+            game_state.automatic_draw,
+            Some(DrawReason::InsufficientMaterial)
+        );
+        assert!(game_state.is_finished());
+    }
+
+    #[test]
+    fn counts_plies_since_captures_and_pawn_moves() {
+        let mut game_state = GameState::new("white".into(), "black".into());
+        play(&mut game_state, &["Nf3", "Nf6"]);
+        assert_eq!(game_state.halfmove_clock(), 2);
+
+        play(&mut game_state, &["e4", "Nxe4", "Nc3"]);
+        assert_eq!(game_state.halfmove_clock(), 1);
+    }
+
+    #[test]
+    fn carries_on_the_halfmove_clock_of_the_start_position() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K1N1 w - - 98 80";
+        let mut game_state = GameState::with_position(
+            "white".into(),
+            "black".into(),
+            Board::from_str(fen).unwrap(),
+        );
+        game_state.start_halfmove_clock = fen_halfmove_clock(fen);
+        assert_eq!(game_state.halfmove_clock(), 98);
+
+        play(&mut game_state, &["Nf3"]);
+        assert!(!game_state.can_claim_draw());
+        play(&mut game_state, &["Kd7"]);
+        assert!(game_state.can_claim_draw());
+        assert!(game_state.claim_draw(Color::White));
+        assert_eq!(game_state.outcome(), Some(Outcome::Draw));
+
+        let mut game_state = GameState::with_position(
+            "white".into(),
+            "black".into(),
+            Board::from_str(fen).unwrap(),
+        );
+        game_state.start_halfmove_clock = 98;
+        play(&mut game_state, &["e4", "Kd7"]);
+        assert_eq!(game_state.halfmove_clock(), 1);
+    }
+
+    #[test]
+    fn flag_fall_loses_unless_the_opponent_cannot_mate() {
+        let fen = "8/8/4k3/8/8/3K4/8/6Nr w - - 0 1";
+        let mut game_state = GameState::with_position(
+            "white".into(),
+            "black".into(),
+            Board::from_str(fen).unwrap(),
+        );
+        game_state.set_time_control(TimeControl::MoveTime(std::time::Duration::ZERO));
+        game_state.start();
+
+        assert!(game_state.check_flag());
+        assert_eq!(game_state.flag_fall, Some(Color::White));
+        assert_eq!(game_state.outcome(), Some(Outcome::Win(Color::Black)));
+
+        let m = ChessMove::from_san(&game_state.current_position(), "Kd4").unwrap();
+        assert!(!game_state.make_move(m));
+
+        game_state.flag_fall = Some(Color::Black);
+        assert_eq!(game_state.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn forfeits_read_differently_from_resignations() {
+        let mut game_state = GameState::new("white".into(), "black".into());
+        game_state.forfeit(Color::Black);
+        assert_eq!(game_state.outcome(), Some(Outcome::Win(Color::White)));
+        assert_eq!(
+            game_state.result_message(),
+            Some("White wins (black forfeited)")
+        );
+
+        let mut game_state = GameState::new("white".into(), "black".into());
+        game_state.resign(Color::Black);
+        game_state.forfeit(Color::White);
+        assert_eq!(
+            game_state.result_message(),
+            Some("White wins (black resigned)")
+        );
+    }
+
+    #[test]
+    fn detects_insufficient_material() {
+        let insufficient = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/8/6N1 w - - 0 1",
+            "8/8/4k3/2b5/8/3K4/8/6B1 w - - 0 1",
+        ];
+        let sufficient = [
+            "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+            "8/8/4k3/3b4/8/3K4/8/6B1 w - - 0 1",
+            "8/8/4k3/8/8/3K4/8/5NN1 w - - 0 1",
+        ];
+
+        for fen in insufficient {
+            let board = Board::from_str(fen).unwrap();
+            assert!(insufficient_material(&board), "{}", fen);
+        }
+        for fen in sufficient {
+            let board = Board::from_str(fen).unwrap();
+            assert!(!insufficient_material(&board), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn finds_attacked_squares() {
+        let board = Board::from_str("4k3/8/8/8/8/8/1P6/R3K3 w - - 0 1").unwrap();
+        let attacked = attacked_squares(&board, Color::White);
+        let is_attacked = |name: &str| {
+            let square = chess::Square::from_str(name).unwrap();
+            attacked & BitBoard::from_square(square) != chess::EMPTY
+        };
+
+        // the rook sees along its rank up to the king and up its file, and
+        // the pawn attacks diagonally but not straight ahead
+        for name in ["a8", "b1", "d1", "a3", "c3", "d2", "f1"] {
+            assert!(is_attacked(name), "{}", name);
+        }
+        for name in ["b3", "g1", "e3", "b8"] {
+            assert!(!is_attacked(name), "{}", name);
+        }
+    }
 }
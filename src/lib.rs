@@ -1,11 +1,28 @@
+pub mod analysis;
+pub mod annotation;
 pub mod app_state;
+pub mod book;
+pub mod cecp;
+pub mod clock;
+pub mod controller;
+pub mod describe;
+pub mod engine_config;
 pub mod evaluation;
 pub mod game_state;
+pub mod notation;
+pub mod pgn;
 pub mod player;
 pub mod prompt;
+pub mod review;
+pub mod settings;
+pub mod setup;
 pub mod strategies;
+pub mod tablebase;
+pub mod terminal;
 pub mod theme;
+pub mod tournament;
 pub mod transposition;
+pub mod uci;
 pub mod ui_state;
 pub mod widget;
 pub mod windows;
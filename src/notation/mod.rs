@@ -0,0 +1,264 @@
+use chess::{Board, BoardStatus, ChessMove, File, MoveGen, Piece};
+use std::str::FromStr;
+
+/// Formats a move in Standard Algebraic Notation, e.g. `Nbd7`, `exd5`,
+/// `e8=Q+` or `O-O#`. The move must be legal on `board`.
+pub fn san(board: &Board, m: ChessMove) -> String {
+    let piece = board
+        .piece_on(m.get_source())
+        .expect("move must start on an occupied square");
+    let source = m.get_source();
+    let dest = m.get_dest();
+
+    let mut san = String::new();
+
+    let file_distance =
+        (source.get_file().to_index() as i32 - dest.get_file().to_index() as i32).abs();
+    if piece == Piece::King && file_distance == 2 {
+        san.push_str(if dest.get_file() == File::G {
+            "O-O"
+        } else {
+            "O-O-O"
+        });
+    } else if piece == Piece::Pawn {
+        // pawns only change files when capturing, which covers en passant too
+        if source.get_file() != dest.get_file() {
+            san.push(file_char(source.get_file()));
+            san.push('x');
+        }
+        san.push_str(&dest.to_string());
+
+        if let Some(promotion) = m.get_promotion() {
+            san.push('=');
+            san.push(piece_char(promotion));
+        }
+    } else {
+        san.push(piece_char(piece));
+        san.push_str(&disambiguation(board, m, piece));
+        if board.piece_on(dest).is_some() {
+            san.push('x');
+        }
+        san.push_str(&dest.to_string());
+    }
+
+    let after = board.make_move_new(m);
+    if after.checkers().popcnt() > 0 {
+        san.push(if after.status() == BoardStatus::Checkmate {
+            '#'
+        } else {
+            '+'
+        });
+    }
+
+    san
+}
+
+/// Formats a sequence of moves played from `board` in SAN.
+pub fn san_line(board: &Board, moves: &[ChessMove]) -> Vec<String> {
+    let mut board = *board;
+
+    moves
+        .iter()
+        .map(|&m| {
+            let san = san(&board, m);
+            board = board.make_move_new(m);
+            san
+        })
+        .collect()
+}
+
+/// Parses a move in coordinate notation (`e2e4`, `e7e8q`) or SAN (`Nf3`),
+/// returning it only if it is legal on `board`.
+pub fn parse_move(board: &Board, input: &str) -> Option<ChessMove> {
+    let input = input.trim();
+    let m = ChessMove::from_str(input)
+        .ok()
+        .or_else(|| ChessMove::from_san(board, input).ok())?;
+
+    if board.legal(m) {
+        Some(m)
+    } else {
+        None
+    }
+}
+
+/// The legal moves in `board` whose SAN or coordinate notation starts with
+/// `prefix`, with their SAN, in alphabetical order.
+pub fn completions(board: &Board, prefix: &str) -> Vec<(ChessMove, String)> {
+    let prefix = prefix.trim();
+    let mut completions: Vec<(ChessMove, String)> = MoveGen::new_legal(board)
+        .map(|m| (m, san(board, m)))
+        .filter(|(m, san)| san.starts_with(prefix) || m.to_string().starts_with(prefix))
+        .collect();
+
+    completions.sort_by(|a, b| a.1.cmp(&b.1));
+    completions
+}
+
+/// Fills in as much of a move as the legal moves starting with `prefix`
+/// have in common, in whichever notation `prefix` is in.
+pub fn complete(board: &Board, prefix: &str) -> String {
+    let prefix = prefix.trim();
+    let completions = completions(board, prefix);
+
+    let by_san: Vec<String> = completions
+        .iter()
+        .map(|(_, san)| san.clone())
+        .filter(|san| san.starts_with(prefix))
+        .collect();
+    let names = if by_san.is_empty() {
+        completions.iter().map(|(m, _)| m.to_string()).collect()
+    } else {
+        by_san
+    };
+
+    let mut common = match names.first() {
+        Some(first) => first.clone(),
+        None => return prefix.to_string(),
+    };
+    for name in &names[1..] {
+        while !name.starts_with(&common) {
+            common.pop();
+        }
+    }
+    common
+}
+
+/// The file, rank, or square needed to tell `m` apart from other moves of the
+/// same kind of piece to the same square.
+fn disambiguation(board: &Board, m: ChessMove, piece: Piece) -> String {
+    let source = m.get_source();
+    let others: Vec<ChessMove> = MoveGen::new_legal(board)
+        .filter(|other| {
+            other.get_dest() == m.get_dest()
+                && other.get_source() != source
+                && board.piece_on(other.get_source()) == Some(piece)
+        })
+        .collect();
+
+    if others.is_empty() {
+        String::new()
+    } else if others
+        .iter()
+        .all(|o| o.get_source().get_file() != source.get_file())
+    {
+        file_char(source.get_file()).to_string()
+    } else if others
+        .iter()
+        .all(|o| o.get_source().get_rank() != source.get_rank())
+    {
+        (source.get_rank().to_index() + 1).to_string()
+    } else {
+        source.to_string()
+    }
+}
+
+fn file_char(file: File) -> char {
+    (b'a' + file.to_index() as u8) as char
+}
+
+fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess::Board;
+    use std::str::FromStr;
+
+    fn check(fen: &str, uci: &str, expected: &str) {
+        let board = Board::from_str(fen).unwrap();
+        let m = ChessMove::from_str(uci).unwrap();
+        assert_eq!(san(&board, m), expected, "{} in {}", uci, fen);
+    }
+
+    #[test]
+    fn formats_simple_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        check(start, "e2e4", "e4");
+        check(start, "g1f3", "Nf3");
+    }
+
+    #[test]
+    fn formats_captures_and_en_passant() {
+        check(
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+            "e4d5",
+            "exd5",
+        );
+        check("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6");
+        check("4k3/8/8/3p4/8/2N5/8/4K3 w - - 0 1", "c3d5", "Nxd5");
+    }
+
+    #[test]
+    fn formats_castling_and_promotion() {
+        check("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1g1", "O-O");
+        check("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1c1", "O-O-O");
+        check("8/4P3/8/8/8/8/8/k3K3 w - - 0 1", "e7e8q", "e8=Q");
+    }
+
+    #[test]
+    fn disambiguates() {
+        check("4k3/8/8/8/8/8/4K3/R4R2 w - - 0 1", "a1d1", "Rad1");
+        check("4k3/R7/8/8/8/8/8/R3K3 w - - 0 1", "a1a4", "R1a4");
+        check("4k3/8/8/8/8/2N1N3/8/2N1K3 w - - 0 1", "c3d5", "Ncd5");
+        check("4k3/8/8/8/8/2N1N3/8/2N1K3 w - - 0 1", "e3d5", "Ned5");
+        check("k7/8/8/2Q1Q3/8/2Q5/8/4K3 w - - 0 1", "c5d4", "Qc5d4");
+    }
+
+    #[test]
+    fn marks_checks_and_mates() {
+        check("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8", "Ra8+");
+        check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#");
+    }
+
+    #[test]
+    fn parses_both_notations() {
+        let board = Board::default();
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+
+        assert_eq!(parse_move(&board, "e2e4"), Some(e4));
+        assert_eq!(parse_move(&board, "e4"), Some(e4));
+        assert_eq!(parse_move(&board, "e2e5"), None);
+        assert_eq!(parse_move(&board, "Ke2"), None);
+    }
+
+    #[test]
+    fn completes_moves() {
+        let board = Board::default();
+        let sans = |prefix| -> Vec<String> {
+            completions(&board, prefix)
+                .into_iter()
+                .map(|(_, san)| san)
+                .collect()
+        };
+
+        assert_eq!(sans("N"), vec!["Na3", "Nc3", "Nf3", "Nh3"]);
+        assert_eq!(sans("g1"), vec!["Nf3", "Nh3"]);
+        assert!(sans("Q").is_empty());
+
+        assert_eq!(complete(&board, "Nf"), "Nf3");
+        assert_eq!(complete(&board, "N"), "N");
+        assert_eq!(complete(&board, "e2"), "e2e");
+        assert_eq!(complete(&board, "Q"), "Q");
+    }
+
+    #[test]
+    fn formats_lines() {
+        let board = Board::default();
+        let moves: Vec<ChessMove> = ["e2e4", "e7e5", "g1f3"]
+            .iter()
+            .map(|m| ChessMove::from_str(m).unwrap())
+            .collect();
+
+        assert_eq!(san_line(&board, &moves), vec!["e4", "e5", "Nf3"]);
+    }
+}
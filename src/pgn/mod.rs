@@ -0,0 +1,231 @@
+use chess::{Board, ChessMove, Color};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::game_state::{GameState, Outcome};
+use crate::notation::san;
+
+/// Movetext lines are wrapped before they get longer than this.
+const LINE_LENGTH: usize = 80;
+
+/// A game ready to be written out in [Portable Game
+/// Notation](https://en.wikipedia.org/wiki/Portable_Game_Notation).
+///
+/// The seven tag roster is always written, with `?` for anything unknown.
+/// Games which don't start from the usual position get `SetUp` and `FEN` tags.
+pub struct PgnGame {
+    headers: Vec<(String, String)>,
+    start_position: Board,
+    moves: Vec<ChessMove>,
+    outcome: Option<Outcome>,
+
+    /// Comments on the position after each ply, counting the starting
+    /// position as 0.
+    comments: HashMap<usize, String>,
+}
+
+impl PgnGame {
+    pub fn new(start_position: Board, moves: Vec<ChessMove>, outcome: Option<Outcome>) -> Self {
+        let mut headers = vec![];
+        for name in ["Event", "Site", "Date", "Round", "White", "Black"] {
+            let value = if name == "Date" { "????.??.??" } else { "?" };
+            headers.push((name.to_string(), value.to_string()));
+        }
+
+        PgnGame {
+            headers,
+            start_position,
+            moves,
+            outcome,
+            comments: HashMap::new(),
+        }
+    }
+
+    /// The game's moves and players, with anything drawn on the board kept
+    /// in comments as `[%csl]` and `[%cal]` commands.
+    pub fn from_game_state(game_state: &GameState, outcome: Option<Outcome>) -> Self {
+        let mut pgn = PgnGame::new(game_state.start_position, game_state.moves(), outcome)
+            .header("White", &game_state.white_name)
+            .header("Black", &game_state.black_name);
+
+        for (&ply, annotations) in &game_state.annotations {
+            if !annotations.is_empty() {
+                pgn = pgn.comment(ply, &annotations.to_string());
+            }
+        }
+        pgn
+    }
+
+    /// Sets a tag, replacing any earlier value it had.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+
+        match self.headers.iter_mut().find(|(n, _)| n == name) {
+            Some(header) => header.1 = value,
+            None => self.headers.push((name.to_string(), value)),
+        }
+
+        self
+    }
+
+    /// Comments on the position after `ply` plies, which comes after the
+    /// move leading to it.
+    pub fn comment(mut self, ply: usize, comment: &str) -> Self {
+        // comments end at the first closing brace, and there's no escaping it
+        self.comments.insert(ply, comment.replace('}', ")"));
+        self
+    }
+
+    fn result(&self) -> &'static str {
+        self.outcome.map_or("*", |o| o.pgn_result())
+    }
+
+    fn movetext(&self) -> Vec<String> {
+        let mut tokens = vec![];
+        let mut board = self.start_position;
+        let mut move_number = 1;
+        let comment = |ply| self.comments.get(&ply).map(|c| format!("{{{}}}", c));
+
+        let mut commented = false;
+        if let Some(comment) = comment(0) {
+            tokens.push(comment);
+            commented = true;
+        }
+
+        for (i, &m) in self.moves.iter().enumerate() {
+            // black's moves get their number again when something came
+            // between them and white's
+            match board.side_to_move() {
+                Color::White => tokens.push(format!("{}.", move_number)),
+                Color::Black if commented || i == 0 => tokens.push(format!("{}...", move_number)),
+                Color::Black => {}
+            }
+
+            tokens.push(san(&board, m));
+            commented = false;
+            if let Some(comment) = comment(i + 1) {
+                tokens.push(comment);
+                commented = true;
+            }
+
+            if board.side_to_move() == Color::Black {
+                move_number += 1;
+            }
+            board = board.make_move_new(m);
+        }
+
+        tokens.push(self.result().to_string());
+        tokens
+    }
+}
+
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.headers.iter() {
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        writeln!(f, "[Result \"{}\"]", self.result())?;
+
+        if self.start_position != Board::default() {
+            writeln!(f, "[SetUp \"1\"]")?;
+            writeln!(f, "[FEN \"{}\"]", self.start_position)?;
+        }
+        writeln!(f)?;
+
+        let mut line = String::new();
+        for token in self.movetext() {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotation::{Annotations, Brush};
+    use std::str::FromStr;
+
+    fn moves(uci: &[&str]) -> Vec<ChessMove> {
+        uci.iter()
+            .map(|m| ChessMove::from_str(m).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn writes_a_game() {
+        let board = Board::default();
+        let game = PgnGame::new(
+            board,
+            moves(&["f2f3", "e7e5", "g2g4", "d8h4"]),
+            Some(Outcome::Win(Color::Black)),
+        )
+        .header("White", "patzer")
+        .header("Black", "Random move");
+
+        let expected = "[Event \"?\"]\n\
+                        [Site \"?\"]\n\
+                        [Date \"????.??.??\"]\n\
+                        [Round \"?\"]\n\
+                        [White \"patzer\"]\n\
+                        [Black \"Random move\"]\n\
+                        [Result \"0-1\"]\n\
+                        \n\
+                        1. f3 e5 2. g4 Qh4# 0-1\n";
+        assert_eq!(game.to_string(), expected);
+    }
+
+    #[test]
+    fn writes_a_game_from_a_position() {
+        let board = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        let game = PgnGame::new(board, moves(&["e8d7", "e2e4"]), None);
+        let pgn = game.to_string();
+
+        assert!(pgn.contains("[Result \"*\"]\n"));
+        assert!(pgn.contains("[SetUp \"1\"]\n"));
+        assert!(pgn.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n"));
+        assert!(pgn.ends_with("\n1... Kd7 2. e4 *\n"));
+    }
+
+    #[test]
+    fn writes_annotations_in_comments() {
+        let mut game_state = GameState::new("white".into(), "black".into());
+        for m in moves(&["e2e4", "e7e5", "g1f3"]) {
+            game_state.make_move(m);
+        }
+
+        let e4 = chess::Square::from_str("e4").unwrap();
+        let mut annotations = Annotations::default();
+        annotations.toggle_square(e4, Brush::Red);
+        game_state.annotations.insert(1, annotations.clone());
+        game_state.annotations.insert(2, Annotations::default());
+        annotations.toggle_square(e4, Brush::Red);
+        annotations.toggle_arrow(e4, chess::Square::from_str("d5").unwrap(), Brush::Green);
+        game_state.annotations.insert(0, annotations);
+
+        let pgn = PgnGame::from_game_state(&game_state, None).to_string();
+        assert!(pgn.ends_with("\n{[%cal Ge4d5]} 1. e4 {[%csl Re4]} 1... e5 2. Nf3 *\n"));
+    }
+
+    #[test]
+    fn wraps_long_games() {
+        let board = Board::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let uci: Vec<&str> = shuffle.iter().cycle().take(60).copied().collect();
+        let game = PgnGame::new(board, moves(&uci), Some(Outcome::Draw));
+
+        let pgn = game.to_string();
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|l| l.len() <= LINE_LENGTH));
+        assert!(movetext.trim_end().ends_with("1/2-1/2"));
+    }
+}
@@ -0,0 +1,472 @@
+use chess::{Board, ChessMove, Game};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::{accepts_draw, Decision, Player};
+use crate::book::Book;
+use crate::clock::Clock;
+use crate::evaluation::Evaluator;
+use crate::game_state::Outcome;
+use crate::strategies::{search, SearchInfo, SearchLimits};
+use crate::tablebase::Tablebase;
+
+/// A computer player which picks moves with a simple function, like
+/// `strategies::random_move`. It resigns if the function has no move.
+pub struct StrategyPlayer {
+    name: String,
+    strategy: fn(&Game) -> Option<ChessMove>,
+}
+
+impl StrategyPlayer {
+    pub fn new(name: &str, strategy: fn(&Game) -> Option<ChessMove>) -> Self {
+        StrategyPlayer {
+            name: name.to_string(),
+            strategy,
+        }
+    }
+}
+
+impl Player for StrategyPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play(
+        &self,
+        game: &Game,
+        _start_position: &Board,
+        _clock: &Clock,
+        _draw_offered: bool,
+        _can_claim_draw: bool,
+    ) -> Decision {
+        match (self.strategy)(game) {
+            Some(m) => Decision::Move(m),
+            None => Decision::Resign,
+        }
+    }
+}
+
+/// patzer's own alpha-beta search, as a player. It searches to `depth`, or
+/// for `move_time` if that's set, or else for as long as the clock allows.
+/// With a book, it plays book moves without searching while it can.
+/// With pondering on, it searches the reply it expects on the opponent's
+/// time, and carries on with that search if the reply comes.
+pub struct AlphaBetaPlayer {
+    name: String,
+    depth: u8,
+    move_time: Option<Duration>,
+    tablebase: Option<Arc<Tablebase>>,
+    evaluator: Evaluator,
+    hash_mb: Option<usize>,
+    threads: usize,
+    book: Option<Book>,
+    ponder: bool,
+
+    /// Shared with the pondering search, which runs on a thread of its own.
+    stop: Arc<AtomicBool>,
+    info: Arc<Mutex<Option<SearchInfo>>>,
+
+    /// The reply the last search expected, and the search of the position
+    /// after it while that's under way.
+    ponder_move: Mutex<Option<ChessMove>>,
+    pondering: Mutex<Option<Pondering>>,
+}
+
+/// A search of the position after the opponent's expected reply.
+struct Pondering {
+    position: Board,
+    search: JoinHandle<(Option<ChessMove>, Option<SearchInfo>)>,
+}
+
+impl AlphaBetaPlayer {
+    pub fn new(
+        name: &str,
+        depth: u8,
+        move_time: Option<Duration>,
+        tablebase: Option<Arc<Tablebase>>,
+    ) -> Self {
+        AlphaBetaPlayer {
+            name: name.to_string(),
+            depth,
+            move_time,
+            tablebase,
+            evaluator: Evaluator::default(),
+            hash_mb: None,
+            threads: 1,
+            book: None,
+            ponder: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            info: Arc::new(Mutex::new(None)),
+            ponder_move: Mutex::new(None),
+            pondering: Mutex::new(None),
+        }
+    }
+
+    pub fn evaluator(mut self, evaluator: Evaluator) -> Self {
+        self.evaluator = evaluator;
+        self
+    }
+
+    /// Roughly how much memory the transposition table may take up.
+    pub fn hash_mb(mut self, hash_mb: Option<usize>) -> Self {
+        self.hash_mb = hash_mb;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn book(mut self, book: Option<Book>) -> Self {
+        self.book = book;
+        self
+    }
+
+    pub fn ponders(mut self, ponder: bool) -> Self {
+        self.ponder = ponder;
+        self
+    }
+
+    /// Stops pondering, if it was, returning what was being pondered.
+    fn stop_pondering(&self) -> Option<Pondering> {
+        let pondering = self.pondering.lock().unwrap().take()?;
+        self.stop.store(true, Ordering::SeqCst);
+        Some(pondering)
+    }
+
+    /// Lets the pondering search carry on for as long as this move may take,
+    /// then takes its result.
+    fn finish_pondering(
+        &self,
+        pondering: Pondering,
+        move_time: Option<Duration>,
+    ) -> (Option<ChessMove>, Option<SearchInfo>) {
+        let deadline = move_time.map(|t| Instant::now() + t);
+        while !pondering.search.is_finished() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.stop.store(true, Ordering::SeqCst);
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        pondering.search.join().unwrap_or((None, None))
+    }
+}
+
+/// Searches `position`, keeping `info` up to date, and returns the best move
+/// along with the last report from the search.
+fn search_position(
+    position: &Board,
+    limits: &SearchLimits,
+    tablebase: Option<&Tablebase>,
+    info: &Mutex<Option<SearchInfo>>,
+) -> (Option<ChessMove>, Option<SearchInfo>) {
+    let mut last = None;
+    let best = search(position, limits, tablebase, |report| {
+        last = Some(report.clone());
+        *info.lock().unwrap() = Some(report.clone());
+    });
+    (best, last)
+}
+
+impl Player for AlphaBetaPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play(
+        &self,
+        game: &Game,
+        _start_position: &Board,
+        clock: &Clock,
+        draw_offered: bool,
+        can_claim_draw: bool,
+    ) -> Decision {
+        let position = game.current_position();
+        // a fixed move time wins over budgeting from the clock
+        let move_time = self.move_time.or_else(|| clock.budget(game.side_to_move()));
+
+        let pondering = self.pondering.lock().unwrap().take();
+        let (best, info) = match pondering {
+            Some(pondering) if pondering.position == position => {
+                self.finish_pondering(pondering, move_time)
+            }
+            pondering => {
+                // the opponent surprised us, so that search is no use
+                if let Some(pondering) = pondering {
+                    self.stop.store(true, Ordering::SeqCst);
+                    pondering.search.join().ok();
+                }
+                self.stop.store(false, Ordering::SeqCst);
+
+                if let Some(m) = self.book.as_ref().and_then(|book| book.pick(&position)) {
+                    return Decision::Move(m);
+                }
+
+                let limits = SearchLimits {
+                    depth: self.depth,
+                    move_time,
+                    stop: Some(&self.stop),
+                    evaluator: self.evaluator,
+                    hash_mb: self.hash_mb,
+                    threads: self.threads,
+                };
+                search_position(&position, &limits, self.tablebase.as_deref(), &self.info)
+            }
+        };
+
+        // the principal variation carries on with the reply we expect
+        *self.ponder_move.lock().unwrap() = match &info {
+            Some(info) if info.pv.first() == best.as_ref() => info.pv.get(1).copied(),
+            _ => None,
+        };
+        let score = info.map(|info| info.score);
+
+        // a tablebase move at the root is played without searching, so the
+        // score comes from the same tables
+        let score = score.or_else(|| {
+            let tablebase = self.tablebase.as_ref()?;
+            tablebase.probe_wdl(&position)
+        });
+        let content = score.is_some_and(accepts_draw);
+        match best {
+            _ if draw_offered && content => Decision::AcceptDraw,
+            _ if can_claim_draw && content => Decision::ClaimDraw,
+            Some(m) => Decision::Move(m),
+            None => Decision::Resign,
+        }
+    }
+
+    fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Starts searching the position after the reply the last search
+    /// expected, to as deep as `play` would, but with no time limit.
+    fn ponder(&self, game: &Game, _clock: &Clock) {
+        if !self.ponder {
+            return;
+        }
+        if let Some(pondering) = self.stop_pondering() {
+            pondering.search.join().ok();
+        }
+
+        let expected = match self.ponder_move.lock().unwrap().take() {
+            Some(m) if game.current_position().legal(m) => m,
+            _ => return,
+        };
+        let position = game.current_position().make_move_new(expected);
+        self.stop.store(false, Ordering::SeqCst);
+
+        let depth = self.depth;
+        let evaluator = self.evaluator;
+        let hash_mb = self.hash_mb;
+        let threads = self.threads;
+        let tablebase = self.tablebase.clone();
+        let stop = self.stop.clone();
+        let info = self.info.clone();
+        let search = thread::spawn(move || {
+            let limits = SearchLimits {
+                depth,
+                move_time: None,
+                stop: Some(&stop),
+                evaluator,
+                hash_mb,
+                threads,
+            };
+            search_position(&position, &limits, tablebase.as_deref(), &info)
+        });
+
+        *self.pondering.lock().unwrap() = Some(Pondering { position, search });
+    }
+
+    fn game_over(&self, _outcome: Outcome) {
+        self.stop_pondering();
+    }
+
+    fn info(&self) -> Option<String> {
+        let info = self.info.lock().unwrap();
+        info.as_ref().map(|info| {
+            format!(
+                "depth {}, score {:+.2}, nodes {}",
+                info.depth,
+                info.score as f64 / 100.0,
+                info.nodes
+            )
+        })
+    }
+}
+
+impl Drop for AlphaBetaPlayer {
+    fn drop(&mut self) {
+        self.stop_pondering();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::first_legal_move;
+    use chess::{Board, MoveGen};
+    use std::str::FromStr;
+
+    #[test]
+    fn strategy_players_resign_without_moves() {
+        let player = StrategyPlayer::new("first", first_legal_move);
+        let stalemate = Game::from_str("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+
+        assert_eq!(
+            player.play(
+                &Game::new(),
+                &Board::default(),
+                &Clock::default(),
+                false,
+                false
+            ),
+            Decision::Move(MoveGen::new_legal(&Board::default()).next().unwrap())
+        );
+        assert_eq!(
+            player.play(
+                &stalemate,
+                &stalemate.current_position(),
+                &Clock::default(),
+                false,
+                false
+            ),
+            Decision::Resign
+        );
+    }
+
+    #[test]
+    fn accepts_draws_only_when_not_better() {
+        let player = AlphaBetaPlayer::new("alpha-beta", 2, None, None);
+        let winning = Game::from_str("4k3/8/8/8/8/8/8/QQ2K3 w - - 0 1").unwrap();
+        let losing = Game::from_str("qq2k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+
+        assert!(matches!(
+            player.play(
+                &winning,
+                &winning.current_position(),
+                &Clock::default(),
+                true,
+                false
+            ),
+            Decision::Move(_)
+        ));
+        assert_eq!(
+            player.play(
+                &losing,
+                &losing.current_position(),
+                &Clock::default(),
+                true,
+                false
+            ),
+            Decision::AcceptDraw
+        );
+        assert!(player.info().is_some());
+    }
+
+    #[test]
+    fn claims_repetitions_only_when_not_better() {
+        let player = AlphaBetaPlayer::new("alpha-beta", 2, None, None);
+
+        for fen in [
+            "4k3/8/8/8/8/8/8/QQ2K3 w - - 0 1",
+            "qq2k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            let mut game = Game::from_str(fen).unwrap();
+            for _ in 0..2 {
+                for m in ["e1d1", "e8d8", "d1e1", "d8e8"] {
+                    game.make_move(ChessMove::from_str(m).unwrap());
+                }
+            }
+            assert!(game.can_declare_draw());
+
+            let decision = player.play(
+                &game,
+                &Board::from_str(fen).unwrap(),
+                &Clock::default(),
+                false,
+                true,
+            );
+            assert_eq!(decision == Decision::ClaimDraw, fen.starts_with("qq"));
+        }
+    }
+
+    #[test]
+    fn claims_only_draws_it_is_told_are_there() {
+        let player = AlphaBetaPlayer::new("alpha-beta", 2, None, None);
+        // `Game` doesn't know the fifty moves are up
+        let losing = Game::from_str("qq2k3/8/8/8/8/8/8/4K3 w - - 100 80").unwrap();
+        assert!(!losing.can_declare_draw());
+
+        assert_eq!(
+            player.play(
+                &losing,
+                &losing.current_position(),
+                &Clock::default(),
+                false,
+                true
+            ),
+            Decision::ClaimDraw
+        );
+        assert!(matches!(
+            player.play(
+                &losing,
+                &losing.current_position(),
+                &Clock::default(),
+                false,
+                false
+            ),
+            Decision::Move(_)
+        ));
+    }
+
+    #[test]
+    fn ponders_on_the_expected_reply() {
+        let player = AlphaBetaPlayer::new("alpha-beta", 3, None, None).ponders(true);
+        let mut game = Game::new();
+
+        let Decision::Move(m) =
+            player.play(&game, &Board::default(), &Clock::default(), false, false)
+        else {
+            panic!("expected a move");
+        };
+        game.make_move(m);
+        player.ponder(&game, &Clock::default());
+
+        let expected = player.pondering.lock().unwrap().as_ref().unwrap().position;
+        let reply = MoveGen::new_legal(&game.current_position())
+            .find(|&reply| game.current_position().make_move_new(reply) == expected)
+            .unwrap();
+        game.make_move(reply);
+
+        assert!(matches!(
+            player.play(&game, &Board::default(), &Clock::default(), false, false),
+            Decision::Move(_)
+        ));
+        assert!(player.pondering.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn plays_book_moves_straight_away() {
+        let player = AlphaBetaPlayer::new("alpha-beta", 6, None, None).book(Some(Book::builtin()));
+
+        match player.play(
+            &Game::new(),
+            &Board::default(),
+            &Clock::default(),
+            false,
+            false,
+        ) {
+            Decision::Move(m) => {
+                assert!(Book::builtin().moves(&Board::default()).contains(&m));
+            }
+            decision => panic!("expected a book move, got {:?}", decision),
+        }
+        assert!(player.info().is_none());
+    }
+}
@@ -0,0 +1,207 @@
+use chess::{Board, ChessMove, Game};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use crate::clock::Clock;
+use crate::evaluation::Score;
+use crate::game_state::Outcome;
+
+mod engine;
+
+pub use engine::{AlphaBetaPlayer, StrategyPlayer};
+
+/// What a player does when it's their turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Move(ChessMove),
+
+    /// Plays the move, and offers a draw along with it.
+    MoveAndOfferDraw(ChessMove),
+
+    /// Accepts the draw the opponent offered.
+    AcceptDraw,
+
+    /// Claims a draw by threefold repetition or the fifty-move rule.
+    ClaimDraw,
+
+    Resign,
+}
+
+/// Anyone who can play a game: a person at the board, an in-process engine,
+/// or an external one.
+///
+/// Players are asked for a `Decision` through `play` on a thread of their
+/// own, so it's fine for `play` to block until they've made up their mind.
+/// The other methods are called from elsewhere while that happens, and
+/// should return promptly.
+pub trait Player: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Whether moves come from the board, through `submit`.
+    fn is_human(&self) -> bool {
+        false
+    }
+
+    /// Decides what to do in `game`, where it's this player's move.
+    /// `start_position` is where the game began, which `Game` doesn't say.
+    /// `draw_offered` says whether the opponent has a draw offer open, and
+    /// `can_claim_draw` whether a draw could be claimed instead of moving,
+    /// which `game` alone can't tell when the fifty-move count started
+    /// before it did.
+    fn play(
+        &self,
+        game: &Game,
+        start_position: &Board,
+        clock: &Clock,
+        draw_offered: bool,
+        can_claim_draw: bool,
+    ) -> Decision;
+
+    /// Abandons any `play` or pondering in progress. `play` should return
+    /// soon after, and what it returns will be ignored.
+    fn stop(&self) {}
+
+    /// Hands over a decision made at the board for the position `position`.
+    /// Only human players take any notice.
+    fn submit(&self, _position: Board, _decision: Decision) {}
+
+    /// Called with each move the opponent makes; `game` includes it.
+    fn opponent_moved(&self, _game: &Game, _m: ChessMove) {}
+
+    /// Called just after this player has moved, so it can start thinking
+    /// about its next move on the opponent's time.
+    fn ponder(&self, _game: &Game, _clock: &Clock) {}
+
+    fn game_over(&self, _outcome: Outcome) {}
+
+    /// What the player last said about its thinking, for display.
+    fn info(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Whether a computer player should take a draw, given its evaluation of
+/// the position (from its own side).
+pub(crate) fn accepts_draw(score: Score) -> bool {
+    score <= 0
+}
+
+enum HumanInput {
+    Decision(Board, Decision),
+    Stop,
+}
+
+/// A person playing at the board, whose decisions arrive through `submit`.
+pub struct HumanPlayer {
+    name: String,
+    sender: Mutex<Sender<HumanInput>>,
+    receiver: Mutex<Receiver<HumanInput>>,
+}
+
+impl HumanPlayer {
+    pub fn new(name: &str) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        HumanPlayer {
+            name: name.to_string(),
+            sender: Mutex::new(sender),
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    fn send(&self, input: HumanInput) {
+        self.sender.lock().unwrap().send(input).ok();
+    }
+}
+
+impl Player for HumanPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn is_human(&self) -> bool {
+        true
+    }
+
+    /// Waits for a decision made at the board in this position; anything
+    /// left over from earlier positions is skipped.
+    fn play(
+        &self,
+        game: &Game,
+        _start_position: &Board,
+        _clock: &Clock,
+        _draw_offered: bool,
+        _can_claim_draw: bool,
+    ) -> Decision {
+        let position = game.current_position();
+        let receiver = self.receiver.lock().unwrap();
+
+        loop {
+            match receiver.recv() {
+                Ok(HumanInput::Decision(p, decision)) if p == position => return decision,
+                Ok(HumanInput::Decision(..)) => {}
+                // the decision is ignored once stopped, so any will do
+                Ok(HumanInput::Stop) | Err(_) => return Decision::Resign,
+            }
+        }
+    }
+
+    fn stop(&self) {
+        self.send(HumanInput::Stop);
+    }
+
+    fn submit(&self, position: Board, decision: Decision) {
+        self.send(HumanInput::Decision(position, decision));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn humans_play_what_was_submitted_for_the_position() {
+        let human = Arc::new(HumanPlayer::new("human"));
+        let mut game = Game::new();
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+        let e5 = ChessMove::from_str("e7e5").unwrap();
+
+        human.submit(Board::default(), Decision::Move(e4));
+        assert_eq!(
+            human.play(&game, &Board::default(), &Clock::default(), false, false),
+            Decision::Move(e4)
+        );
+
+        game.make_move(e4);
+        human.submit(Board::default(), Decision::Move(e4));
+        human.submit(game.current_position(), Decision::Move(e5));
+        assert_eq!(
+            human.play(&game, &Board::default(), &Clock::default(), false, false),
+            Decision::Move(e5)
+        );
+    }
+
+    #[test]
+    fn stopping_a_human_ends_their_turn() {
+        let human = Arc::new(HumanPlayer::new("human"));
+
+        let waiting = {
+            let human = human.clone();
+            thread::spawn(move || {
+                human.play(
+                    &Game::new(),
+                    &Board::default(),
+                    &Clock::default(),
+                    false,
+                    false,
+                )
+            })
+        };
+        human.stop();
+
+        assert_eq!(waiting.join().unwrap(), Decision::Resign);
+    }
+}
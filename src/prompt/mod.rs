@@ -1,27 +1,292 @@
-use chess::{Board, ChessMove, MoveGen};
-use std::io::stdin;
+use chess::{Board, ChessMove, Color, File, MoveGen, Piece, Square, ALL_FILES, ALL_RANKS};
+use std::io::{self, stdin, Write};
+use std::str::FromStr;
 
-pub fn input_human_move(board: &Board, _moves: &mut MoveGen) -> Option<ChessMove> {
+use crate::describe::piece_name;
+use crate::notation::san;
+
+/// What someone playing in the terminal can type at the prompt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Move(ChessMove),
+
+    /// Takes back the last move, and the opponent's reply to it.
+    Undo,
+    Hint,
+    Fen,
+    Pgn,
+    Flip,
+
+    /// Lists the pieces on the board, for when you can't see it.
+    Describe,
+    Resign,
+
+    /// Offers a draw, or accepts or claims one.
+    Draw,
+    Help,
+}
+
+pub const HELP: &str = "Type moves in SAN (Nf3, exd5, O-O, e8=Q), long algebraic (Ng1-f3)
+or UCI (g1f3, e7e8q), or one of these commands:
+
+  undo      take back your last move
+  hint      ask the engine what it would play
+  fen       show the position in FEN
+  pgn       show the game so far in PGN
+  flip      turn the board around
+  describe  list the pieces on the board
+  draw      offer a draw, or accept or claim one
+  resign    give up (quit does too)
+  help      show this again";
+
+/// Asks for a command until one makes sense in `board`, explaining what's
+/// wrong with any that don't. Returns `None` at the end of input.
+pub fn input_command(board: &Board) -> io::Result<Option<Command>> {
     let stdin = stdin();
-    print!("input your move: ");
 
-    let mut buffer = String::new();
-    stdin.read_line(&mut buffer).expect("reading stdin failed");
+    loop {
+        print!("your move: ");
+        io::stdout().flush()?;
 
-    if is_resignation(&buffer) {
-        None
-    } else {
-        match ChessMove::from_san(board, &buffer) {
-            Ok(m) => Some(m),
-            Err(_) => None,
+        let mut buffer = String::new();
+        if stdin.read_line(&mut buffer)? == 0 {
+            return Ok(None);
+        }
+        if buffer.trim().is_empty() {
+            continue;
         }
+
+        match parse_command(board, &buffer) {
+            Ok(command) => return Ok(Some(command)),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+pub fn parse_command(board: &Board, input: &str) -> Result<Command, String> {
+    let input = input.trim();
+    if is_resignation(input) {
+        return Ok(Command::Resign);
+    }
+
+    match input.to_lowercase().as_str() {
+        "undo" | "takeback" => Ok(Command::Undo),
+        "hint" => Ok(Command::Hint),
+        "fen" => Ok(Command::Fen),
+        "pgn" => Ok(Command::Pgn),
+        "flip" | "flip board" => Ok(Command::Flip),
+        "describe" => Ok(Command::Describe),
+        "draw" => Ok(Command::Draw),
+        "help" | "?" => Ok(Command::Help),
+        _ => input_move(board, input).map(Command::Move),
     }
 }
 
 /// is_resignation will parse the input string for a few patterns of resigning,
 /// and will return true if the input signals a resignation.
 fn is_resignation(input: &str) -> bool {
-    matches!(input, "resign" | "quit" | "flip board")
+    matches!(input.trim(), "resign" | "quit")
+}
+
+/// Reads a move in SAN, long algebraic or UCI notation, saying why if it
+/// isn't one of the legal moves in `board`.
+pub fn input_move(board: &Board, input: &str) -> Result<ChessMove, String> {
+    let legal: Vec<ChessMove> = MoveGen::new_legal(board).collect();
+    if legal.is_empty() {
+        return Err("There are no moves left to play".into());
+    }
+
+    // checks, mates and commentary don't change which move it is, and
+    // castling is sometimes written with zeroes
+    let written: String = input
+        .chars()
+        .filter(|c| !"+#!?".contains(*c))
+        .collect::<String>()
+        .replace("0-0", "O-O");
+    let strip = |san: String| san.replace(['+', '#'], "");
+
+    if let Some(&m) = legal.iter().find(|&&m| strip(san(board, m)) == written) {
+        return Ok(m);
+    }
+    if written.starts_with("O-O") {
+        return Err(format!("You can't castle that way now ({})", input));
+    }
+
+    // long algebraic is UCI with a piece letter and some punctuation
+    let coordinates: String = written
+        .trim_start_matches(['N', 'B', 'R', 'Q', 'K'])
+        .chars()
+        .filter(|c| !"-x=".contains(*c))
+        .collect::<String>()
+        .to_lowercase();
+    if let Ok(m) = ChessMove::from_str(&coordinates) {
+        return check_coordinates(board, &legal, m, input);
+    }
+
+    let piece = match written.chars().next() {
+        Some('N') => Piece::Knight,
+        Some('B') => Piece::Bishop,
+        Some('R') => Piece::Rook,
+        Some('Q') => Piece::Queen,
+        Some('K') => Piece::King,
+        _ => Piece::Pawn,
+    };
+    let dest = match last_square(&written) {
+        Some(dest) => dest,
+        None => {
+            return Err(format!(
+                "I can't read {} as a move or a command; type help to see what I can",
+                input
+            ))
+        }
+    };
+
+    let candidates: Vec<ChessMove> = legal
+        .iter()
+        .copied()
+        .filter(|m| m.get_dest() == dest && board.piece_on(m.get_source()) == Some(piece))
+        .collect();
+    let mut sans: Vec<String> = candidates.iter().map(|&m| san(board, m)).collect();
+    sans.sort();
+
+    if let Some(&queen) = candidates
+        .iter()
+        .find(|m| m.get_promotion() == Some(Piece::Queen))
+    {
+        if candidates
+            .iter()
+            .all(|m| m.get_source() == queen.get_source())
+        {
+            return Err(format!("Did you mean {}?", san(board, queen)));
+        }
+    }
+
+    match sans.len() {
+        0 if board.checkers().popcnt() > 0 => {
+            Err(format!("{} doesn't get you out of check", input))
+        }
+        0 => Err(format!(
+            "No {} of yours can move to {}",
+            piece_name(piece),
+            dest
+        )),
+        1 => Err(format!("Did you mean {}?", sans[0])),
+        _ => Err(format!(
+            "{} is ambiguous: it could be {}",
+            input,
+            sans.join(" or ")
+        )),
+    }
+}
+
+/// Explains what's wrong with a move given by its squares, if anything.
+fn check_coordinates(
+    board: &Board,
+    legal: &[ChessMove],
+    m: ChessMove,
+    input: &str,
+) -> Result<ChessMove, String> {
+    if legal.contains(&m) {
+        return Ok(m);
+    }
+
+    let source = m.get_source();
+    match board.piece_on(source) {
+        None => Err(format!("There's no piece on {}", source)),
+        Some(_) if board.color_on(source) != Some(board.side_to_move()) => {
+            Err(format!("The piece on {} isn't yours", source))
+        }
+        Some(Piece::Pawn) if m.get_promotion().is_none() && legal_promotion(legal, m) => {
+            Err(format!("Say what to promote to, like {}q", input.trim()))
+        }
+        Some(_) if board.checkers().popcnt() > 0 => {
+            Err(format!("{} doesn't get you out of check", input))
+        }
+        Some(piece) => Err(format!(
+            "The {} on {} can't move to {}",
+            piece_name(piece),
+            source,
+            m.get_dest()
+        )),
+    }
+}
+
+fn legal_promotion(legal: &[ChessMove], m: ChessMove) -> bool {
+    legal
+        .iter()
+        .any(|l| (l.get_source(), l.get_dest()) == (m.get_source(), m.get_dest()))
+}
+
+/// The last square named in `text`, like the d5 in Nbxd5.
+fn last_square(text: &str) -> Option<Square> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.windows(2).rev().find_map(|pair| {
+        let name: String = pair.iter().collect();
+        if pair[0].is_ascii_lowercase() && pair[1].is_ascii_digit() {
+            Square::from_str(&name).ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Draws `board` as text, White at the bottom unless it's `flipped`. Pieces
+/// are chess symbols, or letters (upper case for White) if `ascii` is set.
+pub fn render_board(board: &Board, flipped: bool, ascii: bool) -> String {
+    let mut ranks = ALL_RANKS.to_vec();
+    let mut files = ALL_FILES.to_vec();
+    if flipped {
+        files.reverse();
+    } else {
+        ranks.reverse();
+    }
+    let file_char = |file: File| (b'a' + file.to_index() as u8) as char;
+
+    let mut text = String::new();
+    for rank in ranks {
+        text.push_str(&format!("{} ", rank.to_index() + 1));
+        for &file in &files {
+            let square = Square::make_square(rank, file);
+            let symbol = match (board.piece_on(square), board.color_on(square)) {
+                (Some(piece), Some(color)) => piece_symbol(piece, color, ascii),
+                _ => '.',
+            };
+            text.push(' ');
+            text.push(symbol);
+        }
+        text.push('\n');
+    }
+
+    text.push_str("  ");
+    for file in files {
+        text.push(' ');
+        text.push(file_char(file));
+    }
+    text.push('\n');
+    text
+}
+
+/// A piece's chess symbol, or its letter if `ascii` is set.
+pub fn piece_symbol(piece: Piece, color: Color, ascii: bool) -> char {
+    let (white, black) = match (piece, ascii) {
+        (Piece::King, false) => ('♔', '♚'),
+        (Piece::Queen, false) => ('♕', '♛'),
+        (Piece::Rook, false) => ('♖', '♜'),
+        (Piece::Bishop, false) => ('♗', '♝'),
+        (Piece::Knight, false) => ('♘', '♞'),
+        (Piece::Pawn, false) => ('♙', '♟'),
+        (Piece::King, true) => ('K', 'k'),
+        (Piece::Queen, true) => ('Q', 'q'),
+        (Piece::Rook, true) => ('R', 'r'),
+        (Piece::Bishop, true) => ('B', 'b'),
+        (Piece::Knight, true) => ('N', 'n'),
+        (Piece::Pawn, true) => ('P', 'p'),
+    };
+
+    match color {
+        Color::White => white,
+        Color::Black => black,
+    }
 }
 
 #[cfg(test)]
@@ -30,9 +295,9 @@ mod tests {
 
     #[test]
     fn it_validates_resignations() {
-        let resignations = vec!["resign", "quit", "flip board"];
+        let resignations = vec!["resign", "quit", "resign\n"];
 
-        let not_resignations = vec!["e4"];
+        let not_resignations = vec!["e4", "flip board"];
 
         for s in resignations {
             assert!(is_resignation(s), "should be a resignation. input: {}", s);
@@ -46,4 +311,61 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn reads_moves_in_every_notation() {
+        let board = Board::default();
+        let nf3 = ChessMove::from_str("g1f3").unwrap();
+
+        for input in ["Nf3", "g1f3", "Ng1-f3", "g1-f3", "Nf3!?\n"] {
+            assert_eq!(
+                parse_command(&board, input),
+                Ok(Command::Move(nf3)),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(parse_command(&board, "UNDO"), Ok(Command::Undo));
+        assert_eq!(parse_command(&board, "flip board"), Ok(Command::Flip));
+    }
+
+    #[test]
+    fn explains_bad_moves() {
+        let board = Board::default();
+        let error = |input| input_move(&board, input).unwrap_err();
+
+        assert_eq!(error("Nf4"), "No knight of yours can move to f4");
+        assert_eq!(error("e3e4"), "There's no piece on e3");
+        assert_eq!(error("e7e5"), "The piece on e7 isn't yours");
+        assert_eq!(error("O-O"), "You can't castle that way now (O-O)");
+        assert!(error("hello").contains("type help"));
+
+        let knights = Board::from_str("4k3/8/8/8/8/2N1N3/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            input_move(&knights, "Nd5"),
+            Err("Nd5 is ambiguous: it could be Ncd5 or Ned5".into())
+        );
+
+        let promotion = Board::from_str("8/4P3/8/8/8/8/8/k3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            input_move(&promotion, "e7e8"),
+            Err("Say what to promote to, like e7e8q".into())
+        );
+        assert_eq!(
+            input_move(&promotion, "e8"),
+            Err("Did you mean e8=Q?".into())
+        );
+    }
+
+    #[test]
+    fn draws_boards() {
+        let board = Board::default();
+        let text = render_board(&board, false, true);
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "8  r n b q k b n r");
+        assert_eq!(lines[7], "1  R N B Q K B N R");
+        assert_eq!(lines[8], "   a b c d e f g h");
+        assert!(render_board(&board, true, false).starts_with("1  ♖ ♘ ♗ ♔ ♕ ♗ ♘ ♖"));
+    }
 }
@@ -0,0 +1,323 @@
+use chess::{Board, BoardStatus, ChessMove, Color};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::evaluation::{Score, CHECKMATE_VALUE};
+use crate::notation::san;
+use crate::strategies::{search, SearchLimits};
+
+/// How deep the review searches each position of the game.
+pub const REVIEW_DEPTH: u8 = 4;
+
+/// Scores past ten pawns either way count as ten pawns, so letting a mate
+/// slip in a position that's won anyway isn't a blunder.
+pub const SCORE_CAP: Score = 1_000;
+
+/// How many centipawns a move can lose before it's an inaccuracy, a mistake
+/// and a blunder.
+const INACCURACY: Score = 50;
+const MISTAKE: Score = 100;
+const BLUNDER: Score = 300;
+
+/// What the review makes of a move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Judgement {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    fn from_loss(loss: Score, best: bool) -> Self {
+        if best || loss <= 0 {
+            Judgement::Best
+        } else if loss < INACCURACY {
+            Judgement::Good
+        } else if loss < MISTAKE {
+            Judgement::Inaccuracy
+        } else if loss < BLUNDER {
+            Judgement::Mistake
+        } else {
+            Judgement::Blunder
+        }
+    }
+
+    /// Whether the move is worth going back to look at.
+    pub fn is_error(self) -> bool {
+        matches!(
+            self,
+            Judgement::Inaccuracy | Judgement::Mistake | Judgement::Blunder
+        )
+    }
+
+    /// The annotation symbol PGN and books use, like `??` for blunders.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Judgement::Best | Judgement::Good => "",
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+}
+
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Judgement::Best => "best",
+            Judgement::Good => "good",
+            Judgement::Inaccuracy => "inaccuracy",
+            Judgement::Mistake => "mistake",
+            Judgement::Blunder => "blunder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What the engine thinks of one position of the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionEval {
+    /// From White's point of view, capped at ten pawns either way.
+    pub score: Score,
+
+    /// `None` once the game is over.
+    pub best_move: Option<ChessMove>,
+}
+
+/// How one move of the game turned out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveReview {
+    /// The ply the move was played on, counting from 1, so it went from the
+    /// position after `ply - 1` plies to the one after `ply`.
+    pub ply: usize,
+    pub color: Color,
+    pub san: String,
+
+    /// How many centipawns worse the move left the mover than the engine's
+    /// choice would have.
+    pub loss: Score,
+    pub judgement: Judgement,
+
+    /// The engine's choice, in SAN, if it's different.
+    pub best: Option<String>,
+}
+
+/// Judges each move of a game from the evaluations of the positions before
+/// and after it, as far as positions have been evaluated.
+pub fn review_moves(
+    start_position: &Board,
+    moves: &[ChessMove],
+    evals: &[PositionEval],
+) -> Vec<MoveReview> {
+    let mut board = *start_position;
+    let mut reviews = vec![];
+
+    for (i, &m) in moves.iter().enumerate() {
+        let (before, after) = match (evals.get(i), evals.get(i + 1)) {
+            (Some(before), Some(after)) => (before, after),
+            _ => break,
+        };
+
+        let color = board.side_to_move();
+        let swing = after.score - before.score;
+        let loss = match color {
+            Color::White => -swing,
+            Color::Black => swing,
+        };
+        let best = before.best_move.filter(|&best| best != m);
+
+        reviews.push(MoveReview {
+            ply: i + 1,
+            color,
+            san: san(&board, m),
+            loss: loss.max(0),
+            judgement: Judgement::from_loss(loss, before.best_move == Some(m)),
+            best: best.map(|best| san(&board, best)),
+        });
+
+        board = board.make_move_new(m);
+    }
+
+    reviews
+}
+
+/// Everything the review has found so far.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReviewReport {
+    /// How much of the game has been gone through, from 0 to 1.
+    pub progress: f32,
+
+    /// The evaluation of each position reviewed so far, starting with the
+    /// starting position.
+    pub evals: Vec<PositionEval>,
+
+    pub moves: Vec<MoveReview>,
+}
+
+/// patzer's search going over every position of a game in the background,
+/// until it's done or dropped.
+pub struct Review {
+    start_position: Board,
+    moves: Vec<ChessMove>,
+    stop: Arc<AtomicBool>,
+    evals: Arc<Mutex<Vec<PositionEval>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Review {
+    pub fn start(start_position: Board, moves: Vec<ChessMove>, depth: u8) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let evals = Arc::new(Mutex::new(vec![]));
+
+        let handle = {
+            let stop = stop.clone();
+            let evals = evals.clone();
+            let moves = moves.clone();
+
+            thread::spawn(move || {
+                let mut board = start_position;
+                let positions = std::iter::once(board).chain(moves.iter().map(|&m| {
+                    board = board.make_move_new(m);
+                    board
+                }));
+
+                for position in positions {
+                    let eval = evaluate(&position, depth, &stop);
+                    if stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    evals.lock().unwrap().push(eval);
+                }
+            })
+        };
+
+        Review {
+            start_position,
+            moves,
+            stop,
+            evals,
+            handle: Some(handle),
+        }
+    }
+
+    /// The game being reviewed.
+    pub fn game(&self) -> (Board, &[ChessMove]) {
+        (self.start_position, &self.moves)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.evals.lock().unwrap().len() > self.moves.len()
+    }
+
+    pub fn report(&self) -> ReviewReport {
+        let evals = self.evals.lock().unwrap().clone();
+
+        ReviewReport {
+            progress: evals.len() as f32 / (self.moves.len() + 1) as f32,
+            moves: review_moves(&self.start_position, &self.moves, &evals),
+            evals,
+        }
+    }
+}
+
+impl Drop for Review {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Searches `board`, or scores it as it stands if the game's over there.
+fn evaluate(board: &Board, depth: u8, stop: &AtomicBool) -> PositionEval {
+    let (score, best_move) = match board.status() {
+        BoardStatus::Checkmate => (-CHECKMATE_VALUE, None),
+        BoardStatus::Stalemate => (0, None),
+        BoardStatus::Ongoing => {
+            let limits = SearchLimits {
+                depth,
+                stop: Some(stop),
+                ..Default::default()
+            };
+
+            let mut score = 0;
+            let best_move = search(board, &limits, None, |info| score = info.score);
+            (score, best_move)
+        }
+    };
+
+    // searches score positions for the side to move
+    let score = match board.side_to_move() {
+        Color::White => score,
+        Color::Black => -score,
+    };
+
+    PositionEval {
+        score: score.clamp(-SCORE_CAP, SCORE_CAP),
+        best_move,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn play(sans: &[&str]) -> Vec<ChessMove> {
+        let mut board = Board::default();
+        sans.iter()
+            .map(|s| {
+                let m = ChessMove::from_san(&board, s).unwrap();
+                board = board.make_move_new(m);
+                m
+            })
+            .collect()
+    }
+
+    #[test]
+    fn judges_moves_by_how_much_they_lose() {
+        let moves = play(&["e4", "e5", "Nf3"]);
+        let eval = |score| PositionEval {
+            score,
+            best_move: None,
+        };
+        let evals = [eval(20), eval(-200), eval(-200), eval(-230)];
+
+        let reviews = review_moves(&Board::default(), &moves, &evals);
+        let judgements: Vec<Judgement> = reviews.iter().map(|r| r.judgement).collect();
+        assert_eq!(
+            judgements,
+            vec![Judgement::Mistake, Judgement::Best, Judgement::Good]
+        );
+        assert_eq!(reviews[0].loss, 220);
+        assert_eq!(reviews[2].san, "Nf3");
+    }
+
+    #[test]
+    fn finds_blunders_in_finished_games() {
+        let moves = play(&["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"]);
+        let review = Review::start(Board::default(), moves, 3);
+
+        let start = Instant::now();
+        while !review.is_finished() {
+            assert!(start.elapsed() < Duration::from_secs(30), "review stalled");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let report = review.report();
+        assert_eq!(report.progress, 1.0);
+        assert_eq!(report.evals.len(), 8);
+        assert_eq!(report.evals[7].score, SCORE_CAP);
+
+        let nf6 = &report.moves[5];
+        assert_eq!(nf6.san, "Nf6");
+        assert_eq!(nf6.judgement, Judgement::Blunder);
+        assert!(nf6.best.is_some());
+        assert_eq!(report.moves[6].judgement, Judgement::Best);
+    }
+}
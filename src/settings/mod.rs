@@ -0,0 +1,254 @@
+use chess::{Board, ChessMove};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::engine_config::{EngineConfig, Strategy};
+use crate::game_state::fen_halfmove_clock;
+use crate::theme::Theme;
+use crate::widget::PieceSet;
+
+/// What the GUI remembers between launches.
+///
+/// Settings are saved as `key = value` lines, with players written as
+/// `human` or an engine spec, like
+///
+/// ```text
+/// white = human
+/// black = alpha_beta:depth=6
+/// flipped = false
+/// theme = Blue
+/// pieces = classic
+/// window = 1200x1000
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// How each side's computer player is configured, or `None` for humans.
+    pub white: Option<EngineConfig>,
+    pub black: Option<EngineConfig>,
+
+    pub flipped: bool,
+
+    /// The name of the board theme.
+    pub theme: String,
+
+    pub piece_set: PieceSet,
+
+    /// The window's size in points, if it's been opened before.
+    pub window_size: Option<(f32, f32)>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            white: None,
+            black: Some(EngineConfig::new(Strategy::AlphaBeta)),
+            flipped: false,
+            theme: Theme::default().name,
+            piece_set: PieceSet::default(),
+            window_size: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Where settings are kept, under the user's config directory.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("patzer").join("settings.conf"))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+}
+
+impl FromStr for Settings {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut settings = Settings::default();
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("expected key = value, not {}", line))?;
+
+            let player = |value: &str| match value {
+                "human" => Ok(None),
+                spec => spec.parse().map(Some),
+            };
+            let flag = |value: &str| {
+                value
+                    .parse::<bool>()
+                    .map_err(|_| format!("{} should be true or false, not {}", key, value))
+            };
+
+            match key {
+                "white" => settings.white = player(value)?,
+                "black" => settings.black = player(value)?,
+                "flipped" => settings.flipped = flag(value)?,
+                "theme" => settings.theme = value.to_string(),
+                "pieces" => settings.piece_set = value.parse()?,
+                "window" => {
+                    let size = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|&(w, h): &(f32, f32)| w > 0.0 && h > 0.0)
+                        .ok_or_else(|| {
+                            format!("window should be a size like 800x600, not {}", value)
+                        })?;
+                    settings.window_size = Some(size);
+                }
+                _ => return Err(format!("unknown setting {}", key)),
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let player = |config: &Option<EngineConfig>| match config {
+            Some(config) => config.to_string(),
+            None => "human".to_string(),
+        };
+
+        writeln!(f, "white = {}", player(&self.white))?;
+        writeln!(f, "black = {}", player(&self.black))?;
+        writeln!(f, "flipped = {}", self.flipped)?;
+        writeln!(f, "theme = {}", self.theme)?;
+        writeln!(f, "pieces = {}", self.piece_set)?;
+        if let Some((width, height)) = self.window_size {
+            writeln!(f, "window = {}x{}", width.round(), height.round())?;
+        }
+        Ok(())
+    }
+}
+
+/// An unfinished game, saved when the GUI closes so it can carry on where it
+/// left off. It's written as the starting position in FEN on one line, and
+/// the moves in UCI notation on the next.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub start_position: Board,
+
+    /// The halfmove clock of the starting position, which `Board` leaves
+    /// out of its FEN.
+    pub start_halfmove_clock: usize,
+
+    pub moves: Vec<ChessMove>,
+}
+
+impl FromStr for Session {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let fen = lines.next().ok_or("the saved game is empty")?;
+        let start_position =
+            Board::from_str(fen).map_err(|_| format!("bad starting position {}", fen))?;
+
+        let mut board = start_position;
+        let mut moves = vec![];
+        for token in lines.flat_map(str::split_whitespace) {
+            let m = ChessMove::from_str(token)
+                .ok()
+                .filter(|&m| board.legal(m))
+                .ok_or_else(|| format!("illegal move {} in the saved game", token))?;
+            board = board.make_move_new(m);
+            moves.push(m);
+        }
+
+        Ok(Session {
+            start_position,
+            start_halfmove_clock: fen_halfmove_clock(fen),
+            moves,
+        })
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let moves: Vec<String> = self.moves.iter().map(|m| m.to_string()).collect();
+        let fen = self.start_position.to_string();
+        let fields: Vec<&str> = fen.split_whitespace().take(4).collect();
+        writeln!(f, "{} {} 1", fields.join(" "), self.start_halfmove_clock)?;
+        writeln!(f, "{}", moves.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn round_trips_through_text() {
+        let settings = Settings {
+            white: Some(EngineConfig {
+                move_time: Some(Duration::from_millis(500)),
+                threads: 2,
+                ..EngineConfig::new(Strategy::AlphaBeta)
+            }),
+            black: None,
+            flipped: true,
+            theme: "High contrast".into(),
+            piece_set: PieceSet::Wood,
+            window_size: Some((800.0, 600.0)),
+        };
+
+        assert_eq!(settings.to_string().parse(), Ok(settings));
+        assert_eq!("".parse(), Ok(Settings::default()));
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!("white = alpha_beta:depth=x".parse::<Settings>().is_err());
+        assert!("flipped = maybe".parse::<Settings>().is_err());
+        assert!("pieces = glass".parse::<Settings>().is_err());
+        assert!("window = 800".parse::<Settings>().is_err());
+        assert!("colour = blue".parse::<Settings>().is_err());
+    }
+
+    #[test]
+    fn saves_unfinished_games() {
+        let start = Board::default();
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+        let e5 = ChessMove::from_str("e7e5").unwrap();
+        let session = Session {
+            start_position: start,
+            start_halfmove_clock: 0,
+            moves: vec![e4, e5],
+        };
+
+        assert_eq!(session.to_string().parse(), Ok(session));
+
+        let session = Session {
+            start_position: Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap(),
+            start_halfmove_clock: 37,
+            moves: vec![ChessMove::from_str("a1a7").unwrap()],
+        };
+        assert!(session
+            .to_string()
+            .starts_with("4k3/8/8/8/8/8/8/R3K3 w - - 37 1\n"));
+        assert_eq!(session.to_string().parse(), Ok(session));
+        assert!(format!("{}\ne2e5\n", start).parse::<Session>().is_err());
+    }
+}
@@ -0,0 +1,335 @@
+use chess::{BitBoard, Board, Color, File, Piece, Rank, Square, ALL_SQUARES};
+use std::str::FromStr;
+
+/// A position being set up by hand, which doesn't have to be legal until
+/// it's used. Pieces are indexed by `Square::to_index`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Setup {
+    pub pieces: [Option<(Piece, Color)>; 64],
+    pub side_to_move: Color,
+
+    /// Whether each side, indexed by `Color::to_index`, may still castle
+    /// king side and queen side.
+    pub castling: [(bool, bool); 2],
+
+    /// The file of a pawn which just moved two squares and can be taken en
+    /// passant.
+    pub en_passant: Option<File>,
+
+    /// Plies since the last capture or pawn move, for the fifty-move rule.
+    pub halfmove_clock: usize,
+}
+
+impl Setup {
+    /// An empty board, with White to move.
+    pub fn empty() -> Self {
+        Setup {
+            pieces: [None; 64],
+            side_to_move: Color::White,
+            castling: [(false, false); 2],
+            en_passant: None,
+            halfmove_clock: 0,
+        }
+    }
+
+    pub fn from_board(board: &Board) -> Self {
+        let mut pieces = [None; 64];
+        for square in ALL_SQUARES {
+            if let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square)) {
+                pieces[square.to_index()] = Some((piece, color));
+            }
+        }
+
+        let rights = |color| {
+            let rights = board.castle_rights(color);
+            (rights.has_kingside(), rights.has_queenside())
+        };
+
+        Setup {
+            pieces,
+            side_to_move: board.side_to_move(),
+            castling: [rights(Color::White), rights(Color::Black)],
+            en_passant: board.en_passant().map(|square| square.get_file()),
+            halfmove_clock: 0,
+        }
+    }
+
+    pub fn piece_on(&self, square: Square) -> Option<(Piece, Color)> {
+        self.pieces[square.to_index()]
+    }
+
+    pub fn put(&mut self, square: Square, piece: Option<(Piece, Color)>) {
+        self.pieces[square.to_index()] = piece;
+    }
+
+    /// Moves whatever is on `from` to `to`, replacing anything there.
+    pub fn move_piece(&mut self, from: Square, to: Square) {
+        if from != to {
+            self.pieces[to.to_index()] = self.pieces[from.to_index()].take();
+        }
+    }
+
+    /// The position in FEN, whether or not it's legal.
+    pub fn fen(&self) -> String {
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                let square = Square::make_square(Rank::from_index(rank), File::from_index(file));
+                match self.piece_on(square) {
+                    Some((piece, color)) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece_char(piece, color));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        let side = match self.side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let mut castling = String::new();
+        for (flag, c) in [
+            (self.castling[0].0, 'K'),
+            (self.castling[0].1, 'Q'),
+            (self.castling[1].0, 'k'),
+            (self.castling[1].1, 'q'),
+        ] {
+            if flag {
+                castling.push(c);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.en_passant_square() {
+            Some(square) => square.to_string(),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{} {} {} {} {} 1",
+            placement, side, castling, en_passant, self.halfmove_clock
+        )
+    }
+
+    /// Checks the position can be played from, explaining what's wrong with
+    /// it if it can't.
+    pub fn validate(&self) -> Result<Board, String> {
+        for color in [Color::White, Color::Black] {
+            let kings = self.squares_with(Piece::King, color).popcnt();
+            if kings != 1 {
+                return Err(format!("{:?} needs exactly one king, not {}", color, kings));
+            }
+        }
+
+        for square in ALL_SQUARES {
+            let back_rank = matches!(square.get_rank(), Rank::First | Rank::Eighth);
+            if back_rank && matches!(self.piece_on(square), Some((Piece::Pawn, _))) {
+                return Err(format!("There's a pawn on the back rank, on {}", square));
+            }
+        }
+
+        let waiting = !self.side_to_move;
+        let king = self.squares_with(Piece::King, waiting).to_square();
+        if self.attacked(king, self.side_to_move) {
+            return Err(format!(
+                "{:?} is in check, but it's {:?}'s move",
+                waiting, self.side_to_move
+            ));
+        }
+
+        for color in [Color::White, Color::Black] {
+            let (king_side, queen_side) = self.castling[color.to_index()];
+            let back_rank = color.to_my_backrank();
+            let has = |piece, file| {
+                let square = Square::make_square(back_rank, file);
+                self.piece_on(square) == Some((piece, color))
+            };
+
+            let king_home = has(Piece::King, File::E);
+            if (king_side && !(king_home && has(Piece::Rook, File::H)))
+                || (queen_side && !(king_home && has(Piece::Rook, File::A)))
+            {
+                return Err(format!(
+                    "{:?} can only castle with the king and rook on their starting squares",
+                    color
+                ));
+            }
+        }
+
+        if let Some(target) = self.en_passant_square() {
+            // the pawn went past the target square to get where it is now
+            let mover = !self.side_to_move;
+            let pawn = match mover {
+                Color::White => target.up(),
+                Color::Black => target.down(),
+            };
+            let origin = match mover {
+                Color::White => target.down(),
+                Color::Black => target.up(),
+            };
+
+            let pawn_there = pawn.is_some_and(|s| self.piece_on(s) == Some((Piece::Pawn, mover)));
+            let path_clear = self.piece_on(target).is_none()
+                && origin.is_some_and(|s| self.piece_on(s).is_none());
+            if !pawn_there || !path_clear {
+                return Err(format!(
+                    "No pawn could just have moved past {} to be taken en passant",
+                    target
+                ));
+            }
+        }
+
+        Board::from_str(&self.fen()).map_err(|_| "This position isn't legal".to_string())
+    }
+
+    /// The square a pawn can be taken en passant on, which is behind the pawn
+    /// from the point of view of whoever moved it.
+    fn en_passant_square(&self) -> Option<Square> {
+        let rank = match self.side_to_move {
+            Color::White => Rank::Sixth,
+            Color::Black => Rank::Third,
+        };
+        self.en_passant.map(|file| Square::make_square(rank, file))
+    }
+
+    fn squares_with(&self, piece: Piece, color: Color) -> BitBoard {
+        ALL_SQUARES
+            .iter()
+            .filter(|&&square| self.piece_on(square) == Some((piece, color)))
+            .fold(chess::EMPTY, |squares, &square| {
+                squares | BitBoard::from_square(square)
+            })
+    }
+
+    /// Whether any of `color`'s pieces attack `square`.
+    fn attacked(&self, square: Square, color: Color) -> bool {
+        let blockers = ALL_SQUARES
+            .iter()
+            .filter(|&&s| self.piece_on(s).is_some())
+            .fold(chess::EMPTY, |squares, &s| {
+                squares | BitBoard::from_square(s)
+            });
+
+        let pawns = self.squares_with(Piece::Pawn, color);
+        let knights = self.squares_with(Piece::Knight, color);
+        let king = self.squares_with(Piece::King, color);
+        let queens = self.squares_with(Piece::Queen, color);
+        let diagonal = self.squares_with(Piece::Bishop, color) | queens;
+        let straight = self.squares_with(Piece::Rook, color) | queens;
+
+        // a pawn of the other color on `square` would attack the pawns which
+        // attack it
+        let attackers = chess::get_pawn_attacks(square, !color, pawns)
+            | (chess::get_knight_moves(square) & knights)
+            | (chess::get_king_moves(square) & king)
+            | (chess::get_bishop_moves(square, blockers) & diagonal)
+            | (chess::get_rook_moves(square, blockers) & straight);
+
+        attackers != chess::EMPTY
+    }
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Setup::from_board(&Board::default())
+    }
+}
+
+fn piece_char(piece: Piece, color: Color) -> char {
+    let c = match piece {
+        Piece::Pawn => 'p',
+        Piece::Knight => 'n',
+        Piece::Bishop => 'b',
+        Piece::Rook => 'r',
+        Piece::Queen => 'q',
+        Piece::King => 'k',
+    };
+
+    match color {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(fen: &str) -> Setup {
+        Setup::from_board(&Board::from_str(fen).unwrap())
+    }
+
+    fn square(name: &str) -> Square {
+        Square::from_str(name).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_fen() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/R3K2R b KQ - 0 1",
+            "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1",
+        ] {
+            let setup = setup(fen);
+            assert_eq!(setup.fen(), fen);
+            assert_eq!(setup.validate(), Ok(Board::from_str(fen).unwrap()));
+        }
+    }
+
+    #[test]
+    fn places_and_moves_pieces() {
+        let mut setup = Setup::empty();
+        setup.put(square("e1"), Some((Piece::King, Color::White)));
+        setup.put(square("d8"), Some((Piece::King, Color::Black)));
+        setup.put(square("a2"), Some((Piece::Rook, Color::White)));
+        setup.move_piece(square("d8"), square("e8"));
+
+        assert_eq!(setup.fen(), "4k3/8/8/8/8/8/R7/4K3 w - - 0 1");
+        assert!(setup.validate().is_ok());
+
+        setup.halfmove_clock = 12;
+        assert_eq!(setup.fen(), "4k3/8/8/8/8/8/R7/4K3 w - - 12 1");
+        assert!(setup.validate().is_ok());
+    }
+
+    #[test]
+    fn explains_what_is_wrong() {
+        let mut no_king = Setup::empty();
+        no_king.put(square("e1"), Some((Piece::King, Color::White)));
+        assert!(no_king.validate().unwrap_err().contains("king"));
+
+        let mut pawn = setup("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        pawn.put(square("a8"), Some((Piece::Pawn, Color::Black)));
+        assert!(pawn.validate().unwrap_err().contains("back rank"));
+
+        let mut check = setup("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        check.put(square("e2"), Some((Piece::Rook, Color::White)));
+        check.side_to_move = Color::Black;
+        assert!(check.validate().is_ok());
+        check.side_to_move = Color::White;
+        assert!(check.validate().unwrap_err().contains("in check"));
+
+        let mut castling = setup("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        castling.castling[0].0 = true;
+        assert!(castling.validate().unwrap_err().contains("castle"));
+
+        let mut en_passant = setup("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        en_passant.en_passant = Some(File::D);
+        assert!(en_passant.validate().unwrap_err().contains("en passant"));
+    }
+}
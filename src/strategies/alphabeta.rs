@@ -1,33 +1,200 @@
 use crate::{
-    evaluation::{evaluate, Score},
+    evaluation::{Evaluator, Score},
+    tablebase::Tablebase,
     transposition::{Evaluation, TranspositionTable},
 };
 use chess::{Board, ChessMove, MoveGen};
 use rand::seq::SliceRandom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often (in nodes) the search checks whether it has run out of time.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Limits on how long a search may run. The search always stops at `depth`,
+/// and may stop sooner if `move_time` elapses or `stop` is raised. When it
+/// stops early, the result of the last completed iteration is used.
+///
+/// The rest say what the search has to work with: how it scores positions,
+/// roughly how much memory its transposition tables take up (by default
+/// about a million entries), and how many threads share the work.
+#[derive(Clone, Copy, Default)]
+pub struct SearchLimits<'a> {
+    pub depth: u8,
+    pub move_time: Option<Duration>,
+    pub stop: Option<&'a AtomicBool>,
+
+    pub evaluator: Evaluator,
+    pub hash_mb: Option<usize>,
+    pub threads: usize,
+}
+
+impl<'a> SearchLimits<'a> {
+    pub fn depth(depth: u8) -> Self {
+        SearchLimits {
+            depth,
+            ..Default::default()
+        }
+    }
+}
+
+/// Progress report emitted after each completed iteration of the search.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u8,
+    pub score: Score,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<ChessMove>,
+}
 
 /// Basic implementation of alpha-beta pruning.
 /// More detail is available on the [CPW Alpha-Beta
 /// page](https://www.chessprogramming.org/Alpha-Beta).
 ///
-/// This basic implementation will eventually be supplanted by an implementation
-/// which includes transposition tables, iterative deepening, and other
-/// enhancements. The interface **is expected to change**.
+/// This searches to a fixed depth; see `search` for time limits and progress
+/// reporting.
 ///
 /// Other improvements to come:
-///  - Principal variation search, to seed the next round of search
 ///  - Quiescence search, to avoid the horizon effect
 pub fn alpha_beta(board: &Board, depth: u8) -> Option<ChessMove> {
-    let mut transposition_table = TranspositionTable::new();
+    alpha_beta_with_tablebase(board, depth, None)
+}
+
+/// Alpha-beta search which consults Syzygy tablebases once few enough pieces
+/// are left on the board. At the root the tablebase move is played outright,
+/// and inside the tree a win/draw/loss probe cuts the search off.
+pub fn alpha_beta_with_tablebase(
+    board: &Board,
+    depth: u8,
+    tablebase: Option<&Tablebase>,
+) -> Option<ChessMove> {
+    search(board, &SearchLimits::depth(depth), tablebase, |_| {})
+}
+
+/// Iterative deepening alpha-beta search. Each iteration searches one ply
+/// deeper than the last, trying the previous best move first, and calls
+/// `report` once it completes. More detail is available on the [CPW
+/// Iterative Deepening page](https://www.chessprogramming.org/Iterative_Deepening).
+pub fn search<F: FnMut(&SearchInfo)>(
+    board: &Board,
+    limits: &SearchLimits,
+    tablebase: Option<&Tablebase>,
+    mut report: F,
+) -> Option<ChessMove> {
+    if let Some(m) = tablebase.and_then(|tb| tb.best_move(board)) {
+        return Some(m);
+    }
+
+    // each thread gets its own share of the moves at the root, and its own
+    // share of the memory for a transposition table
+    let threads = limits.threads.max(1);
+    let start = Instant::now();
+    let mut contexts: Vec<SearchContext> = (0..threads)
+        .map(|_| SearchContext {
+            transposition_table: match limits.hash_mb {
+                Some(mb) => TranspositionTable::with_size_mb(mb / threads),
+                None => TranspositionTable::new(),
+            },
+            tablebase,
+            evaluator: limits.evaluator,
+            nodes: 0,
+            start,
+            move_time: limits.move_time,
+            stop: limits.stop,
+            aborted: false,
+        })
+        .collect();
+
+    let mut moves = current_moves(board);
+    let mut best_move = None;
+
+    for depth in 1..=limits.depth.max(1) {
+        let results: Vec<(Score, Option<ChessMove>)> = if threads == 1 {
+            vec![search_root(board, &moves, depth, &mut contexts[0])]
+        } else {
+            let moves = &moves;
+            thread::scope(|scope| {
+                let handles: Vec<_> = contexts
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, context)| {
+                        let share: Vec<ChessMove> =
+                            moves.iter().skip(i).step_by(threads).copied().collect();
+                        scope.spawn(move || search_root(board, &share, depth, context))
+                    })
+                    .collect();
+
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("search thread panicked"))
+                    .collect()
+            })
+        };
 
+        if contexts.iter().any(|context| context.aborted) {
+            break;
+        }
+
+        // ties go to the first thread, which has the previous best move
+        let (winner, &(best_score, iteration_best)) = results
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, (score, _))| *score)
+            .unwrap();
+
+        best_move = iteration_best;
+
+        if let Some(m) = best_move {
+            let index = moves.iter().position(|&n| n == m).unwrap();
+            moves[..=index].rotate_right(1);
+        }
+
+        let context = &mut contexts[winner];
+        context.transposition_table.store(
+            board.get_hash(),
+            depth,
+            Evaluation::Exact(best_score),
+            best_move,
+        );
+
+        report(&SearchInfo {
+            depth,
+            score: best_score,
+            nodes: contexts.iter().map(|context| context.nodes).sum(),
+            elapsed: start.elapsed(),
+            pv: contexts[winner].principal_variation(board, depth),
+        });
+    }
+
+    // If we ran out of time before even one iteration finished, any legal move
+    // is better than forfeiting on time.
+    best_move.or_else(|| moves.first().copied())
+}
+
+/// Searches each of `moves` from `board` in turn, returning the best score
+/// and the move which got it.
+fn search_root(
+    board: &Board,
+    moves: &[ChessMove],
+    depth: u8,
+    context: &mut SearchContext,
+) -> (Score, Option<ChessMove>) {
     let mut best_score = -40_000;
     let mut best_move = None;
 
     let mut alpha = -80_000;
     let beta = 80_000;
 
-    for m in current_moves(board) {
+    for &m in moves {
         let board = board.make_move_new(m);
-        let score = -alpha_beta_helper(board, -beta, -alpha, depth - 1, &mut transposition_table);
+        let score = -alpha_beta_helper(board, -beta, -alpha, depth - 1, context);
+
+        if context.aborted {
+            break;
+        }
 
         if score > best_score {
             best_score = score;
@@ -37,11 +204,57 @@ pub fn alpha_beta(board: &Board, depth: u8) -> Option<ChessMove> {
         if score > alpha {
             alpha = score;
         }
+    }
+
+    (best_score, best_move)
+}
+
+struct SearchContext<'a> {
+    transposition_table: TranspositionTable,
+    tablebase: Option<&'a Tablebase>,
+    evaluator: Evaluator,
+
+    nodes: u64,
+    start: Instant,
+    move_time: Option<Duration>,
+    stop: Option<&'a AtomicBool>,
+    aborted: bool,
+}
+
+impl<'a> SearchContext<'a> {
+    fn should_abort(&mut self) -> bool {
+        if !self.aborted && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            let out_of_time = self.move_time.is_some_and(|t| self.start.elapsed() >= t);
+            let stopped = self.stop.is_some_and(|s| s.load(Ordering::Relaxed));
 
-        println!("move: {}, score: {}", m, score);
+            self.aborted = out_of_time || stopped;
+        }
+
+        self.aborted
     }
 
-    best_move
+    /// Follows the best moves stored in the transposition table from `board`.
+    fn principal_variation(&mut self, board: &Board, depth: u8) -> Vec<ChessMove> {
+        let mut pv = vec![];
+        let mut board = *board;
+
+        while pv.len() < depth as usize {
+            let following_move = self
+                .transposition_table
+                .retrieve(board.get_hash())
+                .and_then(|entry| entry.following_move);
+
+            match following_move {
+                Some(m) if board.legal(m) => {
+                    pv.push(m);
+                    board = board.make_move_new(m);
+                }
+                _ => break,
+            }
+        }
+
+        pv
+    }
 }
 
 fn alpha_beta_helper(
@@ -49,12 +262,17 @@ fn alpha_beta_helper(
     mut alpha: Score,
     mut beta: Score,
     depth_left: u8,
-    transposition_table: &mut TranspositionTable,
+    context: &mut SearchContext,
 ) -> Score {
+    if context.should_abort() {
+        return 0;
+    }
+    context.nodes += 1;
+
     let hash = board.get_hash();
 
     // Reuse results if they've been computed before
-    if let Some(entry) = transposition_table.retrieve(hash) {
+    if let Some(entry) = context.transposition_table.retrieve(hash) {
         if entry.depth == depth_left {
             let score = match entry.eval {
                 Evaluation::Exact(score) => return score,
@@ -74,28 +292,49 @@ fn alpha_beta_helper(
         }
     }
 
+    // Once we're down to a tablebase position, the result is already known
+    if let Some(score) = context.tablebase.and_then(|tb| tb.probe_wdl(&board)) {
+        context
+            .transposition_table
+            .store(hash, depth_left, Evaluation::Exact(score), None);
+        return score;
+    }
+
     if depth_left == 0 {
         let color = board.side_to_move();
-        let score = evaluate(&board, color, color);
-        transposition_table.store(hash, depth_left, Evaluation::Exact(score));
+        let score = context.evaluator.evaluate(&board, color, color);
+        context
+            .transposition_table
+            .store(hash, depth_left, Evaluation::Exact(score), None);
         return score;
     }
 
+    let mut best_move = None;
+
     for m in current_moves(&board) {
         let board = board.make_move_new(m);
 
-        let score = -alpha_beta_helper(board, -beta, -alpha, depth_left - 1, transposition_table);
+        let score = -alpha_beta_helper(board, -beta, -alpha, depth_left - 1, context);
+
+        if context.aborted {
+            return 0;
+        }
 
         if score >= beta {
-            transposition_table.store(hash, depth_left, Evaluation::Beta(beta));
+            context
+                .transposition_table
+                .store(hash, depth_left, Evaluation::Beta(beta), Some(m));
             return beta;
         }
         if score > alpha {
             alpha = score;
+            best_move = Some(m);
         }
     }
 
-    transposition_table.store(hash, depth_left, Evaluation::Alpha(alpha));
+    context
+        .transposition_table
+        .store(hash, depth_left, Evaluation::Alpha(alpha), best_move);
     alpha
 }
 
@@ -133,4 +372,57 @@ mod tests {
             assert!(expected.contains(&candidate));
         }
     }
+
+    #[test]
+    fn search_reports_each_iteration() {
+        let board = Board::default();
+        let mut depths = vec![];
+
+        let candidate = search(&board, &SearchLimits::depth(3), None, |info| {
+            assert!(!info.pv.is_empty());
+            depths.push(info.depth);
+        });
+
+        assert!(candidate.is_some());
+        assert_eq!(depths, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn search_stops_when_asked() {
+        let board = Board::default();
+        let stop = AtomicBool::new(true);
+        let limits = SearchLimits {
+            depth: 20,
+            stop: Some(&stop),
+            ..Default::default()
+        };
+
+        let mut iterations = 0;
+        let candidate = search(&board, &limits, None, |_| iterations += 1);
+
+        // even a search stopped immediately must come up with a move
+        assert!(candidate.is_some());
+        assert_eq!(iterations, 0);
+    }
+
+    #[test]
+    fn threads_share_the_search() {
+        let board = Board::from_str("2r4k/6pp/8/4N3/8/1Q6/B5PP/7K w - - 0 1").unwrap();
+        let limits = SearchLimits {
+            depth: 4,
+            threads: 3,
+            hash_mb: Some(8),
+            ..Default::default()
+        };
+
+        let mut pv = vec![];
+        let candidate = search(&board, &limits, None, |info| pv = info.pv.clone());
+
+        let expected = [
+            ChessMove::from_san(&board, "Qg8").unwrap(),
+            ChessMove::from_san(&board, "Ng6").unwrap(),
+        ];
+        assert!(expected.contains(&candidate.unwrap()));
+        assert_eq!(pv.first(), candidate.as_ref());
+    }
 }
@@ -3,7 +3,7 @@ use rand::seq::IteratorRandom;
 
 mod alphabeta;
 
-pub use alphabeta::alpha_beta;
+pub use alphabeta::{alpha_beta, alpha_beta_with_tablebase, search, SearchInfo, SearchLimits};
 
 pub fn first_legal_move(game: &Game) -> Option<ChessMove> {
     let mut moves = MoveGen::new_legal(&game.current_position());
@@ -0,0 +1,159 @@
+use chess::{Board, ChessMove};
+use shakmaty::{fen::Fen, CastlingMode, Chess};
+use shakmaty_syzygy::{Tablebase as SyzygyTables, Wdl};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::evaluation::Score;
+
+/// Environment variable naming the directory the tablebases are loaded from.
+pub const SYZYGY_PATH_VAR: &str = "PATZER_SYZYGY_PATH";
+
+/// Score given to a position the tablebases say is won. It is kept well below
+/// the checkmate value so that a mate found by search is still preferred.
+pub const TABLEBASE_WIN: Score = 10_000;
+
+/// Syzygy endgame tablebases, loaded from a local directory of `.rtbw` (WDL)
+/// and `.rtbz` (DTZ) files. See the [CPW Syzygy
+/// page](https://www.chessprogramming.org/Syzygy_Bases) for background.
+///
+/// The probing code works on `shakmaty` positions, so boards are converted
+/// through FEN on the way in and moves through UCI notation on the way out.
+/// That isn't cheap, which is why callers should check `in_range` first.
+pub struct Tablebase {
+    tables: SyzygyTables<Chess>,
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Tablebase {
+            tables: SyzygyTables::new(),
+        }
+    }
+
+    /// Loads every table file found in `path`, returning how many were added.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, path: P) -> std::io::Result<usize> {
+        self.tables.add_directory(path)
+    }
+
+    /// Creates a tablebase from the tables found in `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let mut tablebase = Tablebase::new();
+        tablebase.add_directory(path)?;
+        Ok(tablebase)
+    }
+
+    /// Loads the tables from the directory named by `PATZER_SYZYGY_PATH`.
+    /// Returns `None` if the variable is unset or the directory is unusable.
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var_os(SYZYGY_PATH_VAR)?;
+
+        match Tablebase::open(&path) {
+            Ok(tablebase) => Some(tablebase),
+            Err(e) => {
+                eprintln!("could not load tablebases from {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// The largest number of pieces (kings included) covered by the loaded
+    /// tables, or 0 if nothing has been loaded.
+    pub fn max_pieces(&self) -> usize {
+        self.tables.max_pieces()
+    }
+
+    /// Whether the board has few enough pieces that it's worth probing.
+    pub fn in_range(&self, board: &Board) -> bool {
+        let pieces = board.combined().popcnt() as usize;
+        pieces <= self.max_pieces()
+    }
+
+    /// Probes the win/draw/loss tables, returning a score from the
+    /// perspective of the side to move. Cursed wins and blessed losses are
+    /// drawn under the fifty-move rule, so they score as draws.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Score> {
+        if !self.in_range(board) {
+            return None;
+        }
+
+        let position = to_position(board)?;
+        let wdl = self.tables.probe_wdl_after_zeroing(&position).ok()?;
+
+        Some(match wdl {
+            Wdl::Win => TABLEBASE_WIN,
+            Wdl::Loss => -TABLEBASE_WIN,
+            Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0,
+        })
+    }
+
+    /// Picks the tablebase-optimal move: the one that preserves the best
+    /// outcome while making progress towards it as quickly as possible.
+    pub fn best_move(&self, board: &Board) -> Option<ChessMove> {
+        if !self.in_range(board) {
+            return None;
+        }
+
+        let position = to_position(board)?;
+        let (m, _) = self.tables.best_move(&position).ok()??;
+        let uci = m.to_uci(CastlingMode::Standard).to_string();
+
+        ChessMove::from_str(&uci).ok()
+    }
+}
+
+impl Default for Tablebase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn to_position(board: &Board) -> Option<Chess> {
+    let fen = Fen::from_str(&board.to_string()).ok()?;
+    fen.into_position(CastlingMode::Standard).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess::Board;
+    use std::str::FromStr;
+
+    fn tables_dir() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("syzygy")
+    }
+
+    #[test]
+    fn empty_tablebase_does_not_probe() {
+        let tablebase = Tablebase::new();
+        let board = Board::from_str("8/8/8/8/8/2k5/8/K6R w - - 0 1").unwrap();
+
+        assert_eq!(tablebase.max_pieces(), 0);
+        assert!(!tablebase.in_range(&board));
+        assert_eq!(tablebase.probe_wdl(&board), None);
+        assert_eq!(tablebase.best_move(&board), None);
+    }
+
+    #[test]
+    fn probes_krk() {
+        let tablebase = Tablebase::open(tables_dir()).unwrap();
+
+        let white_to_move = Board::from_str("8/8/8/8/8/2k5/8/K6R w - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_wdl(&white_to_move), Some(TABLEBASE_WIN));
+
+        let black_to_move = Board::from_str("8/8/8/8/8/2k5/8/K6R b - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_wdl(&black_to_move), Some(-TABLEBASE_WIN));
+
+        let m = tablebase.best_move(&white_to_move).unwrap();
+        let after = white_to_move.make_move_new(m);
+        assert_eq!(tablebase.probe_wdl(&after), Some(-TABLEBASE_WIN));
+    }
+
+    #[test]
+    fn probes_bare_minor_as_draw() {
+        let tablebase = Tablebase::open(tables_dir()).unwrap();
+        let board = Board::from_str("8/8/8/8/8/2k5/8/K6N w - - 0 1").unwrap();
+
+        assert_eq!(tablebase.probe_wdl(&board), Some(0));
+    }
+}
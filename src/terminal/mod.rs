@@ -0,0 +1,641 @@
+use chess::{BitBoard, Color, Piece};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use eframe::egui::Color32;
+use std::io;
+use std::time::Duration;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{self, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+use tui::{Frame, Terminal};
+
+use crate::app_state::AppState;
+use crate::clock::format_duration;
+use crate::describe;
+use crate::game_state::attacked_squares;
+use crate::prompt::{parse_command, piece_symbol, Command};
+use crate::ui_state::{square_at, Coords};
+
+/// How often the screen is redrawn when nothing's pressed, so clocks tick and
+/// engine moves show up.
+const TICK: Duration = Duration::from_millis(100);
+
+/// How long an illegal move stays marked on the board.
+const ILLEGAL_MOVE_SHOWN: Duration = Duration::from_millis(500);
+
+const KEYS: &str = "arrows move, enter picks up and puts down, : command, s start, n new, \
+                    u take back, f flip, a analysis, ? help, q quit";
+
+const HELP: &str = "Move the cursor with the arrow keys or hjkl, and press enter or space to pick \
+up a piece and again to put it down. Esc drops it.
+
+s        start the game
+n        new game
+u        take back
+, .      step through the moves
+home end go to the start or the end of the game
+f        flip the board
+t        show the squares the opponent attacks
+a        turn the analysis on or off
+:        type a move in SAN or UCI, or a command: undo, hint, fen, pgn, flip, describe, draw, resign
+q        quit";
+
+/// patzer in the terminal: the same game as the GUI, through `AppState`,
+/// drawn with text and played from the keyboard.
+pub struct TuiApp {
+    state: AppState,
+    ascii: bool,
+
+    /// The square the keyboard is on.
+    cursor: Coords,
+
+    /// What's been typed after `:`, while the command line is open.
+    command_line: Option<String>,
+
+    /// Where the selected pawn is being promoted, while asking what to.
+    promotion: Option<Coords>,
+
+    /// One line for the bottom of the screen, until the next key press.
+    message: Option<String>,
+
+    /// A title and some text shown over everything else, until the next key
+    /// press.
+    popup: Option<(String, String)>,
+
+    quit: bool,
+}
+
+impl TuiApp {
+    pub fn new(state: AppState, ascii: bool) -> Self {
+        // start on the king's pawn of whoever's at the bottom
+        let cursor = if state.board_flipped() {
+            (6, 4)
+        } else {
+            (1, 4)
+        };
+
+        TuiApp {
+            state,
+            ascii,
+            cursor,
+            command_line: None,
+            promotion: None,
+            message: None,
+            popup: None,
+            quit: false,
+        }
+    }
+
+    /// Draws and handles keys until the user quits.
+    pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        while !self.quit {
+            // like the GUI, keep the analysis on whatever's on the board
+            if self.state.is_analysing() {
+                self.state.follow_analysis();
+            }
+
+            terminal.draw(|f| self.draw(f))?;
+
+            if event::poll(TICK)? {
+                if let Event::Key(key) = event::read()? {
+                    self.handle_key(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        self.message = None;
+        if self.popup.take().is_some() {
+            return;
+        }
+
+        if let Some(mut line) = self.command_line.take() {
+            match key.code {
+                KeyCode::Enter => self.run_command(&line),
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    line.pop();
+                    self.command_line = Some(line);
+                }
+                KeyCode::Char(c) => {
+                    line.push(c);
+                    self.command_line = Some(line);
+                }
+                _ => self.command_line = Some(line),
+            }
+            return;
+        }
+
+        if let Some(to) = self.promotion.take() {
+            let piece = match key.code {
+                KeyCode::Char('q') => Piece::Queen,
+                KeyCode::Char('r') => Piece::Rook,
+                KeyCode::Char('b') => Piece::Bishop,
+                KeyCode::Char('n') => Piece::Knight,
+                KeyCode::Esc => {
+                    self.state.ui_select_square(None);
+                    return;
+                }
+                _ => {
+                    self.promotion = Some(to);
+                    return;
+                }
+            };
+            self.state.ui_attempt_move(Some(to), Some(piece));
+            return;
+        }
+
+        // up is towards the top of the screen, whichever way the board is
+        let forward = if self.state.board_flipped() { -1 } else { 1 };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(forward, 0),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(-forward, 0),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(0, -forward),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(0, forward),
+            KeyCode::Enter | KeyCode::Char(' ') => self.press_square(),
+            KeyCode::Esc => self.state.ui_select_square(None),
+            KeyCode::Char(':') => self.command_line = Some(String::new()),
+            KeyCode::Char(',') => self.state.step_view(-1),
+            KeyCode::Char('.') => self.state.step_view(1),
+            KeyCode::Home => self.state.view_ply(Some(0)),
+            KeyCode::End => self.state.view_ply(None),
+            KeyCode::Char('s') => self.state.start_game(),
+            KeyCode::Char('n') => self.state.reset_game(),
+            KeyCode::Char('u') => self.take_back(),
+            KeyCode::Char('f') => self.state.flip_board(),
+            KeyCode::Char('t') => self.state.set_show_threats(!self.state.show_threats()),
+            KeyCode::Char('a') => self.state.set_analysing(!self.state.is_analysing()),
+            KeyCode::Char('?') => self.popup = Some(("Help".into(), HELP.into())),
+            KeyCode::Char('q') => self.quit = true,
+            _ => {}
+        }
+    }
+
+    fn move_cursor(&mut self, ranks: isize, files: isize) {
+        let step = |index: usize, by: isize| (index as isize + by).clamp(0, 7) as usize;
+        self.cursor = (step(self.cursor.0, ranks), step(self.cursor.1, files));
+    }
+
+    /// Picks up the piece under the cursor, or puts the one picked up down
+    /// there, the way clicking does in the GUI.
+    fn press_square(&mut self) {
+        let cursor = self.cursor;
+        let selected = match self.state.ui_selected_square() {
+            Some(selected) if selected != cursor && !self.state.ui_can_select(cursor) => selected,
+            Some(selected) if selected == cursor => {
+                self.state.ui_select_square(None);
+                return;
+            }
+            _ => {
+                if self.state.ui_can_select(cursor) {
+                    self.state.ui_select_square(Some(cursor));
+                } else if !self.state.is_started() {
+                    self.message = Some("Press s to start the game".into());
+                } else if !self.state.human_to_move() {
+                    self.message = Some("It's not your move".into());
+                }
+                return;
+            }
+        };
+
+        let board = self.state.displayed_position();
+        let pawn = board.piece_on(square_at(selected)) == Some(Piece::Pawn);
+        let legal = self
+            .state
+            .ui_legal_destinations()
+            .iter()
+            .any(|&(to, _)| to == cursor);
+        if pawn && legal && (cursor.0 == 0 || cursor.0 == 7) {
+            self.promotion = Some(cursor);
+            return;
+        }
+
+        self.state.ui_attempt_move(Some(cursor), None);
+        if !legal {
+            self.message = Some("That move isn't legal".into());
+        }
+    }
+
+    fn take_back(&mut self) {
+        if self.state.can_take_back() {
+            self.state.take_back();
+        } else {
+            self.message = Some("There's nothing to take back".into());
+        }
+    }
+
+    fn run_command(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+
+        let board = self.state.displayed_position();
+        let command = match parse_command(&board, line) {
+            Ok(command) => command,
+            Err(e) => {
+                self.message = Some(e);
+                return;
+            }
+        };
+
+        let human = self.state.human_side();
+        let playing = self.state.is_started() && !self.state.is_finished();
+        match command {
+            Command::Move(_) if !playing => {
+                self.message = Some("The game isn't going; press s to start it".into());
+            }
+            Command::Move(_)
+                if !self.state.human_to_move() || self.state.viewed_ply().is_some() =>
+            {
+                self.message = Some("It's not your move".into());
+            }
+            Command::Move(m) => self.state.make_move(m),
+            Command::Undo => self.take_back(),
+            Command::Hint => {
+                self.state.set_analysing(true);
+                self.message = Some("The analysis shows the best move it's found".into());
+            }
+            Command::Fen => self.popup = Some(("FEN".into(), board.to_string())),
+            Command::Pgn => self.popup = Some(("PGN".into(), self.state.pgn())),
+            Command::Flip => self.state.flip_board(),
+            Command::Describe => {
+                self.popup = Some(("Position".into(), describe::position(&board)));
+            }
+            Command::Resign | Command::Draw if !playing => {
+                self.message = Some("The game isn't going".into());
+            }
+            Command::Resign => match human {
+                Some(color) => self.state.resign(color),
+                None => self.message = Some("Only the computer is playing".into()),
+            },
+            Command::Draw => match human {
+                Some(color) if self.state.draw_offer() == Some(!color) => {
+                    self.state.accept_draw();
+                }
+                Some(_) if self.state.human_to_move() && self.state.can_claim_draw() => {
+                    self.state.claim_draw();
+                }
+                Some(color) => {
+                    self.state.offer_draw(color);
+                    self.message = Some("You offer a draw".into());
+                }
+                None => self.message = Some("Only the computer is playing".into()),
+            },
+            Command::Help => self.popup = Some(("Help".into(), HELP.into())),
+        }
+    }
+
+    fn draw<B: Backend>(&self, f: &mut Frame<B>) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(f.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(BOARD_WIDTH + 14), Constraint::Min(20)].as_ref())
+            .split(rows[0]);
+
+        self.draw_game(f, columns[0]);
+
+        let side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(6), Constraint::Min(3)].as_ref())
+            .split(columns[1]);
+        self.draw_analysis(f, side[0]);
+        self.draw_move_list(f, side[1]);
+
+        self.draw_bottom_line(f, rows[1]);
+
+        if let Some((title, text)) = &self.popup {
+            let area = centered(f.size(), 70, 60);
+            let popup = Paragraph::new(text.as_str())
+                .block(Block::default().borders(Borders::ALL).title(title.as_str()))
+                .wrap(Wrap { trim: false });
+            f.render_widget(Clear, area);
+            f.render_widget(popup, area);
+        }
+    }
+
+    /// The board between the players, with the game's status below.
+    fn draw_game<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(2),
+                    Constraint::Length(BOARD_HEIGHT),
+                    Constraint::Length(2),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let flipped = self.state.board_flipped();
+        let (top, bottom) = if flipped {
+            (Color::White, Color::Black)
+        } else {
+            (Color::Black, Color::White)
+        };
+
+        f.render_widget(Paragraph::new(self.player_lines(top)), parts[0]);
+        let board =
+            Paragraph::new(self.board_lines()).block(Block::default().borders(Borders::ALL));
+        let board_area = Rect {
+            width: BOARD_WIDTH.min(parts[1].width),
+            ..parts[1]
+        };
+        f.render_widget(board, board_area);
+        f.render_widget(Paragraph::new(self.player_lines(bottom)), parts[2]);
+
+        let mut status = vec![Spans::from(Span::styled(
+            self.state.status_message(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))];
+        if let Some(color) = self.state.draw_offer() {
+            status.push(Spans::from(format!(
+                "{:?} offers a draw; type :draw to accept",
+                color
+            )));
+        }
+        if self.state.viewed_ply().is_some() {
+            status.push(Spans::from(
+                "Viewing an earlier position; press end to go back",
+            ));
+        }
+        f.render_widget(Paragraph::new(status).wrap(Wrap { trim: true }), parts[3]);
+    }
+
+    /// The player's name and clock, and what their engine says.
+    fn player_lines(&self, color: Color) -> Vec<Spans<'static>> {
+        let (white_name, black_name) = self.state.player_names();
+        let (white_info, black_info) = self.state.engine_info();
+        let (name, info) = match color {
+            Color::White => (white_name, white_info),
+            Color::Black => (black_name, black_info),
+        };
+
+        let clock = self.state.clock();
+        let mut line = vec![Span::raw(name)];
+        if let Some(config) = self.state.player_config(color) {
+            line.push(Span::styled(
+                format!(" {}", config),
+                Style::default().add_modifier(Modifier::DIM),
+            ));
+        }
+        if let Some(remaining) = clock.remaining(color) {
+            let style = if clock.running() == Some(color) {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().add_modifier(Modifier::DIM)
+            };
+            line.push(Span::styled(
+                format!("  {}", format_duration(remaining)),
+                style,
+            ));
+        }
+
+        let info = info.unwrap_or_default();
+        vec![
+            Spans::from(line),
+            Spans::from(Span::styled(
+                info,
+                Style::default().add_modifier(Modifier::DIM),
+            )),
+        ]
+    }
+
+    fn board_lines(&self) -> Vec<Spans<'static>> {
+        let theme = self.state.theme();
+        let board = self.state.displayed_position();
+        let flipped = self.state.board_flipped();
+        let selected = self.state.ui_selected_square();
+        let destinations = self.state.ui_legal_destinations();
+        let last_move = self.state.displayed_last_move();
+        let illegal = self
+            .state
+            .ui_illegal_drop()
+            .filter(|&(_, ago)| ago < ILLEGAL_MOVE_SHOWN)
+            .map(|(coords, _)| coords);
+        let threats = if self.state.show_threats() {
+            attacked_squares(&board, !board.side_to_move())
+        } else {
+            chess::EMPTY
+        };
+        let in_check =
+            (*board.checkers() != chess::EMPTY).then(|| board.king_square(board.side_to_move()));
+
+        let mut ranks: Vec<usize> = (0..8).collect();
+        let mut files: Vec<usize> = (0..8).collect();
+        if flipped {
+            files.reverse();
+        } else {
+            ranks.reverse();
+        }
+
+        let mut lines = vec![];
+        for &rank in &ranks {
+            let mut spans = vec![Span::raw(format!("{} ", rank + 1))];
+
+            for &file in &files {
+                let coords = (rank, file);
+                let square = square_at(coords);
+
+                // a1 is dark
+                let mut background = if (rank + file) % 2 == 0 {
+                    theme.dark_square
+                } else {
+                    theme.light_square
+                };
+                if last_move.is_some_and(|m| m.get_source() == square || m.get_dest() == square) {
+                    background = blend(theme.last_move, background);
+                }
+                if threats & BitBoard::from_square(square) != chess::EMPTY {
+                    background = blend(theme.threat, background);
+                }
+                if in_check == Some(square) {
+                    background = blend(theme.check, background);
+                }
+                if destinations.iter().any(|&(to, _)| to == coords) {
+                    background = blend(theme.move_hint, background);
+                }
+                if selected == Some(coords) {
+                    background = theme.selected_square;
+                }
+                if illegal == Some(coords) {
+                    background = blend(theme.illegal_move, background);
+                }
+
+                let symbol = match (board.piece_on(square), board.color_on(square)) {
+                    (Some(piece), Some(color)) => piece_symbol(piece, color, self.ascii),
+                    _ => ' ',
+                };
+
+                let mut style = Style::default()
+                    .fg(style::Color::Rgb(0, 0, 0))
+                    .bg(rgb(background))
+                    .add_modifier(Modifier::BOLD);
+                if coords == self.cursor {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                spans.push(Span::styled(format!(" {} ", symbol), style));
+            }
+
+            lines.push(Spans::from(spans));
+        }
+
+        let names: String = files
+            .iter()
+            .map(|&file| format!(" {} ", (b'a' + file as u8) as char))
+            .collect();
+        lines.push(Spans::from(format!("  {}", names)));
+        lines
+    }
+
+    fn draw_analysis<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title("Analysis");
+
+        let lines = if !self.state.is_analysing() {
+            vec![Spans::from("Off; press a to turn it on")]
+        } else {
+            match self.state.analysis() {
+                Some(line) => vec![
+                    Spans::from(vec![
+                        Span::styled(
+                            line.score_text(),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(format!(
+                            "  depth {}  {} kN/s",
+                            line.depth,
+                            line.nodes_per_second / 1000
+                        )),
+                    ]),
+                    Spans::from(line.pv.join(" ")),
+                ],
+                None => vec![Spans::from("Thinking…")],
+            }
+        };
+
+        f.render_widget(
+            Paragraph::new(lines).block(block).wrap(Wrap { trim: true }),
+            area,
+        );
+    }
+
+    /// The moves in numbered rows, scrolled to keep the one on the board in
+    /// view.
+    fn draw_move_list<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let moves = self.state.move_list();
+        let displayed = self.state.displayed_ply();
+
+        // if black moved first, white's half of the first row is left empty
+        let offset = usize::from(self.state.start_position().side_to_move() == Color::Black);
+        let rows = (moves.len() + offset).div_ceil(2);
+
+        let mut lines = vec![];
+        for row in 0..rows {
+            let mut spans = vec![Span::raw(format!("{:>3}. ", row + 1))];
+            for column in 0..2 {
+                let ply = (row * 2 + column + 1).checked_sub(offset);
+                let san = ply.and_then(|ply| Some((ply, moves.get(ply.checked_sub(1)?)?)));
+                let (text, style) = match san {
+                    Some((ply, san)) if ply == displayed => (
+                        san.clone(),
+                        Style::default().add_modifier(Modifier::REVERSED),
+                    ),
+                    Some((_, san)) => (san.clone(), Style::default()),
+                    None => ("...".to_string(), Style::default()),
+                };
+                spans.push(Span::styled(format!("{:<8}", text), style));
+            }
+            lines.push(Spans::from(spans));
+        }
+
+        let visible = area.height.saturating_sub(2) as usize;
+        let displayed_row = (displayed + offset).saturating_sub(1) / 2;
+        let scroll = (displayed_row + 1).saturating_sub(visible);
+
+        f.render_widget(
+            Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title("Moves"))
+                .scroll((scroll as u16, 0)),
+            area,
+        );
+    }
+
+    fn draw_bottom_line<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let line = if let Some(line) = &self.command_line {
+            f.set_cursor(area.x + 1 + line.chars().count() as u16, area.y);
+            format!(":{}", line)
+        } else if self.promotion.is_some() {
+            "Promote to (q)ueen, (r)ook, (b)ishop or k(n)ight?".to_string()
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else {
+            KEYS.to_string()
+        };
+
+        f.render_widget(Paragraph::new(line), area);
+    }
+}
+
+/// Three columns a square, the rank numbers and the border.
+const BOARD_WIDTH: u16 = 8 * 3 + 2 + 2;
+
+/// A line a square, the file names and the border.
+const BOARD_HEIGHT: u16 = 8 + 1 + 2;
+
+/// A rectangle in the middle of `area`, taking up the given percentages of
+/// it.
+fn centered(area: Rect, width_percent: u16, height_percent: u16) -> Rect {
+    let width = area.width * width_percent / 100;
+    let height = area.height * height_percent / 100;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Paints `over`, which may be translucent, over an opaque color.
+fn blend(over: Color32, under: Color32) -> Color32 {
+    let [r, g, b, a] = over.to_array();
+    // Color32 is premultiplied, so only what's underneath needs scaling
+    let mix =
+        |over: u8, under: u8| over.saturating_add((under as u16 * (255 - a as u16) / 255) as u8);
+    Color32::from_rgb(mix(r, under.r()), mix(g, under.g()), mix(b, under.b()))
+}
+
+fn rgb(color: Color32) -> style::Color {
+    style::Color::Rgb(color.r(), color.g(), color.b())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blends_translucent_colors() {
+        let white = Color32::from_rgb(255, 255, 255);
+        assert_eq!(
+            blend(Color32::from_rgb(10, 20, 30), white),
+            Color32::from_rgb(10, 20, 30)
+        );
+        assert_eq!(blend(Color32::TRANSPARENT, white), white);
+        assert_eq!(
+            blend(Color32::from_rgba_unmultiplied(0, 0, 0, 128), white),
+            Color32::from_rgb(127, 127, 127)
+        );
+    }
+}
@@ -1,13 +1,263 @@
 use eframe::egui::Color32;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-pub fn light_square() -> Color32 {
-    Color32::from_rgb(234, 233, 210)
+/// The colors the board is drawn in, and whether the rest of the GUI is
+/// light or dark to go with them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub dark_mode: bool,
+
+    pub light_square: Color32,
+    pub dark_square: Color32,
+    pub selected_square: Color32,
+
+    /// Dots and rings on the squares the selected piece can move to.
+    pub move_hint: Color32,
+
+    pub illegal_move: Color32,
+
+    /// The from and to squares of the last move.
+    pub last_move: Color32,
+
+    /// The glow around a king in check.
+    pub check: Color32,
+
+    /// Squares attacked by the side not to move.
+    pub threat: Color32,
+
+    /// Arrows and marked squares, in the order PGN's `[%cal]` names their
+    /// colors: green, red, yellow and blue.
+    pub arrows: [Color32; 4],
+}
+
+impl Theme {
+    /// Brown and cream, like a wooden board.
+    pub fn classic() -> Self {
+        Theme {
+            name: "Classic".into(),
+            light_square: Color32::from_rgb(240, 217, 181),
+            dark_square: Color32::from_rgb(181, 136, 99),
+            selected_square: Color32::from_rgb(205, 210, 106),
+            last_move: Color32::from_rgba_unmultiplied(155, 199, 0, 105),
+            ..Theme::blue()
+        }
+    }
+
+    /// The colors patzer has always had.
+    pub fn blue() -> Self {
+        Theme {
+            name: "Blue".into(),
+            dark_mode: false,
+            light_square: Color32::from_rgb(234, 233, 210),
+            dark_square: Color32::from_rgb(75, 115, 153),
+            selected_square: Color32::from_rgb(116, 198, 232),
+            move_hint: Color32::from_rgba_unmultiplied(20, 85, 30, 110),
+            illegal_move: Color32::from_rgba_unmultiplied(220, 40, 40, 160),
+            last_move: Color32::from_rgba_unmultiplied(205, 210, 106, 150),
+            check: Color32::from_rgb(230, 30, 30),
+            threat: Color32::from_rgba_unmultiplied(200, 60, 60, 60),
+            arrows: [
+                Color32::from_rgba_unmultiplied(21, 120, 27, 170),
+                Color32::from_rgba_unmultiplied(136, 32, 32, 170),
+                Color32::from_rgba_unmultiplied(230, 143, 0, 170),
+                Color32::from_rgba_unmultiplied(0, 48, 136, 170),
+            ],
+        }
+    }
+
+    pub fn green() -> Self {
+        Theme {
+            name: "Green".into(),
+            light_square: Color32::from_rgb(238, 238, 210),
+            dark_square: Color32::from_rgb(118, 150, 86),
+            selected_square: Color32::from_rgb(186, 202, 68),
+            last_move: Color32::from_rgba_unmultiplied(246, 246, 105, 150),
+            ..Theme::blue()
+        }
+    }
+
+    /// Stark squares and loud highlights, on a dark GUI.
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: "High contrast".into(),
+            dark_mode: true,
+            light_square: Color32::from_rgb(255, 255, 255),
+            dark_square: Color32::from_rgb(110, 110, 110),
+            selected_square: Color32::from_rgb(0, 120, 255),
+            move_hint: Color32::from_rgba_unmultiplied(255, 140, 0, 220),
+            illegal_move: Color32::from_rgba_unmultiplied(255, 0, 0, 200),
+            last_move: Color32::from_rgba_unmultiplied(255, 215, 0, 170),
+            check: Color32::from_rgb(255, 0, 0),
+            threat: Color32::from_rgba_unmultiplied(255, 0, 255, 90),
+            arrows: [
+                Color32::from_rgb(0, 200, 0),
+                Color32::from_rgb(255, 0, 0),
+                Color32::from_rgb(255, 200, 0),
+                Color32::from_rgb(0, 100, 255),
+            ],
+        }
+    }
+
+    pub fn builtin() -> Vec<Theme> {
+        vec![
+            Theme::classic(),
+            Theme::blue(),
+            Theme::green(),
+            Theme::high_contrast(),
+        ]
+    }
+
+    /// Where user themes are kept, under the user's config directory.
+    pub fn user_themes_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("patzer").join("themes.toml"))
+    }
+
+    pub fn load_user_themes(path: &Path) -> Result<Vec<Theme>, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Theme::parse_user_themes(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Reads themes from TOML, each a `[[theme]]` table with a `name` and
+    /// any colors which differ from the default theme, in hex:
+    ///
+    /// ```toml
+    /// [[theme]]
+    /// name = "Coral"
+    /// dark_mode = true
+    /// light_square = "#f5e6da"
+    /// dark_square = "#e07a5f"
+    /// arrows = ["#2a9d8f", "#e63946", "#f4a261", "#264653"]
+    /// ```
+    pub fn parse_user_themes(text: &str) -> Result<Vec<Theme>, String> {
+        let file: ThemeFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        file.theme.into_iter().map(ThemeSpec::into_theme).collect()
+    }
 }
 
-pub fn dark_square() -> Color32 {
-    Color32::from_rgb(75, 115, 153)
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::blue()
+    }
 }
 
-pub fn selected_square() -> Color32 {
-    Color32::from_rgb(116, 198, 232)
+#[derive(Deserialize)]
+struct ThemeFile {
+    #[serde(default)]
+    theme: Vec<ThemeSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeSpec {
+    name: String,
+    dark_mode: Option<bool>,
+    light_square: Option<String>,
+    dark_square: Option<String>,
+    selected_square: Option<String>,
+    move_hint: Option<String>,
+    illegal_move: Option<String>,
+    last_move: Option<String>,
+    check: Option<String>,
+    threat: Option<String>,
+    arrows: Option<Vec<String>>,
+}
+
+impl ThemeSpec {
+    fn into_theme(self) -> Result<Theme, String> {
+        let base = Theme::default();
+        let color = |hex: Option<String>, default| match hex {
+            Some(hex) => parse_color(&hex)
+                .ok_or_else(|| format!("{} in theme {} isn't a color", hex, self.name)),
+            None => Ok(default),
+        };
+
+        let arrows = match self.arrows {
+            Some(arrows) if arrows.len() == 4 => {
+                let mut colors = base.arrows;
+                for (color_of, hex) in colors.iter_mut().zip(arrows) {
+                    *color_of = color(Some(hex), *color_of)?;
+                }
+                colors
+            }
+            Some(_) => return Err(format!("theme {} needs exactly four arrows", self.name)),
+            None => base.arrows,
+        };
+
+        Ok(Theme {
+            dark_mode: self.dark_mode.unwrap_or(base.dark_mode),
+            light_square: color(self.light_square, base.light_square)?,
+            dark_square: color(self.dark_square, base.dark_square)?,
+            selected_square: color(self.selected_square, base.selected_square)?,
+            move_hint: color(self.move_hint, base.move_hint)?,
+            illegal_move: color(self.illegal_move, base.illegal_move)?,
+            last_move: color(self.last_move, base.last_move)?,
+            check: color(self.check, base.check)?,
+            threat: color(self.threat, base.threat)?,
+            arrows,
+            name: self.name,
+        })
+    }
+}
+
+/// Parses `#rrggbb`, or `#rrggbbaa` for translucent colors.
+fn parse_color(hex: &str) -> Option<Color32> {
+    let digits = hex.strip_prefix('#')?;
+    if !(digits.len() == 6 || digits.len() == 8) || !digits.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+    let alpha = if digits.len() == 8 { channel(6)? } else { 255 };
+
+    Some(Color32::from_rgba_unmultiplied(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        alpha,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_user_themes() {
+        let themes = Theme::parse_user_themes(
+            r##"
+            [[theme]]
+            name = "Coral"
+            dark_mode = true
+            dark_square = "#e07a5f"
+            threat = "#ff000040"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(themes.len(), 1);
+        let coral = &themes[0];
+        assert_eq!(coral.name, "Coral");
+        assert!(coral.dark_mode);
+        assert_eq!(coral.dark_square, Color32::from_rgb(0xe0, 0x7a, 0x5f));
+        assert_eq!(
+            coral.threat,
+            Color32::from_rgba_unmultiplied(0xff, 0, 0, 0x40)
+        );
+        assert_eq!(coral.light_square, Theme::default().light_square);
+    }
+
+    #[test]
+    fn rejects_broken_themes() {
+        for text in [
+            "[[theme]]\nname = \"x\"\nlight_square = \"red\"",
+            "[[theme]]\nname = \"x\"\nlight_sqaure = \"#ffffff\"",
+            "[[theme]]\nname = \"x\"\narrows = [\"#ffffff\"]",
+            "[[theme]]\ndark_mode = true",
+        ] {
+            assert!(Theme::parse_user_themes(text).is_err(), "{}", text);
+        }
+    }
 }
@@ -0,0 +1,92 @@
+/// z-score for a 95% confidence interval.
+const Z_95: f64 = 1.959_964;
+
+/// An Elo difference along with the half-width of its 95% confidence interval.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EloEstimate {
+    pub difference: f64,
+    pub margin: f64,
+}
+
+/// The Elo difference implied by an expected score between 0 and 1.
+pub fn elo_difference(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Estimates the Elo difference from a match result, using the normal
+/// approximation of the per-game score distribution for the error margin.
+/// Returns `None` if no games were played.
+///
+/// Scores are kept half a game away from 0 and 1, so a shutout gives a big
+/// but finite difference instead of an infinite one.
+pub fn estimate(wins: u32, draws: u32, losses: u32) -> Option<EloEstimate> {
+    let games = (wins + draws + losses) as f64;
+    if games == 0.0 {
+        return None;
+    }
+
+    let (w, d, l) = (
+        wins as f64 / games,
+        draws as f64 / games,
+        losses as f64 / games,
+    );
+    let bound = 0.5 / games;
+    let clamp = |score: f64| score.clamp(bound, 1.0 - bound);
+    let score = clamp(w + d / 2.0);
+
+    let variance = w * (1.0 - score).powi(2) + d * (0.5 - score).powi(2) + l * score.powi(2);
+    let std_error = (variance / games).sqrt();
+
+    let low = elo_difference(clamp(score - Z_95 * std_error));
+    let high = elo_difference(clamp(score + Z_95 * std_error));
+
+    Some(EloEstimate {
+        difference: elo_difference(score),
+        margin: (high - low) / 2.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn even_scores_are_even() {
+        assert_eq!(elo_difference(0.5), 0.0);
+
+        let estimate = estimate(10, 10, 10).unwrap();
+        assert_eq!(estimate.difference, 0.0);
+        assert!(estimate.margin > 0.0);
+    }
+
+    #[test]
+    fn matches_known_differences() {
+        assert!((elo_difference(0.75) - 190.85).abs() < 0.01);
+        assert!((elo_difference(0.25) + 190.85).abs() < 0.01);
+    }
+
+    #[test]
+    fn margin_shrinks_with_more_games() {
+        let few = estimate(6, 2, 2).unwrap();
+        let many = estimate(600, 200, 200).unwrap();
+
+        assert!((few.difference - many.difference).abs() < 1e-9);
+        assert!(many.margin < few.margin);
+    }
+
+    #[test]
+    fn shutouts_have_finite_estimates() {
+        let win = estimate(20, 0, 0).unwrap();
+        assert!(win.difference.is_finite() && win.difference > 400.0);
+        assert!(win.margin.is_finite() && win.margin > 0.0);
+
+        let loss = estimate(0, 0, 20).unwrap();
+        assert!((loss.difference + win.difference).abs() < 1e-6);
+        assert!(loss.margin.is_finite());
+    }
+
+    #[test]
+    fn no_games_no_estimate() {
+        assert_eq!(estimate(0, 0, 0), None);
+    }
+}
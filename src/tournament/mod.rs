@@ -0,0 +1,322 @@
+use chess::{Board, Color};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::evaluation::{evaluate, Score};
+use crate::game_state::{fen_halfmove_clock, GameState, Outcome};
+use crate::player::{Decision, Player};
+
+mod elo;
+mod sprt;
+
+pub use elo::{elo_difference, estimate, EloEstimate};
+pub use sprt::{Pentanomial, Sprt, SprtDecision};
+
+/// A position to start games from, with how many plies it's been since the
+/// last capture or pawn move, which `Board` doesn't keep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Opening {
+    pub position: Board,
+    pub halfmove_clock: usize,
+}
+
+impl From<Board> for Opening {
+    fn from(position: Board) -> Self {
+        Opening {
+            position,
+            halfmove_clock: 0,
+        }
+    }
+}
+
+/// Rules for ending games early, so matches don't waste time on games whose
+/// result is already clear.
+#[derive(Clone, Copy, Debug)]
+pub struct Adjudication {
+    /// Games are drawn once this many plies have been played.
+    pub max_plies: usize,
+
+    /// A side is considered lost once the static evaluation is at least this
+    /// far against it...
+    pub resign_score: Score,
+
+    /// ...for this many plies in a row.
+    pub resign_plies: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication {
+            max_plies: 400,
+            resign_score: 1_000,
+            resign_plies: 10,
+        }
+    }
+}
+
+/// A finished game, along with why it finished.
+pub struct GameRecord {
+    pub game_state: GameState,
+    pub outcome: Outcome,
+    pub reason: String,
+}
+
+/// Plays a game between two computer players from `start`, synchronously.
+pub fn play_game(
+    white: &dyn Player,
+    black: &dyn Player,
+    start: Opening,
+    adjudication: &Adjudication,
+) -> GameRecord {
+    let mut game_state = GameState::with_position(white.name(), black.name(), start.position);
+    game_state.start_halfmove_clock = start.halfmove_clock;
+    game_state.started = true;
+
+    // how many plies in a row the evaluation has favored `leader` decisively
+    let mut leader = None;
+    let mut decisive_plies = 0;
+
+    let (outcome, reason) = loop {
+        let board = game_state.current_position();
+        let to_move = board.side_to_move();
+
+        if let Some(result) = game_state.game.result() {
+            break (Outcome::from_game_result(result), format!("{:?}", result));
+        }
+        if let Some(reason) = game_state.automatic_draw {
+            break (Outcome::Draw, reason.description().into());
+        }
+        if game_state.moves().len() >= adjudication.max_plies {
+            break (Outcome::Draw, "adjudicated: move limit".into());
+        }
+
+        let score = evaluate(&board, Color::White, to_move);
+        let favored = if score >= adjudication.resign_score {
+            Some(Color::White)
+        } else if score <= -adjudication.resign_score {
+            Some(Color::Black)
+        } else {
+            None
+        };
+
+        decisive_plies = if favored.is_some() && favored == leader {
+            decisive_plies + 1
+        } else {
+            1
+        };
+        leader = favored;
+
+        if let Some(color) = leader {
+            if decisive_plies >= adjudication.resign_plies {
+                break (Outcome::Win(color), "adjudicated: resignation".into());
+            }
+        }
+
+        let (player, opponent) = match to_move {
+            Color::White => (white, black),
+            Color::Black => (black, white),
+        };
+
+        let clock = game_state.clock.clone();
+        match player.play(
+            &game_state.game,
+            &game_state.start_position,
+            &clock,
+            game_state.draw_offered(),
+            game_state.can_claim_draw(),
+        ) {
+            Decision::Move(m) | Decision::MoveAndOfferDraw(m) if !board.legal(m) => {
+                break (Outcome::Win(!to_move), format!("illegal move {}", m));
+            }
+            decision @ (Decision::Move(m) | Decision::MoveAndOfferDraw(m)) => {
+                game_state.make_move(m);
+                if let Decision::MoveAndOfferDraw(_) = decision {
+                    game_state.offer_draw(to_move);
+                }
+
+                opponent.opponent_moved(&game_state.game, m);
+                player.ponder(&game_state.game, &game_state.clock);
+            }
+            Decision::AcceptDraw if game_state.accept_draw(to_move) => {}
+            Decision::ClaimDraw if game_state.claim_draw(to_move) => {}
+            Decision::Resign => {
+                game_state.resign(to_move);
+            }
+            decision => {
+                break (Outcome::Win(!to_move), format!("invalid {:?}", decision));
+            }
+        }
+    };
+
+    white.game_over(outcome);
+    black.game_over(outcome);
+
+    GameRecord {
+        game_state,
+        outcome,
+        reason,
+    }
+}
+
+/// Wins, draws and losses from the first player's point of view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    /// Records a game in which the first player had `color`.
+    pub fn record(&mut self, outcome: Outcome, color: Color) {
+        match outcome {
+            Outcome::Win(winner) if winner == color => self.wins += 1,
+            Outcome::Win(_) => self.losses += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn elo(&self) -> Option<EloEstimate> {
+        estimate(self.wins, self.draws, self.losses)
+    }
+}
+
+/// Reads opening positions, one FEN or EPD per line. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_openings<P: AsRef<Path>>(path: P) -> io::Result<Vec<Opening>> {
+    let contents = fs::read_to_string(path)?;
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_position)
+        .collect()
+}
+
+fn parse_position(line: &str) -> io::Result<Opening> {
+    // EPD leaves off the move counters, and may have operations after them
+    let fields: Vec<&str> = line.split_whitespace().take(6).collect();
+    let fen = if fields.len() >= 6 && fields[4].parse::<u32>().is_ok() {
+        fields.join(" ")
+    } else {
+        format!("{} 0 1", fields[..fields.len().min(4)].join(" "))
+    };
+
+    let position = Board::from_str(&fen).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid position: {}", line),
+        )
+    })?;
+
+    Ok(Opening {
+        position,
+        halfmove_clock: fen_halfmove_clock(&fen),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine_config::{EngineConfig, Strategy};
+
+    #[test]
+    fn plays_until_adjudicated() {
+        let white = EngineConfig::new(Strategy::FirstLegalMove)
+            .build(None)
+            .unwrap();
+        let black = EngineConfig::new(Strategy::FirstLegalMove)
+            .build(None)
+            .unwrap();
+        let adjudication = Adjudication {
+            max_plies: 6,
+            ..Default::default()
+        };
+
+        let record = play_game(&*white, &*black, Board::default().into(), &adjudication);
+
+        assert_eq!(record.outcome, Outcome::Draw);
+        assert_eq!(record.game_state.moves().len(), 6);
+    }
+
+    #[test]
+    fn finds_checkmate() {
+        let white = EngineConfig::new(Strategy::AlphaBeta).build(None).unwrap();
+        let black = EngineConfig::new(Strategy::FirstLegalMove)
+            .build(None)
+            .unwrap();
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+
+        let record = play_game(&*white, &*black, board.into(), &Adjudication::default());
+
+        assert_eq!(record.outcome, Outcome::Win(Color::White));
+        assert_eq!(record.game_state.moves().len(), 1);
+    }
+
+    #[test]
+    fn adjudicates_lopsided_games() {
+        let white = EngineConfig::new(Strategy::FirstLegalMove)
+            .build(None)
+            .unwrap();
+        let black = EngineConfig::new(Strategy::FirstLegalMove)
+            .build(None)
+            .unwrap();
+        let board = Board::from_str("qqqqk3/8/8/8/8/8/8/7K w - - 0 1").unwrap();
+        let adjudication = Adjudication {
+            resign_plies: 2,
+            ..Default::default()
+        };
+
+        let record = play_game(&*white, &*black, board.into(), &adjudication);
+
+        assert_eq!(record.outcome, Outcome::Win(Color::Black));
+        assert_eq!(record.reason, "adjudicated: resignation");
+    }
+
+    #[test]
+    fn keeps_score() {
+        let mut score = MatchScore::default();
+        score.record(Outcome::Win(Color::White), Color::White);
+        score.record(Outcome::Win(Color::White), Color::Black);
+        score.record(Outcome::Draw, Color::Black);
+
+        assert_eq!(
+            score,
+            MatchScore {
+                wins: 1,
+                draws: 1,
+                losses: 1
+            }
+        );
+        assert_eq!(score.games(), 3);
+        assert_eq!(score.elo().unwrap().difference, 0.0);
+    }
+
+    #[test]
+    fn parses_fen_and_epd() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert_eq!(parse_position(start).unwrap(), Board::default().into());
+        assert_eq!(
+            parse_position(&format!("{} 0 1", start)).unwrap(),
+            Board::default().into()
+        );
+        assert_eq!(
+            parse_position(&format!("{} id \"start\";", start)).unwrap(),
+            Board::default().into()
+        );
+        assert_eq!(
+            parse_position(&format!("{} 12 30", start))
+                .unwrap()
+                .halfmove_clock,
+            12
+        );
+        assert!(parse_position("not a position").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use chess::Color;
+
+use crate::game_state::Outcome;
+
+/// A sequential probability ratio test between H0, that a candidate is
+/// `elo0` stronger than its baseline, and H1, that it is `elo1` stronger.
+/// `alpha` and `beta` are the false positive and false negative rates.
+///
+/// Games are played in pairs from the same opening with colors reversed, and
+/// each pair is scored as a whole, which cancels out most of the bias from
+/// unbalanced openings. This is the pentanomial model used by
+/// [fishtest](https://github.com/official-stockfish/fishtest).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtDecision {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+impl Sprt {
+    /// H0 is accepted once the log-likelihood ratio drops below this.
+    pub fn lower_bound(&self) -> f64 {
+        (self.beta / (1.0 - self.alpha)).ln()
+    }
+
+    /// H1 is accepted once the log-likelihood ratio rises above this.
+    pub fn upper_bound(&self) -> f64 {
+        ((1.0 - self.beta) / self.alpha).ln()
+    }
+
+    pub fn llr(&self, pairs: &Pentanomial) -> f64 {
+        pairs.llr(self.elo0, self.elo1)
+    }
+
+    pub fn decide(&self, pairs: &Pentanomial) -> SprtDecision {
+        let llr = self.llr(pairs);
+
+        if llr <= self.lower_bound() {
+            SprtDecision::AcceptH0
+        } else if llr >= self.upper_bound() {
+            SprtDecision::AcceptH1
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+/// How much of a pair an empty bucket of the pentanomial counts for.
+const PRIOR: f64 = 1e-3;
+
+/// Counts of game pairs by the candidate's total score over the pair:
+/// 0, ½, 1, 1½ and 2 points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pentanomial {
+    pub counts: [u32; 5],
+}
+
+impl Pentanomial {
+    /// Records a pair of games, given the color the candidate had in each.
+    pub fn record(&mut self, first: (Outcome, Color), second: (Outcome, Color)) {
+        let half_points = |(outcome, color): (Outcome, Color)| match outcome {
+            Outcome::Win(winner) if winner == color => 2,
+            Outcome::Win(_) => 0,
+            Outcome::Draw => 1,
+        };
+
+        self.counts[half_points(first) + half_points(second)] += 1;
+    }
+
+    pub fn pairs(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    /// Mean and variance of the per-pair score, scaled to between 0 and 1.
+    /// Like fishtest, empty buckets count as a sliver of a pair, so a run of
+    /// identical results still has some spread and can settle the test.
+    fn moments(&self) -> (f64, f64) {
+        let counts = self.counts.map(|count| match count {
+            0 => PRIOR,
+            count => count as f64,
+        });
+        let pairs: f64 = counts.iter().sum();
+        let frequency = |i: usize| counts[i] / pairs;
+        let score = |i: usize| i as f64 / 4.0;
+
+        let mean: f64 = (0..5).map(|i| frequency(i) * score(i)).sum();
+        let variance: f64 = (0..5)
+            .map(|i| frequency(i) * (score(i) - mean).powi(2))
+            .sum();
+
+        (mean, variance)
+    }
+
+    /// The generalized log-likelihood ratio of H1 (`elo1`) over H0 (`elo0`),
+    /// using the normal approximation from Michel Van den Bergh's [notes on
+    /// the GSPRT](https://www.cantate.be/Fishtest/GSPRT_approximation.pdf).
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        if self.pairs() == 0 {
+            return 0.0;
+        }
+
+        let (mean, variance) = self.moments();
+        let s0 = expected_score(elo0);
+        let s1 = expected_score(elo1);
+
+        self.pairs() as f64 * (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance)
+    }
+}
+
+/// The expected score for a player who is `elo` points stronger.
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_pairs() {
+        let mut pairs = Pentanomial::default();
+
+        pairs.record(
+            (Outcome::Win(Color::White), Color::White),
+            (Outcome::Draw, Color::Black),
+        );
+        pairs.record(
+            (Outcome::Win(Color::Black), Color::White),
+            (Outcome::Win(Color::White), Color::Black),
+        );
+
+        assert_eq!(pairs.counts, [1, 0, 0, 1, 0]);
+        assert_eq!(pairs.pairs(), 2);
+    }
+
+    #[test]
+    fn bounds_match_error_rates() {
+        let sprt = Sprt::default();
+
+        assert!((sprt.lower_bound() + 2.944).abs() < 0.001);
+        assert!((sprt.upper_bound() - 2.944).abs() < 0.001);
+    }
+
+    #[test]
+    fn accepts_h1_for_a_clearly_better_candidate() {
+        let sprt = Sprt::default();
+        let pairs = Pentanomial {
+            counts: [15, 60, 180, 120, 75],
+        };
+
+        assert!(sprt.llr(&pairs) > 0.0);
+        assert_eq!(sprt.decide(&pairs), SprtDecision::AcceptH1);
+    }
+
+    #[test]
+    fn accepts_h0_for_a_clearly_worse_candidate() {
+        let sprt = Sprt::default();
+        let pairs = Pentanomial {
+            counts: [75, 120, 180, 60, 15],
+        };
+
+        assert!(sprt.llr(&pairs) < 0.0);
+        assert_eq!(sprt.decide(&pairs), SprtDecision::AcceptH0);
+    }
+
+    #[test]
+    fn accepts_h1_when_the_candidate_wins_every_pair() {
+        let sprt = Sprt::default();
+        let pairs = Pentanomial {
+            counts: [0, 0, 0, 0, 10],
+        };
+
+        assert!(sprt.llr(&pairs).is_finite());
+        assert_eq!(sprt.decide(&pairs), SprtDecision::AcceptH1);
+    }
+
+    #[test]
+    fn continues_without_evidence() {
+        let sprt = Sprt::default();
+
+        assert_eq!(sprt.decide(&Pentanomial::default()), SprtDecision::Continue);
+        assert_eq!(
+            sprt.decide(&Pentanomial {
+                counts: [0, 0, 7, 0, 0]
+            }),
+            SprtDecision::Continue
+        );
+    }
+}
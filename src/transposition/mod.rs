@@ -33,7 +33,16 @@ pub struct TranspositionTable {
 
 impl TranspositionTable {
     pub fn new() -> Self {
-        let size = 1_000_000;
+        Self::with_entries(1_000_000)
+    }
+
+    /// A table taking up about `megabytes` of memory.
+    pub fn with_size_mb(megabytes: usize) -> Self {
+        let entry_size = std::mem::size_of::<Option<TableEntry>>();
+        Self::with_entries((megabytes * 1024 * 1024 / entry_size).max(1))
+    }
+
+    fn with_entries(size: usize) -> Self {
         let transpositions = vec![None; size];
         TranspositionTable {
             transpositions,
@@ -49,7 +58,13 @@ impl TranspositionTable {
         self.transpositions.is_empty()
     }
 
-    pub fn store(&mut self, hash: Hash, depth: u8, eval: Evaluation) {
+    pub fn store(
+        &mut self,
+        hash: Hash,
+        depth: u8,
+        eval: Evaluation,
+        following_move: Option<ChessMove>,
+    ) {
         let position = hash as usize % self.size;
 
         let entry = self.transpositions.get_mut(position).unwrap();
@@ -58,7 +73,7 @@ impl TranspositionTable {
                 hash,
                 depth,
                 eval,
-                following_move: None,
+                following_move,
             });
         }
     }
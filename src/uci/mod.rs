@@ -0,0 +1,727 @@
+use chess::{Action, Board, ChessMove, Color, Game};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, TimeControl};
+use crate::game_state::Outcome;
+use crate::notation;
+use crate::player::{accepts_draw, Decision, Player};
+
+/// How long an engine gets to answer `uci` and `isready`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to search for when no limits have been configured.
+const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(1);
+
+/// How much longer than its time an engine gets to answer before it's taken
+/// to have hung.
+const MOVE_GRACE: Duration = Duration::from_secs(2);
+
+/// Limits sent along with each `go` command when there's no clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UciLimits {
+    pub depth: Option<u8>,
+    pub move_time: Option<Duration>,
+}
+
+impl UciLimits {
+    /// Builds the `go` command for `side`. Games with a clock pass it on to
+    /// the engine so it can budget its own time, instead of these limits.
+    fn go_command(&self, clock: &Clock, side: Color) -> String {
+        let mut go = String::from("go");
+
+        match clock.time_control() {
+            TimeControl::Unlimited => {}
+            TimeControl::MoveTime(_) => {
+                let t = clock.budget(side).unwrap_or_default();
+                return format!("go movetime {}", t.as_millis());
+            }
+            TimeControl::Level { increment, .. } => {
+                let ms = |color| clock.remaining(color).unwrap_or_default().as_millis();
+                go.push_str(&format!(
+                    " wtime {} btime {} winc {} binc {}",
+                    ms(Color::White),
+                    ms(Color::Black),
+                    increment.as_millis(),
+                    increment.as_millis()
+                ));
+                if let Some(moves) = clock.moves_to_go(side) {
+                    go.push_str(&format!(" movestogo {}", moves));
+                }
+                return go;
+            }
+        }
+
+        if let Some(depth) = self.depth {
+            go.push_str(&format!(" depth {}", depth));
+        }
+
+        match self.move_time {
+            Some(t) => go.push_str(&format!(" movetime {}", t.as_millis())),
+            None if self.depth.is_none() => {
+                go.push_str(&format!(" movetime {}", DEFAULT_MOVE_TIME.as_millis()))
+            }
+            None => {}
+        }
+
+        go
+    }
+
+    /// How long the engine can take over a move for `side` before it's taken
+    /// to have hung, or `None` if it's only limited by depth.
+    fn move_timeout(&self, clock: &Clock, side: Color) -> Option<Duration> {
+        let allowed = match clock.time_control() {
+            TimeControl::Unlimited => match self.move_time {
+                Some(t) => Some(t),
+                None if self.depth.is_none() => Some(DEFAULT_MOVE_TIME),
+                None => None,
+            },
+            TimeControl::MoveTime(_) => Some(clock.budget(side).unwrap_or_default()),
+            TimeControl::Level { .. } => Some(clock.remaining(side).unwrap_or_default()),
+        };
+
+        allowed.map(|t| t + MOVE_GRACE)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UciScore {
+    Centipawns(i64),
+    Mate(i64),
+}
+
+impl fmt::Display for UciScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciScore::Centipawns(cp) => write!(f, "{:+.2}", *cp as f64 / 100.0),
+            UciScore::Mate(moves) => write!(f, "#{}", moves),
+        }
+    }
+}
+
+/// The interesting parts of an `info` line sent while the engine searches.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UciInfo {
+    pub depth: Option<u32>,
+    pub score: Option<UciScore>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub pv: Vec<String>,
+}
+
+impl UciInfo {
+    /// Parses an `info` line, returning `None` if it has nothing worth
+    /// showing (like `info string` or current move updates).
+    pub fn parse(line: &str) -> Option<UciInfo> {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("info") {
+            return None;
+        }
+
+        let mut info = UciInfo::default();
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => info.depth = tokens.next().and_then(|t| t.parse().ok()),
+                "nodes" => info.nodes = tokens.next().and_then(|t| t.parse().ok()),
+                "nps" => info.nps = tokens.next().and_then(|t| t.parse().ok()),
+                "score" => {
+                    let kind = tokens.next();
+                    let value = tokens.next().and_then(|t| t.parse().ok());
+                    info.score = match (kind, value) {
+                        (Some("cp"), Some(cp)) => Some(UciScore::Centipawns(cp)),
+                        (Some("mate"), Some(moves)) => Some(UciScore::Mate(moves)),
+                        _ => None,
+                    };
+                }
+                "pv" => {
+                    info.pv = tokens.by_ref().map(String::from).collect();
+                }
+                "string" => return None,
+                _ => {}
+            }
+        }
+
+        if info.score.is_none() && info.pv.is_empty() {
+            None
+        } else {
+            Some(info)
+        }
+    }
+}
+
+impl fmt::Display for UciInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+
+        if let Some(depth) = self.depth {
+            parts.push(format!("depth {}", depth));
+        }
+        if let Some(score) = self.score {
+            parts.push(format!("score {}", score));
+        }
+        if let Some(nodes) = self.nodes {
+            parts.push(format!("nodes {}", nodes));
+        }
+        if let Some(nps) = self.nps {
+            parts.push(format!("nps {}", nps));
+        }
+        if !self.pv.is_empty() {
+            parts.push(format!("pv {}", self.pv.join(" ")));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// An external engine speaking the [Universal Chess
+/// Interface](https://backscattering.de/chess/uci/), running as a child
+/// process. The engine is asked to quit when this is dropped.
+pub struct UciEngine {
+    name: String,
+    child: Mutex<Child>,
+    stdin: Mutex<ChildStdin>,
+
+    /// Lines read from the engine's stdout by a background thread, so that we
+    /// can stop waiting on an engine which has hung. Whoever holds the lock
+    /// is waiting for a reply.
+    lines: Mutex<Receiver<String>>,
+
+    limits: Mutex<UciLimits>,
+    info: Mutex<Option<UciInfo>>,
+
+    /// Whether to think on the opponent's time, about the reply the engine
+    /// expects (`ponder_move`). While it does, `pondering` holds the position
+    /// it is thinking about.
+    ponder: bool,
+    ponder_move: Mutex<Option<ChessMove>>,
+    pondering: Mutex<Option<Board>>,
+
+    /// Where the game being played started, as of the last `best_move`.
+    start_position: Mutex<Board>,
+}
+
+fn receive(lines: &Receiver<String>, timeout: Option<Duration>) -> io::Result<String> {
+    let closed = || io::Error::new(io::ErrorKind::UnexpectedEof, "engine closed its output");
+
+    match timeout {
+        Some(timeout) => lines.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => {
+                io::Error::new(io::ErrorKind::TimedOut, "engine did not respond in time")
+            }
+            RecvTimeoutError::Disconnected => closed(),
+        }),
+        None => lines.recv().map_err(|_| closed()),
+    }
+}
+
+impl UciEngine {
+    /// Starts the engine and waits for it to finish the UCI handshake.
+    pub fn spawn<S: AsRef<str>>(command: &str, args: &[S]) -> io::Result<UciEngine> {
+        let mut child = Command::new(command)
+            .args(args.iter().map(|a| a.as_ref()))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            name: command.to_string(),
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            lines: Mutex::new(lines),
+            limits: Mutex::new(UciLimits::default()),
+            info: Mutex::new(None),
+            ponder: false,
+            ponder_move: Mutex::new(None),
+            pondering: Mutex::new(None),
+            start_position: Mutex::new(Board::default()),
+        };
+
+        engine.send("uci")?;
+        loop {
+            let line = receive(engine.lines.get_mut().unwrap(), Some(HANDSHAKE_TIMEOUT))?;
+            if let Some(id) = line.strip_prefix("id name ") {
+                engine.name = id.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.wait_until_ready()?;
+
+        Ok(engine)
+    }
+
+    fn send(&self, command: &str) -> io::Result<()> {
+        let mut stdin = self.stdin.lock().unwrap();
+        writeln!(stdin, "{}", command)?;
+        stdin.flush()
+    }
+
+    fn wait_until_ready(&self) -> io::Result<()> {
+        let lines = self.lines.lock().unwrap();
+        self.send("isready")?;
+        while receive(&lines, Some(HANDSHAKE_TIMEOUT))?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// The name the engine reported with `id name`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_limits(&self, limits: UciLimits) {
+        *self.limits.lock().unwrap() = limits;
+    }
+
+    pub fn set_option(&self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.wait_until_ready()
+    }
+
+    /// Lets the engine think on the opponent's time.
+    pub fn enable_pondering(&mut self) -> io::Result<()> {
+        self.set_option("Ponder", "true")?;
+        self.ponder = true;
+        Ok(())
+    }
+
+    /// The most recent search information the engine sent.
+    pub fn latest_info(&self) -> Option<UciInfo> {
+        self.info.lock().unwrap().clone()
+    }
+
+    /// Asks the engine for its move in the current position of `game`,
+    /// which started from `start_position`. Returns `None` if the engine has
+    /// no move to give, and a `TimedOut` error if it takes well over its time.
+    pub fn best_move(
+        &self,
+        game: &Game,
+        start_position: &Board,
+        clock: &Clock,
+    ) -> io::Result<Option<ChessMove>> {
+        *self.start_position.lock().unwrap() = *start_position;
+        let board = game.current_position();
+        let timeout = self
+            .limits
+            .lock()
+            .unwrap()
+            .move_timeout(clock, board.side_to_move());
+        let lines = self.lines.lock().unwrap();
+
+        let pondered = self.pondering.lock().unwrap().take();
+        match pondered {
+            // the opponent played the move we were pondering on, so carry on
+            Some(position) if position == board => self.send("ponderhit")?,
+            Some(_) => {
+                self.send("stop")?;
+                self.read_best_move(&lines, &board, Some(MOVE_GRACE))?;
+                self.go(game, clock, false)?;
+            }
+            None => self.go(game, clock, false)?,
+        }
+
+        self.read_best_move(&lines, &board, timeout)
+    }
+
+    fn go(&self, game: &Game, clock: &Clock, ponder: bool) -> io::Result<()> {
+        let board = game.current_position();
+        let go = self
+            .limits
+            .lock()
+            .unwrap()
+            .go_command(clock, board.side_to_move());
+
+        let start_position = *self.start_position.lock().unwrap();
+        self.send(&position_command(&start_position, game))?;
+        if ponder {
+            self.send(&go.replacen("go", "go ponder", 1))
+        } else {
+            self.send(&go)
+        }
+    }
+
+    /// Reads the engine's output up to its `bestmove`, which is for `board`,
+    /// giving up once `timeout` has passed.
+    fn read_best_move(
+        &self,
+        lines: &Receiver<String>,
+        board: &Board,
+        timeout: Option<Duration>,
+    ) -> io::Result<Option<ChessMove>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let left = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let line = receive(lines, left)?;
+            let mut tokens = line.split_whitespace();
+
+            match tokens.next() {
+                Some("info") => {
+                    if let Some(info) = UciInfo::parse(&line) {
+                        *self.info.lock().unwrap() = Some(info);
+                    }
+                }
+                Some("bestmove") => {
+                    let best = tokens.next().and_then(|m| notation::parse_move(board, m));
+
+                    let expected = match (best, tokens.next(), tokens.next()) {
+                        (Some(best), Some("ponder"), Some(reply)) => {
+                            notation::parse_move(&board.make_move_new(best), reply)
+                        }
+                        _ => None,
+                    };
+                    *self.ponder_move.lock().unwrap() = expected;
+
+                    return Ok(best);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Player for UciEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play(
+        &self,
+        game: &Game,
+        start_position: &Board,
+        clock: &Clock,
+        draw_offered: bool,
+        can_claim_draw: bool,
+    ) -> Decision {
+        let best = match self.best_move(game, start_position, clock) {
+            Ok(best) => best,
+            Err(e) => {
+                eprintln!("{} failed to move: {}", self.name, e);
+
+                // a hung engine forfeits, and is stopped for good
+                if e.kind() == io::ErrorKind::TimedOut {
+                    self.child.lock().unwrap().kill().ok();
+                }
+                return Decision::Resign;
+            }
+        };
+
+        let score = self.latest_info().and_then(|info| info.score);
+        let takes_draw = match score {
+            Some(UciScore::Centipawns(cp)) => accepts_draw(cp),
+            Some(UciScore::Mate(moves)) => moves < 0,
+            None => false,
+        };
+
+        match best {
+            _ if draw_offered && takes_draw => Decision::AcceptDraw,
+            _ if can_claim_draw && takes_draw => Decision::ClaimDraw,
+            Some(m) => Decision::Move(m),
+            None => Decision::Resign,
+        }
+    }
+
+    fn stop(&self) {
+        self.send("stop").ok();
+    }
+
+    /// Starts pondering on the reply the engine expects, if it gave one.
+    fn ponder(&self, game: &Game, clock: &Clock) {
+        if !self.ponder {
+            return;
+        }
+
+        let expected = match self.ponder_move.lock().unwrap().take() {
+            Some(m) if game.current_position().legal(m) => m,
+            _ => return,
+        };
+
+        let mut game = game.clone();
+        game.make_move(expected);
+        if self.go(&game, clock, true).is_ok() {
+            *self.pondering.lock().unwrap() = Some(game.current_position());
+        }
+    }
+
+    fn game_over(&self, _outcome: Outcome) {
+        // any pondering search is wound up by the next `best_move`
+        if self.pondering.lock().unwrap().is_some() {
+            self.stop();
+        }
+    }
+
+    fn info(&self) -> Option<String> {
+        self.latest_info().map(|info| info.to_string())
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let quit = self.send("quit");
+        let child = self.child.get_mut().unwrap();
+
+        // engines which don't close their output on quit get killed
+        let closed = matches!(
+            receive(self.lines.get_mut().unwrap(), Some(Duration::from_millis(500))),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof
+        );
+        if quit.is_err() || !closed {
+            child.kill().ok();
+        }
+        child.wait().ok();
+    }
+}
+
+/// Describes the game as a `position` command: where it started and every
+/// move since, so the engine can see repetitions. If the moves don't lead
+/// from `start_position` to the current position, only that can be sent.
+fn position_command(start_position: &Board, game: &Game) -> String {
+    let moves: Vec<ChessMove> = game
+        .actions()
+        .iter()
+        .filter_map(|action| match action {
+            Action::MakeMove(m) => Some(*m),
+            _ => None,
+        })
+        .collect();
+
+    let mut board = *start_position;
+    for m in moves.iter() {
+        board = board.make_move_new(*m);
+    }
+
+    if board != game.current_position() {
+        return format!("position fen {}", game.current_position());
+    }
+
+    let mut command = if *start_position == Board::default() {
+        String::from("position startpos")
+    } else {
+        format!("position fen {}", start_position)
+    };
+    if !moves.is_empty() {
+        command.push_str(" moves");
+        for m in moves {
+            command.push_str(&format!(" {}", m));
+        }
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// A stand-in engine which plays e2e4 or e7e5, whatever the position.
+    #[cfg(unix)]
+    const SCRIPTED_ENGINE: &str = r#"
+        while read -r line; do
+            case "$line" in
+                uci) echo "id name Scripted"; echo "uciok" ;;
+                isready) echo "readyok" ;;
+                "position startpos") move=e2e4 ;;
+                position*) move=e7e5 ;;
+                go*)
+                    echo "info string thinking"
+                    echo "info depth 3 score cp 25 nodes 120 nps 1000 pv $move"
+                    echo "bestmove $move"
+                    ;;
+                quit) exit 0 ;;
+            esac
+        done
+    "#;
+
+    #[test]
+    #[cfg(unix)]
+    fn plays_through_the_engine() {
+        let engine = UciEngine::spawn("sh", &["-c", SCRIPTED_ENGINE]).unwrap();
+        assert_eq!(engine.name(), "Scripted");
+
+        let mut game = Game::new();
+        let clock = Clock::default();
+        let e4 = engine
+            .best_move(&game, &Board::default(), &clock)
+            .unwrap()
+            .unwrap();
+        assert_eq!(e4, ChessMove::from_str("e2e4").unwrap());
+
+        game.make_move(e4);
+        let e5 = engine
+            .best_move(&game, &Board::default(), &clock)
+            .unwrap()
+            .unwrap();
+        assert_eq!(e5, ChessMove::from_str("e7e5").unwrap());
+
+        let info = engine.latest_info().unwrap();
+        assert_eq!(info.depth, Some(3));
+        assert_eq!(info.score, Some(UciScore::Centipawns(25)));
+        assert_eq!(info.pv, vec!["e7e5"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn decides_as_a_player() {
+        let engine = UciEngine::spawn("sh", &["-c", SCRIPTED_ENGINE]).unwrap();
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+
+        let decision = engine.play(
+            &Game::new(),
+            &Board::default(),
+            &Clock::default(),
+            true,
+            false,
+        );
+        assert_eq!(decision, Decision::Move(e4));
+        assert_eq!(
+            engine.info().as_deref(),
+            Some("depth 3, score +0.25, nodes 120, nps 1000, pv e2e4")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn forfeits_engines_which_hang() {
+        // answers the handshake, then never moves
+        let hung = r#"
+            while read -r line; do
+                case "$line" in
+                    uci) echo "uciok" ;;
+                    isready) echo "readyok" ;;
+                esac
+            done
+        "#;
+        let engine = UciEngine::spawn("sh", &["-c", hung]).unwrap();
+        engine.set_limits(UciLimits {
+            depth: None,
+            move_time: Some(Duration::from_millis(50)),
+        });
+
+        let decision = engine.play(
+            &Game::new(),
+            &Board::default(),
+            &Clock::default(),
+            false,
+            false,
+        );
+        assert_eq!(decision, Decision::Resign);
+
+        let start = Instant::now();
+        while engine.child.lock().unwrap().try_wait().unwrap().is_none() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "engine not killed"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn fails_to_spawn_missing_engines() {
+        let no_args: &[&str] = &[];
+        assert!(UciEngine::spawn("/nonexistent/uci-engine", no_args).is_err());
+    }
+
+    #[test]
+    fn parses_info_lines() {
+        let info = UciInfo::parse(
+            "info depth 12 seldepth 15 multipv 1 score mate -3 nodes 5000 nps 250000 time 20 pv d2d4 g8f6",
+        )
+        .unwrap();
+
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.score, Some(UciScore::Mate(-3)));
+        assert_eq!(info.nodes, Some(5000));
+        assert_eq!(info.nps, Some(250000));
+        assert_eq!(info.pv, vec!["d2d4", "g8f6"]);
+        assert_eq!(
+            info.to_string(),
+            "depth 12, score #-3, nodes 5000, nps 250000, pv d2d4 g8f6"
+        );
+
+        assert_eq!(UciInfo::parse("info string hello there"), None);
+        assert_eq!(UciInfo::parse("info currmove e2e4 currmovenumber 1"), None);
+        assert_eq!(UciInfo::parse("bestmove e2e4"), None);
+    }
+
+    #[test]
+    fn describes_positions() {
+        let start = Board::default();
+        let mut game = Game::new();
+        assert_eq!(position_command(&start, &game), "position startpos");
+
+        game.make_move(ChessMove::from_str("e2e4").unwrap());
+        assert_eq!(
+            position_command(&start, &game),
+            "position startpos moves e2e4"
+        );
+
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+        let start = Board::from_str(fen).unwrap();
+        let mut game = Game::new_with_board(start);
+        assert_eq!(
+            position_command(&start, &game),
+            format!("position fen {}", fen)
+        );
+
+        game.make_move(ChessMove::from_str("e1d1").unwrap());
+        assert_eq!(
+            position_command(&start, &game),
+            format!("position fen {} moves e1d1", fen)
+        );
+        assert_eq!(
+            position_command(&Board::default(), &game),
+            format!("position fen {}", game.current_position())
+        );
+    }
+
+    #[test]
+    fn builds_go_commands() {
+        let go = |limits: UciLimits| limits.go_command(&Clock::default(), Color::White);
+        assert_eq!(go(UciLimits::default()), "go movetime 1000");
+
+        let limits = UciLimits {
+            depth: Some(8),
+            move_time: None,
+        };
+        assert_eq!(go(limits), "go depth 8");
+
+        let limits = UciLimits {
+            depth: None,
+            move_time: Some(Duration::from_millis(250)),
+        };
+        assert_eq!(go(limits), "go movetime 250");
+
+        let clock = Clock::new(TimeControl::Level {
+            moves: 40,
+            base: Duration::from_secs(60),
+            increment: Duration::from_secs(1),
+        });
+        assert_eq!(
+            limits.go_command(&clock, Color::White),
+            "go wtime 60000 btime 60000 winc 1000 binc 1000 movestogo 40"
+        );
+    }
+}
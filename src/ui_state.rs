@@ -1,6 +1,48 @@
+use crate::setup::Setup;
+use crate::theme::Theme;
+use crate::widget::PieceSet;
+use chess::{Color, File, Piece, Rank, Square};
+use std::time::Instant;
+
+/// A square as `(rank, file)` indices, with `(0, 0)` being a1.
 pub type Coords = (usize, usize);
 
 #[derive(Clone, Default)]
 pub struct UiState {
     pub selected_square: Option<Coords>,
+
+    /// The ply being looked at in the move list, counting the starting
+    /// position as 0, or `None` when following the game.
+    pub viewed_ply: Option<usize>,
+
+    /// Where an illegal move was last dropped, and when, so the board can
+    /// show it was refused.
+    pub illegal_drop: Option<(Coords, Instant)>,
+
+    /// Whether to shade the squares the opponent attacks.
+    pub show_threats: bool,
+
+    /// Whether the board is drawn from Black's side.
+    pub flipped: bool,
+
+    pub piece_set: PieceSet,
+
+    pub theme: Theme,
+
+    /// Whether the analysis panel is searching the position on the board.
+    pub analysing: bool,
+
+    /// The position being set up, while the board is in setup mode.
+    pub setup: Option<Setup>,
+
+    /// The piece clicking in setup mode puts down, or `None` to remove.
+    pub setup_piece: Option<(Piece, Color)>,
+}
+
+pub fn square_at(coords: Coords) -> Square {
+    Square::make_square(Rank::from_index(coords.0), File::from_index(coords.1))
+}
+
+pub fn coords_of(square: Square) -> Coords {
+    (square.get_rank().to_index(), square.get_file().to_index())
 }
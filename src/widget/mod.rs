@@ -1,64 +1,252 @@
+use crate::annotation::{Annotations, Brush};
 use crate::app_state::AppState;
-use crate::theme;
-use chess::{Board, Color, File, Piece, Rank};
+use crate::describe;
+use crate::game_state::attacked_squares;
+use crate::setup::Setup;
+use crate::theme::Theme;
+use crate::ui_state::{coords_of, square_at, Coords};
+use chess::{BitBoard, Board, ChessMove, Color, File, Piece, Rank};
 use eframe::egui;
 use egui::Widget;
+use std::time::Duration;
+
+mod pieces;
+pub use pieces::PieceSet;
+
+/// Squares don't shrink below this, however little room there is.
+const MIN_SQUARE_SIZE: f32 = 32.0;
+
+/// How long a square flashes after an illegal move is dropped on it.
+const ILLEGAL_MOVE_FLASH: Duration = Duration::from_millis(600);
+
+/// What the pointer press currently going on started on.
+#[derive(Clone, Copy, Default)]
+struct Press {
+    /// The press picked up a piece, which follows the pointer until it's
+    /// dropped.
+    on_piece: bool,
+
+    /// The press was on the piece which was already selected, so a click
+    /// puts it back down.
+    on_selected: bool,
+}
 
 pub struct ChessBoard {
     board: Board,
-    selected_square: Option<(usize, usize)>,
+    selected_square: Option<Coords>,
     state: AppState,
-    // TODO: last move
+    last_move: Option<ChessMove>,
+    show_threats: bool,
+    flipped: bool,
+    piece_set: PieceSet,
+    theme: Theme,
+    setup: Option<Setup>,
+    annotations: Annotations,
+    best_move: Option<ChessMove>,
+
+    /// The arrow being drawn with the right button, from where it started to
+    /// the square under the pointer.
+    drawing: Option<(Coords, Coords, Brush)>,
 }
 
 impl ChessBoard {
-    pub fn new(board: Board, selected_square: Option<(usize, usize)>, state: AppState) -> Self {
+    pub fn new(board: Board, selected_square: Option<Coords>, state: AppState) -> Self {
         Self {
             board,
             selected_square,
             state,
+            last_move: None,
+            show_threats: false,
+            flipped: false,
+            piece_set: PieceSet::default(),
+            theme: Theme::default(),
+            setup: None,
+            annotations: Annotations::default(),
+            best_move: None,
+            drawing: None,
         }
     }
+
+    /// Shows a position being set up instead of `board`, whose pieces can be
+    /// put down, dragged around and taken off freely.
+    pub fn setup(mut self, setup: Option<Setup>) -> Self {
+        self.setup = setup;
+        self
+    }
+
+    pub fn pieces(mut self, piece_set: PieceSet) -> Self {
+        self.piece_set = piece_set;
+        self
+    }
+
+    /// Arrows and marked squares drawn on the position.
+    pub fn annotations(mut self, annotations: Annotations) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// The move the engine likes best, drawn as a blue arrow along with the
+    /// annotations if it's legal in the position shown.
+    pub fn best_move(mut self, best_move: Option<ChessMove>) -> Self {
+        self.best_move = best_move;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Draws the board from Black's side, with rank 1 at the top.
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Highlights the squares the move which led to this position went from
+    /// and to.
+    pub fn last_move(mut self, last_move: Option<ChessMove>) -> Self {
+        self.last_move = last_move;
+        self
+    }
+
+    /// Shades every square the side not to move attacks.
+    pub fn threats(mut self, show_threats: bool) -> Self {
+        self.show_threats = show_threats;
+        self
+    }
 }
 
 impl Widget for ChessBoard {
     fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
-        let square_min_size = 100.0;
-
         ui.spacing_mut().item_spacing = egui::vec2(0.0, 0.0);
 
+        // as big as fits, keeping whole pixels per square so the edges stay
+        // crisp
+        let available = ui.available_size();
+        let square_size = (available.x.min(available.y) / 8.0)
+            .floor()
+            .max(MIN_SQUARE_SIZE);
+
         let (rect, response) = ui.allocate_exact_size(
-            egui::vec2(8.0 * square_min_size, 8.0 * square_min_size),
+            egui::vec2(8.0 * square_size, 8.0 * square_size),
             egui::Sense::click_and_drag(),
         );
 
-        let x_range = rect.x_range();
-        let y_range = rect.y_range();
+        // none of the game's highlights mean anything while setting up
+        let playing = self.setup.is_none();
+        let mut focused = None;
+
+        let dragged_from = if playing {
+            if !self.handle_drawing_pointer(ui, rect, &response) {
+                self.handle_pointer(ui, rect, &response);
+            }
+            focused = self.handle_square_focus(ui, rect, &response);
+            self.selected_square = self.state.ui_selected_square();
+
+            let press = ui.data().get_temp::<Press>(response.id).unwrap_or_default();
+            self.selected_square
+                .filter(|_| press.on_piece && response.dragged())
+        } else {
+            let dragged_from = self.handle_setup_pointer(ui, rect, &response);
+            self.setup = self.state.setup();
+            self.selected_square = dragged_from;
+            dragged_from
+        };
+
+        let hints = if playing {
+            self.state.ui_legal_destinations()
+        } else {
+            vec![]
+        };
+        let illegal_drop = match self.state.ui_illegal_drop() {
+            Some((coords, elapsed)) if playing && elapsed < ILLEGAL_MOVE_FLASH => {
+                ui.ctx().request_repaint_after(ILLEGAL_MOVE_FLASH - elapsed);
+                Some(coords)
+            }
+            _ => None,
+        };
+
+        let last_move = self
+            .last_move
+            .filter(|_| playing)
+            .map(|m| [coords_of(m.get_source()), coords_of(m.get_dest())]);
+        let threats = if self.show_threats && playing {
+            attacked_squares(&self.board, !self.board.side_to_move())
+        } else {
+            chess::EMPTY
+        };
+        let king_in_check = if playing && *self.board.checkers() != chess::EMPTY {
+            Some(coords_of(self.board.king_square(self.board.side_to_move())))
+        } else {
+            None
+        };
 
         for file_idx in 0..8usize {
             for rank_idx in 0..8usize {
-                let selected = Some((rank_idx, file_idx)) == self.selected_square;
-                let x_offset = (file_idx as f32) * square_min_size;
-                let y_offset = ((7 - rank_idx) as f32) * square_min_size;
-                let xr = std::ops::RangeInclusive::new(
-                    *x_range.start() + x_offset,
-                    x_range.start() + x_offset + square_min_size,
-                );
-                let yr = std::ops::RangeInclusive::new(
-                    *y_range.start() + y_offset,
-                    y_range.start() + y_offset + square_min_size,
-                );
-
-                let square_rect = egui::Rect::from_x_y_ranges(xr, yr);
+                let coords = (rank_idx, file_idx);
+                let square_rect = square_rect(rect, coords, self.flipped);
                 let painter = ui.painter_at(square_rect);
 
+                let mut tints = Vec::new();
+                if last_move.is_some_and(|squares| squares.contains(&coords)) {
+                    tints.push(self.theme.last_move);
+                }
+                if threats & BitBoard::from_square(square_at(coords)) != chess::EMPTY {
+                    tints.push(self.theme.threat);
+                }
+
+                let highlight = if Some(coords) == illegal_drop {
+                    Some(self.theme.illegal_move)
+                } else if Some(coords) == self.selected_square {
+                    Some(self.theme.selected_square)
+                } else {
+                    None
+                };
+                let hint = hints
+                    .iter()
+                    .find(|(to, _)| *to == coords)
+                    .map(|&(_, capture)| capture);
+
                 self.paint_square(
                     ui,
-                    (rank_idx, file_idx),
+                    coords,
                     square_rect,
                     painter,
                     &response,
-                    selected,
+                    &tints,
+                    highlight,
+                    Some(coords) == king_in_check,
+                    hint,
+                    Some(coords) == dragged_from,
+                );
+            }
+        }
+
+        paint_coordinates(&ui.painter_at(rect), rect, self.flipped, &self.theme);
+        if playing {
+            self.paint_annotations(&ui.painter_at(rect), rect);
+        }
+        if let Some(coords) = focused {
+            ui.painter_at(rect).rect_stroke(
+                square_rect(rect, coords, self.flipped).shrink(1.0),
+                0.0,
+                ui.visuals().selection.stroke,
+            );
+        }
+
+        // the piece being dragged goes over everything else
+        if let (Some(from), Some(pos)) = (dragged_from, response.interact_pointer_pos()) {
+            if let Some((piece, color)) = self.piece_at(square_at(from)) {
+                let square =
+                    egui::Rect::from_center_size(pos, egui::Vec2::splat(rect.width() / 8.0));
+                paint_piece(
+                    ui,
+                    &ui.painter_at(rect),
+                    square,
+                    self.piece_set,
+                    piece,
+                    color,
                 );
             }
         }
@@ -68,6 +256,7 @@ impl Widget for ChessBoard {
 }
 
 impl ChessBoard {
+    #[allow(clippy::too_many_arguments)]
     fn paint_square(
         &mut self,
         ui: &mut egui::Ui,
@@ -75,46 +264,39 @@ impl ChessBoard {
         rect: egui::Rect,
         painter: egui::Painter,
         response: &egui::Response,
-        selected: bool,
+        tints: &[egui::Color32],
+        highlight: Option<egui::Color32>,
+        in_check: bool,
+        hint: Option<bool>,
+        dragged: bool,
     ) {
         let (rank_idx, file_idx) = coords;
         let board = self.board;
-        let rank = chess::Rank::from_index(rank_idx);
-        let file = chess::File::from_index(file_idx);
-        let square = chess::Square::make_square(rank, file);
-
-        let piece = board.piece_on(square);
-        let color = board.color_on(square);
+        let piece = self.piece_at(square_at(coords));
         let light = (rank_idx + file_idx) % 2 == 1;
 
-        let popup_id =
-            ui.make_persistent_id(format!("promotion-dialogue-{}-{}", rank_idx, file_idx));
+        let popup_id = promotion_popup_id(ui, coords);
 
         egui::popup::popup_below_widget(ui, popup_id, response, |ui| {
             if let Some((rank_from, file_from)) = self.selected_square {
                 if attempting_promotion(&board, (rank_from, file_from), (rank_idx, file_idx)) {
                     if ui.button("queen").clicked() {
-                        println!("queren");
                         self.state
                             .ui_attempt_move(Some((rank_idx, file_idx)), Some(Piece::Queen));
                         ui.memory().toggle_popup(popup_id);
                     } else if ui.button("rook").clicked() {
-                        println!("rook");
                         self.state
                             .ui_attempt_move(Some((rank_idx, file_idx)), Some(Piece::Rook));
                         ui.memory().toggle_popup(popup_id);
                     } else if ui.button("knight").clicked() {
-                        println!("knight");
                         self.state
                             .ui_attempt_move(Some((rank_idx, file_idx)), Some(Piece::Knight));
                         ui.memory().toggle_popup(popup_id);
                     } else if ui.button("bishop").clicked() {
-                        println!("bish");
                         self.state
                             .ui_attempt_move(Some((rank_idx, file_idx)), Some(Piece::Bishop));
                         ui.memory().toggle_popup(popup_id);
                     } else if ui.button("cancel").clicked() {
-                        println!("nope");
                         self.state.ui_select_square(None);
                         ui.memory().toggle_popup(popup_id);
                     }
@@ -122,79 +304,467 @@ impl ChessBoard {
             }
         });
 
-        if self.square_clicked(response, &rect) {
-            self.handle_click(ui, rank_idx, file_idx, &board, popup_id);
-        }
-
-        let bg_color = if selected {
-            theme::selected_square()
-        } else if light {
-            theme::light_square()
+        let bg_color = if light {
+            self.theme.light_square
         } else {
-            theme::dark_square()
+            self.theme.dark_square
         };
 
         painter.rect_filled(rect, egui::Rounding::none(), bg_color);
+        for &tint in tints {
+            painter.rect_filled(rect, egui::Rounding::none(), tint);
+        }
+        if let Some(highlight) = highlight {
+            painter.rect_filled(rect, egui::Rounding::none(), highlight);
+        }
 
-        if let (Some(piece), Some(color)) = (piece, color) {
-            let color_symbol = piece_symbol(piece, Color::Black);
-            let line_symbol = piece_symbol(piece, Color::White);
+        let size = rect.width();
 
-            let text_color = match color {
-                Color::White => egui::Color32::WHITE,
-                Color::Black => egui::Color32::BLACK,
-            };
+        // a glow which fades out towards the edges of the square
+        if in_check {
+            let check = self.theme.check;
+            for step in 0..6u8 {
+                let alpha = 40 + step * 20;
+                let radius = size * (0.5 - step as f32 * 0.06);
+                painter.circle_filled(
+                    rect.center(),
+                    radius,
+                    egui::Color32::from_rgba_unmultiplied(check.r(), check.g(), check.b(), alpha),
+                );
+            }
+        }
 
-            painter.text(
-                rect.center(),
-                egui::Align2::CENTER_CENTER,
-                color_symbol,
-                egui::FontId::proportional(60.0),
-                text_color,
-            );
-            painter.text(
+        // dots for quiet moves, and rings around pieces which can be taken
+        match hint {
+            Some(true) => painter.circle_stroke(
                 rect.center(),
-                egui::Align2::CENTER_CENTER,
-                line_symbol,
-                egui::FontId::proportional(60.0),
-                egui::Color32::BLACK,
-            );
+                size * 0.44,
+                egui::Stroke::new(size * 0.08, self.theme.move_hint),
+            ),
+            Some(false) => painter.circle_filled(rect.center(), size * 0.15, self.theme.move_hint),
+            None => {}
+        }
+
+        if let Some((piece, color)) = piece {
+            if !dragged {
+                paint_piece(ui, &painter, rect, self.piece_set, piece, color);
+            }
         }
     }
 
-    fn square_clicked(&self, response: &egui::Response, rect: &egui::Rect) -> bool {
-        response.clicked()
-            && response
-                .interact_pointer_pos()
-                .map_or(false, |pos| rect.contains(pos))
+    fn piece_at(&self, square: chess::Square) -> Option<(Piece, Color)> {
+        match &self.setup {
+            Some(setup) => setup.piece_on(square),
+            None => self.board.piece_on(square).zip(self.board.color_on(square)),
+        }
     }
 
-    fn handle_click(
+    /// Setting up a position: clicks put down the piece picked from the
+    /// palette, right clicks take pieces off, and pieces can be dragged to
+    /// another square or off the board. Returns where the piece being dragged
+    /// came from.
+    fn handle_setup_pointer(
         &mut self,
         ui: &egui::Ui,
-        rank: usize,
-        file: usize,
-        board: &Board,
-        popup_id: egui::Id,
-    ) {
-        match self.selected_square {
-            None => {
-                self.state.ui_select_square(Some((rank, file)));
+        rect: egui::Rect,
+        response: &egui::Response,
+    ) -> Option<Coords> {
+        let coords = response
+            .interact_pointer_pos()
+            .and_then(|pos| coords_at(rect, pos, self.flipped));
+        let drag_id = response.id.with("setup-drag");
+
+        if response.drag_started() {
+            let from = coords.filter(|&coords| self.piece_at(square_at(coords)).is_some());
+            ui.data().insert_temp(drag_id, from);
+        }
+
+        let from = ui.data().get_temp::<Option<Coords>>(drag_id).flatten();
+
+        if response.secondary_clicked() {
+            if let Some(coords) = coords {
+                self.state.setup_remove(coords);
             }
-            Some((r2, f2)) if r2 != rank || f2 != file => {
-                if !attempting_promotion(board, (r2, f2), (rank, file)) {
-                    self.state.ui_attempt_move(Some((rank, file)), None);
-                } else {
-                    ui.memory().toggle_popup(popup_id);
+        } else if response.drag_released() {
+            ui.data().remove::<Option<Coords>>(drag_id);
+
+            match (from, coords) {
+                (_, Some(coords)) if response.clicked() => self.state.setup_click(coords),
+                (Some(from), to) => self.state.setup_move(from, to),
+                _ => {}
+            }
+            return None;
+        }
+
+        from.filter(|_| response.dragged())
+    }
+
+    /// Drawing on the board with the right button: clicks mark squares and
+    /// drags draw arrows, with the brush picked by the modifier keys held.
+    /// Pressing the left button rubs everything out. Returns whether the
+    /// press going on is for drawing, so it isn't taken as a move.
+    fn handle_drawing_pointer(
+        &mut self,
+        ui: &egui::Ui,
+        rect: egui::Rect,
+        response: &egui::Response,
+    ) -> bool {
+        let coords = response
+            .interact_pointer_pos()
+            .and_then(|pos| coords_at(rect, pos, self.flipped));
+        let start_id = response.id.with("drawing");
+
+        let (secondary, modifiers) = {
+            let input = ui.input();
+            (
+                input.pointer.button_down(egui::PointerButton::Secondary),
+                input.modifiers,
+            )
+        };
+        let brush = Brush::for_modifiers(modifiers.shift, modifiers.alt || modifiers.ctrl);
+
+        if response.drag_started() {
+            if !secondary {
+                self.state.clear_annotations();
+                self.annotations = Annotations::default();
+                return false;
+            }
+            ui.data().insert_temp(start_id, coords);
+        }
+
+        let start = match ui.data().get_temp::<Option<Coords>>(start_id) {
+            Some(start) => start,
+            None => return false,
+        };
+
+        if response.drag_released() {
+            ui.data().remove::<Option<Coords>>(start_id);
+            match (start, coords) {
+                (Some(from), Some(to)) if from == to => {
+                    self.state.annotate_square(square_at(from), brush);
+                }
+                (Some(from), Some(to)) => {
+                    self.state
+                        .annotate_arrow(square_at(from), square_at(to), brush);
                 }
+                _ => {}
             }
-            _ => {
+            self.annotations = self.state.displayed_annotations();
+        } else if let (Some(from), Some(to)) = (start, coords) {
+            self.drawing = Some((from, to, brush));
+        }
+
+        true
+    }
+
+    /// Marked squares, with arrows over them: the engine's best move, the
+    /// ones drawn, and the one being drawn.
+    fn paint_annotations(&self, painter: &egui::Painter, rect: egui::Rect) {
+        let size = rect.width() / 8.0;
+        let color = |brush: Brush| self.theme.arrows[brush.index()];
+
+        for &(square, brush) in &self.annotations.squares {
+            let center = square_rect(rect, coords_of(square), self.flipped).center();
+            painter.circle_stroke(
+                center,
+                size * 0.45,
+                egui::Stroke::new(size * 0.07, color(brush)),
+            );
+        }
+
+        let best_move = self.best_move.filter(|&m| self.board.legal(m)).map(|m| {
+            (
+                coords_of(m.get_source()),
+                coords_of(m.get_dest()),
+                Brush::Blue,
+            )
+        });
+        let arrows = self
+            .annotations
+            .arrows
+            .iter()
+            .map(|&(from, to, brush)| (coords_of(from), coords_of(to), brush));
+
+        for (from, to, brush) in best_move.into_iter().chain(arrows).chain(self.drawing) {
+            if from != to {
+                paint_arrow(painter, rect, self.flipped, (from, to), color(brush));
+            }
+        }
+    }
+
+    /// Picks up pieces and moves them, either by dragging them to where they
+    /// should go or by clicking on the piece and then on its destination.
+    fn handle_pointer(&mut self, ui: &egui::Ui, rect: egui::Rect, response: &egui::Response) {
+        let coords = response
+            .interact_pointer_pos()
+            .and_then(|pos| coords_at(rect, pos, self.flipped));
+
+        if response.drag_started() {
+            let mut press = Press::default();
+
+            if let Some(coords) = coords {
+                if self.selected_square == Some(coords) {
+                    press.on_piece = true;
+                    press.on_selected = true;
+                } else if self.state.ui_can_select(coords) {
+                    self.state.ui_select_square(Some(coords));
+                    press.on_piece = true;
+                }
+            }
+
+            ui.data().insert_temp(response.id, press);
+        }
+
+        if !response.drag_released() {
+            return;
+        }
+
+        let press = ui.data().get_temp::<Press>(response.id).unwrap_or_default();
+        let from = self.state.ui_selected_square();
+
+        match (from, coords) {
+            // a drop, or the second click of click-to-move
+            (Some(from), Some(to)) if from != to && (press.on_piece || response.clicked()) => {
+                self.attempt_move(ui, from, to);
+            }
+            (Some(_), Some(_)) if press.on_selected && response.clicked() => {
                 self.state.ui_select_square(None);
             }
+            _ => {}
+        }
+    }
+
+    /// Gives each square a node that can be tabbed to, which tells screen
+    /// readers what's on it. Enter or space on a square does what clicking it
+    /// would, and the arrow keys move to the squares around it. Returns the
+    /// square with the focus, if one has it.
+    fn handle_square_focus(
+        &mut self,
+        ui: &egui::Ui,
+        rect: egui::Rect,
+        response: &egui::Response,
+    ) -> Option<Coords> {
+        let square_id = |coords: Coords| response.id.with(("square", coords));
+        let mut focused = None;
+        let mut activated = false;
+
+        // in the order they're drawn, so tabbing reads the board like a page
+        for row in 0..8 {
+            for column in 0..8 {
+                let coords = grid_coords((column, row), self.flipped);
+                let square = ui.interact(
+                    square_rect(rect, coords, self.flipped),
+                    square_id(coords),
+                    egui::Sense::focusable_noninteractive(),
+                );
+
+                let selected = self.state.ui_selected_square() == Some(coords);
+                square.widget_info(|| {
+                    let mut label = describe::square(&self.board, square_at(coords));
+                    if selected {
+                        label.push_str(", selected");
+                    }
+                    egui::WidgetInfo::labeled(egui::WidgetType::Other, label)
+                });
+
+                if !square.has_focus() {
+                    continue;
+                }
+                focused = Some(coords);
+
+                let pressed = |key| ui.input_mut().consume_key(egui::Modifiers::NONE, key);
+                activated = pressed(egui::Key::Enter) || pressed(egui::Key::Space);
+
+                let step = [
+                    (egui::Key::ArrowLeft, (-1, 0)),
+                    (egui::Key::ArrowRight, (1, 0)),
+                    (egui::Key::ArrowUp, (0, -1)),
+                    (egui::Key::ArrowDown, (0, 1)),
+                ]
+                .into_iter()
+                .find(|&(key, _)| pressed(key));
+                if let Some((_, (dx, dy))) = step {
+                    let column = (column as i32 + dx).clamp(0, 7) as usize;
+                    let row = (row as i32 + dy).clamp(0, 7) as usize;
+                    let next = grid_coords((column, row), self.flipped);
+                    ui.memory().request_focus(square_id(next));
+                }
+            }
+        }
+
+        let coords = focused.filter(|_| activated)?;
+        match self.state.ui_selected_square() {
+            Some(from) if from == coords => self.state.ui_select_square(None),
+            _ if self.state.ui_can_select(coords) => self.state.ui_select_square(Some(coords)),
+            Some(from) => self.attempt_move(ui, from, coords),
+            None => {}
+        }
+
+        // the square won't take the focus again to say so
+        if self.state.ui_selected_square() == Some(coords) {
+            let label = describe::square(&self.board, square_at(coords)) + ", selected";
+            ui.output()
+                .events
+                .push(egui::output::OutputEvent::ValueChanged(
+                    egui::WidgetInfo::labeled(egui::WidgetType::Other, label),
+                ));
         }
+
+        focused
+    }
+
+    /// Plays a move from `from` to `to`, asking what to promote to first if
+    /// it's a legal promotion.
+    fn attempt_move(&mut self, ui: &egui::Ui, from: Coords, to: Coords) {
+        if !attempting_promotion(&self.board, from, to) {
+            self.state.ui_attempt_move(Some(to), None);
+            return;
+        }
+
+        let promotion = chess::ChessMove::new(square_at(from), square_at(to), Some(Piece::Queen));
+        if self.board.legal(promotion) {
+            ui.memory().toggle_popup(promotion_popup_id(ui, to));
+        } else {
+            self.state.ui_attempt_move(Some(to), Some(Piece::Queen));
+        }
+    }
+}
+
+fn promotion_popup_id(ui: &egui::Ui, coords: Coords) -> egui::Id {
+    ui.make_persistent_id(format!("promotion-dialogue-{}-{}", coords.0, coords.1))
+}
+
+/// Where the square at `coords` is drawn, as `(column, row)` counted from
+/// the top left corner of the board.
+fn grid_position(coords: Coords, flipped: bool) -> (usize, usize) {
+    let (rank_idx, file_idx) = coords;
+    if flipped {
+        (7 - file_idx, rank_idx)
+    } else {
+        (file_idx, 7 - rank_idx)
     }
 }
 
+/// The area of the board `rect` taken up by the square at `coords`.
+fn square_rect(rect: egui::Rect, coords: Coords, flipped: bool) -> egui::Rect {
+    let size = rect.width() / 8.0;
+    let (column, row) = grid_position(coords, flipped);
+
+    let min = rect.min + egui::vec2(column as f32 * size, row as f32 * size);
+    egui::Rect::from_min_size(min, egui::vec2(size, size))
+}
+
+/// The square under `pos`, if it's on the board `rect`.
+fn coords_at(rect: egui::Rect, pos: egui::Pos2, flipped: bool) -> Option<Coords> {
+    if !rect.contains(pos) {
+        return None;
+    }
+
+    let size = rect.width() / 8.0;
+    let column = (((pos.x - rect.min.x) / size) as usize).min(7);
+    let row = (((pos.y - rect.min.y) / size) as usize).min(7);
+
+    Some(grid_coords((column, row), flipped))
+}
+
+/// The square drawn at `(column, row)`, the other way round from
+/// `grid_position`.
+fn grid_coords((column, row): (usize, usize), flipped: bool) -> Coords {
+    if flipped {
+        (row, 7 - column)
+    } else {
+        (7 - row, column)
+    }
+}
+
+/// An arrow from the middle of one square to the middle of another.
+fn paint_arrow(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    flipped: bool,
+    (from, to): (Coords, Coords),
+    color: egui::Color32,
+) {
+    let size = rect.width() / 8.0;
+    let start = square_rect(rect, from, flipped).center();
+    let tip = square_rect(rect, to, flipped).center();
+
+    let direction = (tip - start).normalized();
+    let across = direction.rot90() * size * 0.22;
+    let base = tip - direction * size * 0.4;
+
+    // the shaft stops where the head starts so a translucent arrow doesn't
+    // get darker where they'd overlap
+    painter.line_segment([start, base], egui::Stroke::new(size * 0.16, color));
+    painter.add(egui::Shape::convex_polygon(
+        vec![tip, base + across, base - across],
+        color,
+        egui::Stroke::none(),
+    ));
+}
+
+/// File letters along the bottom edge and rank numbers along the left edge,
+/// each in the color of the other kind of square so they show up on both.
+fn paint_coordinates(painter: &egui::Painter, rect: egui::Rect, flipped: bool, theme: &Theme) {
+    let size = rect.width() / 8.0;
+    let font = egui::FontId::proportional(size * 0.18);
+    let margin = size * 0.05;
+
+    for index in 0..8usize {
+        let (bottom, left) = if flipped {
+            ((7, 7 - index), (index, 7))
+        } else {
+            ((0, index), (index, 0))
+        };
+
+        let file_square = square_rect(rect, bottom, flipped);
+        painter.text(
+            file_square.right_bottom() + egui::vec2(-margin, -margin),
+            egui::Align2::RIGHT_BOTTOM,
+            (b'a' + bottom.1 as u8) as char,
+            font.clone(),
+            label_color(theme, bottom),
+        );
+
+        let rank_square = square_rect(rect, left, flipped);
+        painter.text(
+            rank_square.left_top() + egui::vec2(margin, margin),
+            egui::Align2::LEFT_TOP,
+            left.0 + 1,
+            font.clone(),
+            label_color(theme, left),
+        );
+    }
+}
+
+fn label_color(theme: &Theme, coords: Coords) -> egui::Color32 {
+    if (coords.0 + coords.1) % 2 == 1 {
+        theme.dark_square
+    } else {
+        theme.light_square
+    }
+}
+
+/// Draws a piece filling the square `rect`.
+fn paint_piece(
+    ui: &egui::Ui,
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    piece_set: PieceSet,
+    piece: Piece,
+    color: Color,
+) {
+    let texture = piece_set.texture(ui.ctx(), piece, color);
+    let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+
+    painter.add(egui::Shape::image(
+        texture.id(),
+        rect,
+        uv,
+        egui::Color32::WHITE,
+    ));
+}
+
 fn attempting_promotion(board: &Board, from: (usize, usize), to: (usize, usize)) -> bool {
     let rank = Rank::from_index(from.0);
     let file = File::from_index(from.1);
@@ -208,19 +778,41 @@ fn attempting_promotion(board: &Board, from: (usize, usize), to: (usize, usize))
     false
 }
 
-fn piece_symbol(piece: Piece, color: Color) -> &'static str {
-    match (piece, color) {
-        (Piece::King, Color::White) => "♔",
-        (Piece::Queen, Color::White) => "♕",
-        (Piece::Rook, Color::White) => "♖",
-        (Piece::Bishop, Color::White) => "♗",
-        (Piece::Knight, Color::White) => "♘",
-        (Piece::Pawn, Color::White) => "♙",
-        (Piece::King, Color::Black) => "♚",
-        (Piece::Queen, Color::Black) => "♛",
-        (Piece::Rook, Color::Black) => "♜",
-        (Piece::Bishop, Color::Black) => "♝",
-        (Piece::Knight, Color::Black) => "♞",
-        (Piece::Pawn, Color::Black) => "♟",
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_points_to_squares_and_back() {
+        let rect = egui::Rect::from_min_size(egui::pos2(10.0, 20.0), egui::vec2(800.0, 800.0));
+
+        assert_eq!(
+            coords_at(rect, egui::pos2(15.0, 815.0), false),
+            Some((0, 0))
+        );
+        assert_eq!(
+            coords_at(rect, egui::pos2(805.0, 25.0), false),
+            Some((7, 7))
+        );
+        assert_eq!(coords_at(rect, egui::pos2(5.0, 25.0), false), None);
+
+        for flipped in [false, true] {
+            for coords in [(0, 0), (3, 4), (7, 7), (1, 6)] {
+                assert_eq!(
+                    coords_at(rect, square_rect(rect, coords, flipped).center(), flipped),
+                    Some(coords)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn flipping_puts_black_at_the_bottom() {
+        let rect = egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(800.0, 800.0));
+
+        // h8 is in the bottom left corner, and a1 in the top right
+        assert_eq!(coords_at(rect, egui::pos2(5.0, 795.0), true), Some((7, 7)));
+        assert_eq!(coords_at(rect, egui::pos2(795.0, 5.0), true), Some((0, 0)));
+        assert_eq!(coords_at(rect, egui::pos2(150.0, 50.0), true), Some((0, 6)));
     }
 }
@@ -0,0 +1,110 @@
+use chess::{Color, Piece};
+use eframe::egui;
+use std::fmt;
+use std::str::FromStr;
+
+/// The bundled sets of piece images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PieceSet {
+    #[default]
+    Classic,
+    Wood,
+    Flat,
+}
+
+impl PieceSet {
+    pub const ALL: [PieceSet; 3] = [PieceSet::Classic, PieceSet::Wood, PieceSet::Flat];
+
+    fn svg(self, piece: Piece, color: Color) -> &'static [u8] {
+        macro_rules! image {
+            ($dir:literal, $file:literal) => {
+                include_bytes!(concat!("../../assets/pieces/", $dir, "/", $file, ".svg")) as &[u8]
+            };
+        }
+
+        macro_rules! set {
+            ($dir:literal) => {
+                match (color, piece) {
+                    (Color::White, Piece::Pawn) => image!($dir, "wP"),
+                    (Color::White, Piece::Knight) => image!($dir, "wN"),
+                    (Color::White, Piece::Bishop) => image!($dir, "wB"),
+                    (Color::White, Piece::Rook) => image!($dir, "wR"),
+                    (Color::White, Piece::Queen) => image!($dir, "wQ"),
+                    (Color::White, Piece::King) => image!($dir, "wK"),
+                    (Color::Black, Piece::Pawn) => image!($dir, "bP"),
+                    (Color::Black, Piece::Knight) => image!($dir, "bN"),
+                    (Color::Black, Piece::Bishop) => image!($dir, "bB"),
+                    (Color::Black, Piece::Rook) => image!($dir, "bR"),
+                    (Color::Black, Piece::Queen) => image!($dir, "bQ"),
+                    (Color::Black, Piece::King) => image!($dir, "bK"),
+                }
+            };
+        }
+
+        match self {
+            PieceSet::Classic => set!("classic"),
+            PieceSet::Wood => set!("wood"),
+            PieceSet::Flat => set!("flat"),
+        }
+    }
+
+    /// The texture for a piece, rasterized the first time it's asked for and
+    /// kept in the context's memory after that.
+    pub fn texture(self, ctx: &egui::Context, piece: Piece, color: Color) -> egui::TextureHandle {
+        let name = format!("{}-{:?}-{:?}", self, color, piece);
+        let id = egui::Id::new("piece-texture").with(&name);
+
+        if let Some(texture) = ctx.data().get_temp::<egui::TextureHandle>(id) {
+            return texture;
+        }
+
+        // the images are bundled, so a broken one is a bug rather than
+        // something to recover from
+        let image = egui_extras::image::load_svg_bytes(self.svg(piece, color))
+            .unwrap_or_else(|e| panic!("piece image {} is broken: {}", name, e));
+        let texture = ctx.load_texture(name, image, egui::TextureFilter::Linear);
+
+        ctx.data().insert_temp(id, texture.clone());
+        texture
+    }
+}
+
+impl fmt::Display for PieceSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PieceSet::Classic => "classic",
+            PieceSet::Wood => "wood",
+            PieceSet::Flat => "flat",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for PieceSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PieceSet::ALL
+            .into_iter()
+            .find(|set| set.to_string() == s)
+            .ok_or_else(|| format!("unknown piece set {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_piece_has_an_image() {
+        for set in PieceSet::ALL {
+            for color in [Color::White, Color::Black] {
+                for piece in chess::ALL_PIECES {
+                    let image = egui_extras::image::load_svg_bytes(set.svg(piece, color))
+                        .unwrap_or_else(|e| panic!("{} {:?} {:?}: {}", set, color, piece, e));
+                    assert_eq!(image.size, [256, 256]);
+                }
+            }
+        }
+    }
+}
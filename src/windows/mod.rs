@@ -1,34 +1,96 @@
+use chess::{BoardStatus, Color};
 use eframe::{egui, App};
 use egui::{menu, Ui};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::analysis::AnalysisLine;
 use crate::app_state::AppState;
-use crate::strategies::{alpha_beta, first_legal_move, random_move};
-use crate::widget::ChessBoard;
+use crate::clock::{format_duration, Clock, TimeControl};
+use crate::describe;
+use crate::engine_config::{EngineConfig, Strategy};
+use crate::evaluation::{Evaluator, Score};
+use crate::review::{ReviewReport, SCORE_CAP};
+use crate::widget::{ChessBoard, PieceSet};
 
-use crate::player::Player;
+use crate::notation;
+use crate::player::HumanPlayer;
+use crate::prompt;
+use crate::setup::Setup;
+
+/// Where the game in progress is kept in eframe's storage.
+pub const SESSION_KEY: &str = "session";
 
 pub struct PatzerApp {
     state: AppState,
+
+    /// Where to save settings on the way out, if anywhere.
+    settings_path: Option<PathBuf>,
+    window_size: Option<egui::Vec2>,
 }
 
 impl PatzerApp {
-    pub fn new(state: AppState) -> Self {
-        Self { state }
+    pub fn new(state: AppState, settings_path: Option<PathBuf>) -> Self {
+        Self {
+            state,
+            settings_path,
+            window_size: None,
+        }
     }
 }
 
 impl App for PatzerApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // repainting has to happen even if we're not interacting with the UI, since the computer
-        // might make a move. this could be made more efficient by triggering an explicit repaint
-        // *if* there has been a move on the board.
-        ctx.request_repaint_after(std::time::Duration::from_millis(10));
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.window_size = Some(frame.info().window_info.size);
+
+        let dark_mode = self.state.theme().dark_mode;
+        if ctx.style().visuals.dark_mode != dark_mode {
+            ctx.set_visuals(if dark_mode {
+                egui::Visuals::dark()
+            } else {
+                egui::Visuals::light()
+            });
+        }
+
+        // moves and results wake us up through the game controller's notifier,
+        // but a running clock needs redrawing as it ticks
+        if self.state.clock().running().is_some() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
+        // likewise for the analysis, which nothing else tells us about
+        if self.state.is_analysing() {
+            self.state.follow_analysis();
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+        if self.state.is_reviewing() {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
 
         display_main_window(ctx, &mut self.state);
     }
 
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let session = self.state.session().map(|s| s.to_string());
+        storage.set_string(SESSION_KEY, session.unwrap_or_default());
+    }
+
+    // the window size is saved along with the other settings
+    fn persist_native_window(&self) -> bool {
+        false
+    }
+
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Some(path) = &self.settings_path {
+            let mut settings = self.state.settings();
+            settings.window_size = self.window_size.map(|size| (size.x, size.y));
+            if let Err(e) = settings.save(path) {
+                eprintln!("Couldn't save settings to {}: {}", path.display(), e);
+            }
+        }
+
+        self.state.shutdown();
         println!("Goodbye! I hope you had fun! 👋");
     }
 }
@@ -42,33 +104,668 @@ fn display_main_window(ctx: &egui::Context, state: &mut AppState) {
         ui.heading(state.status_message());
     });
 
+    egui::SidePanel::right("move_list_panel").show(ctx, |ui| {
+        display_analysis(ui, state);
+        ui.separator();
+        if state.is_finished() {
+            display_review(ui, state);
+            ui.separator();
+        }
+        display_move_list(ui, state);
+    });
+
+    handle_shortcuts(ctx, state);
+
+    if let Some((color, config)) = display_engine_settings(ctx, !state.is_started()) {
+        let error = state
+            .configure_player(color, config)
+            .err()
+            .map(|e| format!("Could not start engine: {}", e));
+        engine_settings_applied(ctx, error);
+    }
+
     egui::CentralPanel::default().show(ctx, |ui| {
         let (white_name, black_name) = state.player_names();
-        let current_position = state.current_position();
+        let (white_info, black_info) = state.engine_info();
+        let white_config = state.player_config(Color::White);
+        let black_config = state.player_config(Color::Black);
+        let clock = state.clock();
+        let position = state.displayed_position();
         let selected_square = state.ui_selected_square();
+        let last_move = state.displayed_last_move();
+        let show_threats = state.show_threats();
+        let flipped = state.board_flipped();
+        let piece_set = state.piece_set();
+        let theme = state.theme();
+        let analysis = state.analysis();
+        // the analysis knows best, but a review knows what to play too
+        let best_move = match &analysis {
+            Some(analysis) => analysis.best_move,
+            None => state.review().and_then(|review| {
+                let eval = review.evals.get(state.displayed_ply())?;
+                eval.best_move
+            }),
+        };
+        let annotations = state.displayed_annotations();
+        let setup = state.setup();
         let inner_state = state.clone();
 
         egui::Frame::none().show(ui, |ui| {
             ui.group(|ui| {
-                ui.add(egui::Label::new(black_name));
-                ui.add(ChessBoard::new(
-                    current_position,
-                    selected_square,
-                    inner_state,
-                ));
-                ui.add(egui::Label::new(white_name));
+                // whoever is at the bottom of the board gets the bottom label
+                let white = (white_name, white_config, white_info, Color::White);
+                let black = (black_name, black_config, black_info, Color::Black);
+                let (top, bottom) = if flipped {
+                    (white, black)
+                } else {
+                    (black, white)
+                };
+
+                player_label(ui, top.0, top.1, &clock, top.3);
+                if let Some(info) = top.2 {
+                    ui.small(info);
+                }
+
+                // leave room under the board for the other player and the
+                // game controls
+                let reserved = 4.0 * ui.spacing().interact_size.y;
+                let board_space = ui.available_size() - egui::vec2(0.0, reserved);
+                ui.allocate_ui(board_space, |ui| {
+                    ui.horizontal(|ui| {
+                        let bar_width = if analysis.is_some() {
+                            EVAL_BAR_WIDTH
+                        } else {
+                            0.0
+                        };
+                        let board_space = ui.available_size() - egui::vec2(bar_width, 0.0);
+
+                        let board = ui
+                            .allocate_ui(board_space, |ui| {
+                                ui.add(
+                                    ChessBoard::new(position, selected_square, inner_state)
+                                        .last_move(last_move)
+                                        .threats(show_threats)
+                                        .flipped(flipped)
+                                        .pieces(piece_set)
+                                        .theme(theme.clone())
+                                        .annotations(annotations.clone())
+                                        .best_move(best_move)
+                                        .setup(setup.clone()),
+                                )
+                            })
+                            .inner;
+
+                        if let Some(analysis) = &analysis {
+                            eval_bar(ui, board.rect.height(), analysis, flipped);
+                        }
+                    });
+                });
+                player_label(ui, bottom.0, bottom.1, &clock, bottom.3);
+                if let Some(info) = bottom.2 {
+                    ui.small(info);
+                }
+            });
+        });
+
+        match setup {
+            Some(setup) => display_setup_controls(ui, state, &setup, piece_set),
+            None => display_game_controls(ui, state),
+        }
+    });
+
+    display_description(ctx);
+    announce_moves(ctx, state);
+}
+
+/// Has screen readers read `text` out. Only the last thing said in a frame
+/// gets read, so this should come after the widgets.
+fn say(ctx: &egui::Context, text: impl ToString) {
+    ctx.output()
+        .events
+        .push(egui::output::OutputEvent::ValueChanged(
+            egui::WidgetInfo::labeled(egui::WidgetType::Label, text),
+        ));
+}
+
+/// Reads out each move as it's played or stepped to, and how the game
+/// stands when that changes.
+fn announce_moves(ctx: &egui::Context, state: &AppState) {
+    if !ctx.options().screen_reader {
+        return;
+    }
+
+    let id = egui::Id::new("announced");
+    let now = (state.displayed_ply(), state.status_message());
+    let announced = ctx.data().get_temp::<(usize, &'static str)>(id);
+    ctx.data().insert_temp(id, now);
+
+    // nothing needs saying about the position things start in
+    let announced = match announced {
+        Some(announced) if announced != now => announced,
+        _ => return,
+    };
+
+    let mut words = vec![];
+    if announced.0 != now.0 {
+        words.push(
+            state
+                .spoken_last_move()
+                .unwrap_or_else(|| "Starting position".into()),
+        );
+    }
+    if announced.1 != now.1 {
+        words.push(now.1.to_string());
+    }
+    say(ctx, words.join(". "));
+}
+
+fn description_id() -> egui::Id {
+    egui::Id::new("position_description")
+}
+
+/// Lists the pieces on the board in a window, and reads them out.
+fn describe_position(ctx: &egui::Context, state: &AppState) {
+    let description = describe::position(&state.displayed_position());
+    say(ctx, &description);
+    ctx.data().insert_temp(description_id(), description);
+}
+
+fn display_description(ctx: &egui::Context) {
+    let id = description_id();
+    let description = match ctx.data().get_temp::<String>(id) {
+        Some(description) => description,
+        None => return,
+    };
+
+    let mut open = true;
+    egui::Window::new("Position")
+        .open(&mut open)
+        .collapsible(false)
+        .show(ctx, |ui| ui.label(&description));
+    if !open {
+        ctx.data().remove::<String>(id);
+    }
+}
+
+const EVAL_BAR_WIDTH: f32 = 24.0;
+
+/// A bar which fills up with White's color as White's position gets better,
+/// from the bottom if White is at the bottom of the board.
+fn eval_bar(ui: &mut Ui, height: f32, analysis: &AnalysisLine, flipped: bool) {
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(EVAL_BAR_WIDTH, height), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+
+    let white_height = rect.height() * analysis.white_share();
+    let white_rect = if flipped {
+        egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), white_height))
+    } else {
+        egui::Rect::from_min_max(egui::pos2(rect.min.x, rect.max.y - white_height), rect.max)
+    };
+
+    painter.rect_filled(rect, egui::Rounding::none(), egui::Color32::from_gray(40));
+    painter.rect_filled(
+        white_rect,
+        egui::Rounding::none(),
+        egui::Color32::from_gray(235),
+    );
+    response.on_hover_text(analysis.score_text());
+}
+
+/// What the background search makes of the position on the board, with a
+/// switch to start and stop it.
+fn display_analysis(ui: &mut Ui, state: &AppState) {
+    let mut analysing = state.is_analysing();
+    if ui.checkbox(&mut analysing, "Analysis").changed() {
+        state.set_analysing(analysing);
+    }
+
+    if !analysing {
+        return;
+    }
+
+    match state.analysis() {
+        Some(line) => {
+            ui.horizontal(|ui| {
+                ui.strong(line.score_text());
+                ui.label(format!("depth {}", line.depth));
+                ui.label(format!("{} kN/s", line.nodes_per_second / 1000));
             });
+            ui.add(egui::Label::new(line.pv.join(" ")).wrap(true));
+        }
+        None if state.displayed_position().status() != BoardStatus::Ongoing => {
+            ui.weak("There are no moves to look at");
+        }
+        None => {
+            ui.weak("Thinking…");
+        }
+    }
+}
+
+/// Going over the finished game with the engine: a graph of how the
+/// evaluation went, and the moves where it went wrong, which show the
+/// position before them with the engine's choice when clicked.
+fn display_review(ui: &mut Ui, state: &AppState) {
+    if ui
+        .add_enabled(!state.is_reviewing(), egui::Button::new("Review game"))
+        .clicked()
+    {
+        state.start_review();
+    }
+
+    let review = match state.review() {
+        Some(review) => review,
+        None => return,
+    };
+
+    if review.progress < 1.0 {
+        ui.add(egui::ProgressBar::new(review.progress).show_percentage());
+    }
+    eval_graph(ui, state, &review);
+
+    // if black moved first, the first move is black's half of move 1
+    let offset = usize::from(state.start_position().side_to_move() == Color::Black);
+    let errors: Vec<_> = review
+        .moves
+        .iter()
+        .filter(|m| m.judgement.is_error())
+        .collect();
+
+    if errors.is_empty() && review.progress >= 1.0 {
+        ui.weak("No mistakes found");
+    }
+
+    let viewed_ply = state.viewed_ply();
+    for m in errors {
+        let number = (m.ply - 1 + offset) / 2 + 1;
+        let dots = if m.color == Color::White { "." } else { "…" };
+        let mut text = format!(
+            "{}{} {}{} {}",
+            number,
+            dots,
+            m.san,
+            m.judgement.symbol(),
+            m.judgement
+        );
+        if let Some(best) = &m.best {
+            text.push_str(&format!(", best was {}", best));
+        }
+
+        let selected = viewed_ply == Some(m.ply - 1);
+        if ui
+            .selectable_label(selected, text)
+            .on_hover_text(format!("Loses {:.2} pawns", m.loss as f32 / 100.0))
+            .clicked()
+        {
+            state.view_ply(Some(m.ply - 1));
+        }
+    }
+}
+
+const EVAL_GRAPH_HEIGHT: f32 = 80.0;
+
+/// The evaluation after each ply, with White's advantage going up. Clicking
+/// it shows the position there.
+fn eval_graph(ui: &mut Ui, state: &AppState, review: &ReviewReport) {
+    let size = egui::vec2(ui.available_width(), EVAL_GRAPH_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+    let painter = ui.painter_at(rect);
+
+    let plies = state.move_list().len().max(1);
+    let x_of = |ply: usize| rect.left() + rect.width() * ply as f32 / plies as f32;
+    let y_of =
+        |score: Score| rect.center().y - rect.height() / 2.0 * score as f32 / SCORE_CAP as f32;
+
+    painter.rect_filled(rect, egui::Rounding::none(), egui::Color32::from_gray(40));
+    let middle = rect.center().y;
+    painter.line_segment(
+        [
+            egui::pos2(rect.left(), middle),
+            egui::pos2(rect.right(), middle),
+        ],
+        egui::Stroke::new(1.0, egui::Color32::from_gray(120)),
+    );
+
+    let points: Vec<egui::Pos2> = review
+        .evals
+        .iter()
+        .enumerate()
+        .map(|(ply, eval)| egui::pos2(x_of(ply), y_of(eval.score)))
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(2.0, egui::Color32::from_gray(235)),
+    ));
+
+    let x = x_of(state.displayed_ply());
+    painter.line_segment(
+        [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+        egui::Stroke::new(1.0, ui.visuals().selection.bg_fill),
+    );
+
+    if response.clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let ply = ((pos.x - rect.left()) / rect.width() * plies as f32).round();
+            state.view_ply(Some(ply.max(0.0) as usize));
+        }
+    }
+}
+
+/// The piece palette and the rest of what goes into a position besides the
+/// pieces, for setting one up.
+fn display_setup_controls(ui: &mut Ui, state: &AppState, setup: &Setup, piece_set: PieceSet) {
+    let chosen = state.setup_piece();
+    let size = egui::vec2(36.0, 36.0);
+
+    for color in [Color::White, Color::Black] {
+        ui.horizontal(|ui| {
+            for piece in chess::ALL_PIECES {
+                let texture = piece_set.texture(ui.ctx(), piece, color);
+                let button = egui::ImageButton::new(texture.id(), size)
+                    .selected(chosen == Some((piece, color)));
+                if ui.add(button).clicked() {
+                    state.set_setup_piece(Some((piece, color)));
+                }
+            }
+
+            if color == Color::White
+                && ui
+                    .selectable_label(chosen.is_none(), "remove")
+                    .on_hover_text("Right clicking also takes pieces off")
+                    .clicked()
+            {
+                state.set_setup_piece(None);
+            }
         });
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("To move:");
+        for color in [Color::White, Color::Black] {
+            if ui
+                .radio(setup.side_to_move == color, format!("{:?}", color))
+                .clicked()
+            {
+                state.edit_setup(|setup| setup.side_to_move = color);
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Castling:");
+        for (color, name) in [(Color::White, "White"), (Color::Black, "Black")] {
+            let (mut king_side, mut queen_side) = setup.castling[color.to_index()];
+            let changed = ui
+                .checkbox(&mut king_side, format!("{} O-O", name))
+                .changed()
+                | ui.checkbox(&mut queen_side, format!("{} O-O-O", name))
+                    .changed();
+            if changed {
+                state
+                    .edit_setup(|setup| setup.castling[color.to_index()] = (king_side, queen_side));
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("En passant:");
+        let file_name = |file: chess::File| ((b'a' + file.to_index() as u8) as char).to_string();
+        egui::ComboBox::from_id_source("en_passant")
+            .selected_text(setup.en_passant.map_or("none".to_string(), file_name))
+            .show_ui(ui, |ui| {
+                if ui
+                    .selectable_label(setup.en_passant.is_none(), "none")
+                    .clicked()
+                {
+                    state.edit_setup(|setup| setup.en_passant = None);
+                }
+                for file in chess::ALL_FILES {
+                    let selected = setup.en_passant == Some(file);
+                    if ui.selectable_label(selected, file_name(file)).clicked() {
+                        state.edit_setup(|setup| setup.en_passant = Some(file));
+                    }
+                }
+            });
+    });
 
-        if ui.add(egui::Button::new("resign")).clicked() && state.human_to_move() {
+    ui.horizontal(|ui| {
+        ui.label("Halfmove clock:");
+        let mut halfmove_clock = setup.halfmove_clock;
+        if ui
+            .add(egui::DragValue::new(&mut halfmove_clock).clamp_range(0..=150))
+            .on_hover_text("Moves by either side since the last capture or pawn move")
+            .changed()
+        {
+            state.edit_setup(|setup| setup.halfmove_clock = halfmove_clock);
+        }
+    });
+
+    ui.horizontal(|ui| {
+        if ui.button("Clear").clicked() {
+            state.edit_setup(|setup| *setup = Setup::empty());
+        }
+        if ui.button("Starting position").clicked() {
+            state.edit_setup(|setup| *setup = Setup::default());
+        }
+    });
+
+    let valid = setup.validate();
+    if let Err(problem) = &valid {
+        ui.colored_label(egui::Color32::RED, problem);
+    }
+
+    ui.horizontal(|ui| {
+        // a position which doesn't validate stays in setup mode, so there's
+        // nothing to do with the result here
+        if ui
+            .add_enabled(valid.is_ok(), egui::Button::new("Play"))
+            .clicked()
+        {
+            let _ = state.finish_setup(false);
+        }
+        if ui
+            .add_enabled(valid.is_ok(), egui::Button::new("Analyse"))
+            .clicked()
+        {
+            let _ = state.finish_setup(true);
+        }
+        if ui.button("Cancel").clicked() {
+            state.cancel_setup();
+        }
+    });
+}
+
+/// Keys for what's otherwise done with the mouse, while nothing's being
+/// typed: the arrows, home and end step through the game, F flips the board,
+/// S starts the game, ctrl+N starts a new one, ctrl+Z takes back a move and
+/// ctrl+Y (or ctrl+shift+Z) redoes it, and enter goes to the move field.
+fn handle_shortcuts(ctx: &egui::Context, state: &mut AppState) {
+    if ctx.wants_keyboard_input() {
+        return;
+    }
+
+    let pressed = |modifiers, key| ctx.input_mut().consume_key(modifiers, key);
+    let none = egui::Modifiers::NONE;
+    let command = egui::Modifiers::COMMAND;
+
+    if pressed(none, egui::Key::ArrowLeft) {
+        state.step_view(-1);
+    }
+    if pressed(none, egui::Key::ArrowRight) {
+        state.step_view(1);
+    }
+    if pressed(none, egui::Key::Home) {
+        state.view_ply(Some(0));
+    }
+    if pressed(none, egui::Key::End) {
+        state.view_ply(None);
+    }
+    if pressed(none, egui::Key::F) {
+        state.flip_board();
+    }
+    if pressed(none, egui::Key::D) {
+        describe_position(ctx, state);
+    }
+    if pressed(none, egui::Key::S) && !state.is_started() {
+        state.start_game();
+    }
+    if pressed(command, egui::Key::N) {
+        state.reset_game();
+    }
+    if pressed(command, egui::Key::Z) && state.can_take_back() {
+        state.take_back();
+    }
+    let redo =
+        pressed(command, egui::Key::Y) || pressed(command | egui::Modifiers::SHIFT, egui::Key::Z);
+    if redo && state.can_redo() {
+        state.redo();
+    }
+
+    // the enter that gets there mustn't submit the empty field
+    if pressed(none, egui::Key::Enter) {
+        ctx.memory().request_focus(move_entry_id());
+    }
+}
+
+fn move_entry_id() -> egui::Id {
+    egui::Id::new("move_entry")
+}
+
+/// How many legal moves to suggest under the move field.
+const MOVE_SUGGESTIONS: usize = 8;
+
+/// A field for typing moves in SAN or coordinate notation. Tab completes
+/// as much of the move as the legal moves have in common, the moves which
+/// fit are listed underneath, and enter plays it.
+fn move_entry(ui: &mut Ui, state: &AppState, enabled: bool) {
+    let id = move_entry_id();
+    let error_id = id.with("error");
+    let mut text = ui.data().get_temp::<String>(id).unwrap_or_default();
+    let mut error = ui.data().get_temp::<String>(error_id);
+    let board = state.displayed_position();
+
+    let response = ui
+        .horizontal(|ui| {
+            ui.label("Move:");
+            ui.add_enabled(
+                enabled,
+                egui::TextEdit::singleline(&mut text)
+                    .id(id)
+                    .lock_focus(true)
+                    .hint_text("Nf3 or g1f3 (enter)")
+                    .desired_width(120.0),
+            )
+        })
+        .inner;
+
+    // with the focus locked, tab stays in the field rather than moving on
+    if response.has_focus() && ui.input().key_pressed(egui::Key::Tab) {
+        text = notation::complete(&board, &text);
+        if let Some(mut edit_state) = egui::TextEdit::load_state(ui.ctx(), id) {
+            let end = egui::text::CCursor::new(text.chars().count());
+            edit_state.set_ccursor_range(Some(egui::text::CCursorRange::one(end)));
+            egui::TextEdit::store_state(ui.ctx(), id, edit_state);
+        }
+    }
+    if response.changed() {
+        error = None;
+    }
+
+    let mut play = None;
+    if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+        if !text.trim().is_empty() {
+            match prompt::input_move(&board, &text) {
+                Ok(m) => play = Some(m),
+                Err(e) => error = Some(e),
+            }
+        }
+        response.request_focus();
+    }
+
+    if enabled && !text.trim().is_empty() {
+        ui.horizontal_wrapped(|ui| {
+            let completions = notation::completions(&board, &text);
+            for (m, san) in completions.iter().take(MOVE_SUGGESTIONS) {
+                if ui.small_button(san).clicked() {
+                    play = Some(*m);
+                }
+            }
+            if completions.len() > MOVE_SUGGESTIONS {
+                ui.weak(format!("and {} more", completions.len() - MOVE_SUGGESTIONS));
+            }
+        });
+    }
+    if let Some(error) = &error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+
+    if let Some(m) = play.filter(|_| enabled) {
+        state.make_move(m);
+        text.clear();
+        error = None;
+    }
+
+    ui.data().insert_temp(id, text);
+    match error {
+        Some(error) => ui.data().insert_temp(error_id, error),
+        None => ui.data().remove::<String>(error_id),
+    }
+}
+
+/// Resigning, and offering, answering and claiming draws, for humans.
+fn display_game_controls(ui: &mut Ui, state: &AppState) {
+    let in_progress = state.is_started() && !state.is_finished();
+    let human = state.human_side().filter(|_| in_progress);
+    let human_to_move = human == Some(state.side_to_move());
+    let draw_offer = state.draw_offer();
+
+    move_entry(ui, state, human_to_move && state.viewed_ply().is_none());
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(human_to_move, egui::Button::new("resign"))
+            .clicked()
+        {
             state.resign(state.side_to_move());
         }
 
-        if ui.add(egui::Button::new("declare draw")).clicked() {
-            println!("attempting to declare draw");
-            state.declare_draw(state.side_to_move());
+        let can_offer = human.is_some() && draw_offer.is_none();
+        if ui
+            .add_enabled(can_offer, egui::Button::new("offer draw"))
+            .clicked()
+        {
+            if let Some(color) = human {
+                state.offer_draw(color);
+            }
+        }
+
+        let can_claim = human_to_move && state.can_claim_draw();
+        if ui
+            .add_enabled(can_claim, egui::Button::new("claim draw"))
+            .on_hover_text("Threefold repetition or fifty moves without a capture or pawn move")
+            .clicked()
+        {
+            state.claim_draw();
         }
     });
+
+    match draw_offer {
+        Some(color) if human_to_move && color != state.side_to_move() => {
+            ui.horizontal(|ui| {
+                ui.label(format!("{:?} offers a draw", color));
+                if ui.button("accept").clicked() {
+                    state.accept_draw();
+                }
+                if ui.button("decline").clicked() {
+                    state.decline_draw();
+                }
+            });
+        }
+        Some(color) => {
+            ui.label(format!("{:?} has offered a draw", color));
+        }
+        None => {}
+    }
 }
 
 fn display_menu(ui: &mut Ui, state: &mut AppState) {
@@ -81,52 +778,518 @@ fn display_menu(ui: &mut Ui, state: &mut AppState) {
             if ui.button("Start").clicked() {
                 state.start_game();
             }
+
+            let in_progress = state.is_started() && !state.is_finished();
+            if ui
+                .add_enabled(!in_progress, egui::Button::new("Set up position"))
+                .clicked()
+            {
+                state.start_setup();
+                ui.close_menu();
+            }
+
+            ui.menu_button("Time control", |ui| {
+                ui.set_enabled(!state.is_started());
+
+                let minutes = |m: u64| Duration::from_secs(m * 60);
+                let seconds = Duration::from_secs;
+                for time_control in [
+                    TimeControl::Unlimited,
+                    TimeControl::incremental(minutes(1), Duration::ZERO),
+                    TimeControl::incremental(minutes(3), seconds(2)),
+                    TimeControl::incremental(minutes(5), Duration::ZERO),
+                    TimeControl::incremental(minutes(15), seconds(10)),
+                    TimeControl::Level {
+                        moves: 40,
+                        base: minutes(90),
+                        increment: seconds(30),
+                    },
+                    TimeControl::MoveTime(seconds(10)),
+                ] {
+                    let selected = state.clock().time_control() == time_control;
+                    if ui.radio(selected, time_control.to_string()).clicked() {
+                        state.set_time_control(time_control);
+                    }
+                }
+            });
+
+            save_game_entry(ui, state);
         });
 
         ui.menu_button("Players", |ui| {
             ui.set_enabled(!state.is_started());
 
-            ui.menu_button("White", |ui| {
-                if ui.button("Human").clicked() {
-                    state.set_white_player(Player::Human("Human".to_string()));
-                } else if ui.button("Random move").clicked() {
-                    state.set_white_player(Player::Computer(
-                        "Random move".into(),
-                        Arc::new(Box::new(random_move)),
-                    ));
-                } else if ui.button("First legal move").clicked() {
-                    state.set_white_player(Player::Computer(
-                        "First legal move".into(),
-                        Arc::new(Box::new(first_legal_move)),
-                    ));
-                } else if ui.button("Negamax alpha-beta").clicked() {
-                    state.set_white_player(Player::Computer(
-                        "Negamax alpha-beta".into(),
-                        Arc::new(Box::new(|g| alpha_beta(&g.current_position(), 6))),
-                    ));
+            ui.menu_button("White", |ui| player_menu(ui, state, Color::White));
+            ui.menu_button("Black", |ui| player_menu(ui, state, Color::Black));
+        });
+
+        ui.menu_button("View", |ui| {
+            if ui.button("Flip board").clicked() {
+                state.flip_board();
+                ui.close_menu();
+            }
+
+            ui.menu_button("Pieces", |ui| {
+                for piece_set in PieceSet::ALL {
+                    let selected = state.piece_set() == piece_set;
+                    if ui.radio(selected, piece_set.to_string()).clicked() {
+                        state.set_piece_set(piece_set);
+                    }
                 }
             });
 
-            ui.menu_button("Black", |ui| {
-                if ui.button("Human").clicked() {
-                    state.set_black_player(Player::Human("Human".to_string()));
-                } else if ui.button("Random move").clicked() {
-                    state.set_black_player(Player::Computer(
-                        "Random move".into(),
-                        Arc::new(Box::new(random_move)),
-                    ));
-                } else if ui.button("First legal move").clicked() {
-                    state.set_black_player(Player::Computer(
-                        "First legal move".into(),
-                        Arc::new(Box::new(first_legal_move)),
-                    ));
-                } else if ui.button("Negamax alpha-beta").clicked() {
-                    state.set_black_player(Player::Computer(
-                        "Negamax alpha-beta".into(),
-                        Arc::new(Box::new(|g| alpha_beta(&g.current_position(), 6))),
-                    ));
+            ui.menu_button("Theme", |ui| theme_menu(ui, state));
+
+            let mut show_threats = state.show_threats();
+            if ui
+                .checkbox(&mut show_threats, "Threatened squares")
+                .changed()
+            {
+                state.set_show_threats(show_threats);
+            }
+
+            ui.separator();
+            if ui.button("Describe position").clicked() {
+                describe_position(ui.ctx(), state);
+                ui.close_menu();
+            }
+
+            // squares can be tabbed to, and moves are read out, while it's on
+            if cfg!(feature = "screen_reader") {
+                let mut screen_reader = ui.ctx().options().screen_reader;
+                if ui.checkbox(&mut screen_reader, "Screen reader").changed() {
+                    ui.ctx().options().screen_reader = screen_reader;
+                }
+            }
+        });
+    });
+}
+
+/// The built-in and user themes, and a way to pick up changes to the user's
+/// themes file.
+fn theme_menu(ui: &mut Ui, state: &AppState) {
+    let current = state.theme();
+    for theme in state.themes() {
+        let selected = theme.name == current.name;
+        if ui.radio(selected, &theme.name).clicked() {
+            state.set_theme(theme);
+        }
+    }
+
+    ui.separator();
+    let error_id = ui.id().with("themes_error");
+    if ui.button("Reload user themes").clicked() {
+        match state.load_user_themes() {
+            Ok(()) => {
+                ui.data().remove::<String>(error_id);
+            }
+            Err(e) => {
+                ui.data().insert_temp(error_id, e);
+            }
+        }
+    }
+
+    let error = ui.data().get_temp::<String>(error_id);
+    if let Some(error) = error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+}
+
+/// Choices of player for one side.
+fn player_menu(ui: &mut Ui, state: &AppState, color: Color) {
+    if ui.button("Human").clicked() {
+        let human = Arc::new(HumanPlayer::new("Human"));
+        match color {
+            Color::White => state.set_white_player(human),
+            Color::Black => state.set_black_player(human),
+        }
+    }
+
+    for strategy in [
+        Strategy::RandomMove,
+        Strategy::FirstLegalMove,
+        Strategy::AlphaBeta,
+    ] {
+        let config = EngineConfig::new(strategy);
+        if ui.button(config.name()).clicked() {
+            state
+                .configure_player(color, config)
+                .expect("built-in players don't fail to start");
+        }
+    }
+
+    if ui.button("Engine settings…").clicked() {
+        let config = state
+            .player_config(color)
+            .unwrap_or_else(|| EngineConfig::new(Strategy::AlphaBeta));
+        let dialog = EngineDialog {
+            color,
+            config,
+            error: None,
+        };
+        ui.data().insert_temp(engine_dialog_id(), Some(dialog));
+        ui.close_menu();
+    }
+
+    uci_engine_entry(ui, state, color);
+}
+
+/// The engine settings being edited for one side, before they're applied.
+#[derive(Clone)]
+struct EngineDialog {
+    color: Color,
+    config: EngineConfig,
+    error: Option<String>,
+}
+
+fn engine_dialog_id() -> egui::Id {
+    egui::Id::new("engine_settings")
+}
+
+/// A window for choosing everything about a computer player, returning the
+/// settings once they're applied. Players can't be changed mid-game, so
+/// nothing can be edited then.
+fn display_engine_settings(ctx: &egui::Context, enabled: bool) -> Option<(Color, EngineConfig)> {
+    let id = engine_dialog_id();
+    let mut dialog = ctx.data().get_temp::<Option<EngineDialog>>(id).flatten()?;
+    let mut open = true;
+    let mut apply = false;
+
+    egui::Window::new(format!("{:?} engine settings", dialog.color))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.set_enabled(enabled);
+            let config = &mut dialog.config;
+
+            egui::Grid::new("engine_settings_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Strategy");
+                    egui::ComboBox::from_id_source("strategy")
+                        .selected_text(strategy_name(config.strategy))
+                        .show_ui(ui, |ui| {
+                            for strategy in [
+                                Strategy::RandomMove,
+                                Strategy::FirstLegalMove,
+                                Strategy::AlphaBeta,
+                                Strategy::Uci,
+                            ] {
+                                ui.selectable_value(
+                                    &mut config.strategy,
+                                    strategy,
+                                    strategy_name(strategy),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    let searches = matches!(config.strategy, Strategy::AlphaBeta | Strategy::Uci);
+                    if config.strategy == Strategy::Uci {
+                        ui.label("Command");
+                        let mut command = config.command.clone().unwrap_or_default();
+                        ui.add(
+                            egui::TextEdit::singleline(&mut command).hint_text("path/to/engine"),
+                        );
+                        config.command = Some(command).filter(|c| !c.trim().is_empty());
+                        ui.end_row();
+                    }
+
+                    if searches {
+                        ui.label("Limit");
+                        ui.horizontal(|ui| {
+                            let mut timed = config.move_time.is_some();
+                            ui.radio_value(&mut timed, false, "depth");
+                            ui.radio_value(&mut timed, true, "time per move");
+
+                            if timed {
+                                let mut ms =
+                                    config.move_time.map_or(1000, |t| t.as_millis() as u64);
+                                ui.add(
+                                    egui::DragValue::new(&mut ms)
+                                        .clamp_range(10..=600_000)
+                                        .suffix(" ms"),
+                                );
+                                config.move_time = Some(Duration::from_millis(ms));
+                            } else {
+                                config.move_time = None;
+                                ui.add(egui::DragValue::new(&mut config.depth).clamp_range(1..=30));
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    if config.strategy == Strategy::AlphaBeta {
+                        ui.label("Evaluation");
+                        egui::ComboBox::from_id_source("evaluator")
+                            .selected_text(config.evaluator.to_string())
+                            .show_ui(ui, |ui| {
+                                for evaluator in Evaluator::ALL {
+                                    ui.selectable_value(
+                                        &mut config.evaluator,
+                                        evaluator,
+                                        evaluator.to_string(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                    } else {
+                        config.evaluator = Evaluator::default();
+                    }
+
+                    if searches {
+                        ui.label("Hash");
+                        ui.horizontal(|ui| {
+                            let mut custom = config.hash_mb.is_some();
+                            ui.checkbox(&mut custom, "");
+                            let mut mb = config.hash_mb.unwrap_or(64);
+                            ui.add_enabled(
+                                custom,
+                                egui::DragValue::new(&mut mb)
+                                    .clamp_range(1..=4096)
+                                    .suffix(" MB"),
+                            );
+                            config.hash_mb = custom.then_some(mb);
+                        });
+                        ui.end_row();
+
+                        ui.label("Threads");
+                        ui.add(egui::DragValue::new(&mut config.threads).clamp_range(1..=64));
+                        ui.end_row();
+
+                        ui.label("Opening book");
+                        ui.checkbox(&mut config.book, "");
+                        ui.end_row();
+
+                        ui.label("Ponder");
+                        ui.checkbox(&mut config.ponder, "");
+                        ui.end_row();
+                    } else {
+                        *config = EngineConfig::new(config.strategy);
+                    }
+
+                    if config.strategy != Strategy::Uci {
+                        config.command = None;
+                    }
+                });
+
+            ui.weak(config.to_string());
+
+            if let Some(error) = &dialog.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            let ready = config.strategy != Strategy::Uci || config.command.is_some();
+            if ui.add_enabled(ready, egui::Button::new("Apply")).clicked() {
+                apply = true;
+            }
+        });
+
+    let result = apply.then(|| (dialog.color, dialog.config.clone()));
+    ctx.data().insert_temp(id, Some(dialog).filter(|_| open));
+    result
+}
+
+/// Shows the result of applying engine settings in their dialog, closing it
+/// if the player started.
+fn engine_settings_applied(ctx: &egui::Context, error: Option<String>) {
+    let id = engine_dialog_id();
+    let mut data = ctx.data();
+    let dialog = data.get_temp::<Option<EngineDialog>>(id).flatten();
+
+    let dialog = match error {
+        Some(error) => dialog.map(|dialog| EngineDialog {
+            error: Some(error),
+            ..dialog
+        }),
+        None => None,
+    };
+    data.insert_temp(id, dialog);
+}
+
+fn strategy_name(strategy: Strategy) -> &'static str {
+    match strategy {
+        Strategy::RandomMove => "Random move",
+        Strategy::FirstLegalMove => "First legal move",
+        Strategy::AlphaBeta => "Negamax alpha-beta",
+        Strategy::Uci => "UCI engine",
+    }
+}
+
+/// The moves of the game so far, which can be clicked on to look at earlier
+/// positions, along with takeback controls.
+fn display_move_list(ui: &mut Ui, state: &AppState) {
+    let moves = state.move_list();
+    let viewed_ply = state.viewed_ply();
+
+    ui.horizontal(|ui| {
+        if ui.button("⏮").clicked() {
+            state.view_ply(Some(0));
+        }
+        if ui.button("◀").clicked() {
+            state.step_view(-1);
+        }
+        if ui.button("▶").clicked() {
+            state.step_view(1);
+        }
+        if ui.button("⏭").clicked() {
+            state.view_ply(None);
+        }
+    });
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(state.can_take_back(), egui::Button::new("Take back"))
+            .clicked()
+        {
+            state.take_back();
+        }
+        if ui
+            .add_enabled(state.can_redo(), egui::Button::new("Redo"))
+            .clicked()
+        {
+            state.redo();
+        }
+    });
+
+    if viewed_ply.is_some() {
+        ui.label("Viewing an earlier position");
+    }
+
+    ui.separator();
+
+    // if black moved first, white's half of the first row is left empty
+    let offset = usize::from(state.start_position().side_to_move() == Color::Black);
+    let rows = (moves.len() + offset).div_ceil(2);
+
+    egui::ScrollArea::vertical()
+        .stick_to_bottom(viewed_ply.is_none())
+        .show(ui, |ui| {
+            egui::Grid::new("move_list").num_columns(3).show(ui, |ui| {
+                for row in 0..rows {
+                    ui.label(format!("{}.", row + 1));
+
+                    for column in 0..2 {
+                        let index = (row * 2 + column).checked_sub(offset);
+                        match index.and_then(|i| moves.get(i).map(|san| (i + 1, san))) {
+                            Some((ply, san)) => {
+                                let selected = match viewed_ply {
+                                    Some(viewed) => viewed == ply,
+                                    None => ply == moves.len(),
+                                };
+                                if ui.selectable_label(selected, san).clicked() {
+                                    state.view_ply(Some(ply));
+                                }
+                            }
+                            None if index.is_none() => {
+                                ui.label("…");
+                            }
+                            None => {}
+                        }
+                    }
+
+                    ui.end_row();
                 }
             });
         });
+}
+
+/// A player's name and how they're configured, with their clock beside it if
+/// the game has one.
+fn player_label(
+    ui: &mut Ui,
+    name: String,
+    config: Option<EngineConfig>,
+    clock: &Clock,
+    color: Color,
+) {
+    ui.horizontal(|ui| {
+        ui.label(name);
+        if let Some(config) = config {
+            ui.weak(config.to_string());
+        }
+
+        if let Some(remaining) = clock.remaining(color) {
+            let text = egui::RichText::new(format_duration(remaining)).monospace();
+            if clock.running() == Some(color) {
+                ui.label(text.strong());
+            } else {
+                ui.label(text.weak());
+            }
+        }
+    });
+}
+
+/// A field for where to save the game, and a button to save it there as PGN.
+fn save_game_entry(ui: &mut Ui, state: &AppState) {
+    let id = ui.id().with("save_game");
+    let result_id = id.with("result");
+    let mut path = ui.data().get_temp::<String>(id).unwrap_or_default();
+    let result = ui.data().get_temp::<Result<String, String>>(result_id);
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut path).hint_text("path/to/game.pgn"));
+
+        if ui
+            .add_enabled(!path.trim().is_empty(), egui::Button::new("Save PGN"))
+            .clicked()
+        {
+            let result = match state.save_pgn(Path::new(path.trim())) {
+                Ok(()) => Ok(format!("Saved to {}", path.trim())),
+                Err(e) => Err(format!("Could not save the game: {}", e)),
+            };
+            ui.data().insert_temp(result_id, result);
+        }
     });
+
+    match result {
+        Some(Ok(message)) => {
+            ui.weak(message);
+        }
+        Some(Err(error)) => {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        None => {}
+    }
+
+    ui.data().insert_temp(id, path);
+}
+
+/// A field for the command which runs an external UCI engine, and a button
+/// to start it and have it play `color`.
+fn uci_engine_entry(ui: &mut Ui, state: &AppState, color: Color) {
+    let id = ui.id().with("uci").with(color.to_index());
+    let error_id = id.with("error");
+    let mut command = ui.data().get_temp::<String>(id).unwrap_or_default();
+    let error = ui.data().get_temp::<String>(error_id);
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut command).hint_text("path/to/engine"));
+
+        if ui.button("UCI engine").clicked() {
+            let config = EngineConfig {
+                command: Some(command.clone()),
+                ..EngineConfig::new(Strategy::Uci)
+            };
+
+            match state.configure_player(color, config) {
+                Ok(()) => {
+                    ui.data().remove::<String>(error_id);
+                }
+                Err(e) => {
+                    ui.data()
+                        .insert_temp(error_id, format!("Could not start engine: {}", e));
+                }
+            }
+        }
+    });
+
+    if let Some(error) = error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+
+    ui.data().insert_temp(id, command);
 }